@@ -0,0 +1,2 @@
+ALTER TABLE subscription_tokens
+  DROP COLUMN created_at;
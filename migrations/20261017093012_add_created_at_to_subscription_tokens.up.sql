@@ -0,0 +1,2 @@
+ALTER TABLE subscription_tokens
+  ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now();
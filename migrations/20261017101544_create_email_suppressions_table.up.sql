@@ -0,0 +1,9 @@
+-- Addresses are stored lowercase so a change of letter case cannot get
+-- around a suppression.
+CREATE TABLE email_suppressions(
+   email TEXT NOT NULL CHECK (email = LOWER(email)),
+   reason TEXT NOT NULL,
+   details TEXT NULL,
+   suppressed_at timestamptz NOT NULL DEFAULT now(),
+   PRIMARY KEY (email)
+);
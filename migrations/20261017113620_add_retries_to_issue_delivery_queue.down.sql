@@ -0,0 +1,5 @@
+DROP TABLE issue_delivery_failures;
+
+ALTER TABLE issue_delivery_queue
+  DROP COLUMN n_retries,
+  DROP COLUMN execute_after;
@@ -0,0 +1,12 @@
+ALTER TABLE issue_delivery_queue
+  ADD COLUMN n_retries INTEGER NOT NULL DEFAULT 0,
+  ADD COLUMN execute_after timestamptz NOT NULL DEFAULT now();
+
+CREATE TABLE issue_delivery_failures (
+   newsletter_issue_id uuid NOT NULL REFERENCES newsletter_issues (newsletter_issue_id),
+   subscriber_email TEXT NOT NULL,
+   n_retries INTEGER NOT NULL,
+   error TEXT NOT NULL,
+   failed_at timestamptz NOT NULL DEFAULT now(),
+   PRIMARY KEY(newsletter_issue_id, subscriber_email)
+);
@@ -0,0 +1,11 @@
+CREATE TABLE issue_delivery_log (
+   id BIGSERIAL PRIMARY KEY,
+   newsletter_issue_id uuid NOT NULL REFERENCES newsletter_issues (newsletter_issue_id),
+   subscriber_email TEXT NOT NULL,
+   outcome TEXT NOT NULL,
+   error TEXT NULL,
+   attempted_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX issue_delivery_log_newsletter_issue_id_outcome_idx
+  ON issue_delivery_log (newsletter_issue_id, outcome);
@@ -0,0 +1,2 @@
+ALTER TABLE newsletter_issues
+  DROP COLUMN scheduled_for;
@@ -0,0 +1,2 @@
+ALTER TABLE newsletter_issues
+  ADD COLUMN scheduled_for timestamptz NULL;
@@ -0,0 +1,3 @@
+ALTER TABLE issue_delivery_queue
+  DROP COLUMN locked_until,
+  DROP COLUMN locked_by;
@@ -0,0 +1,3 @@
+ALTER TABLE issue_delivery_queue
+  ADD COLUMN locked_until timestamptz NULL,
+  ADD COLUMN locked_by uuid NULL;
@@ -0,0 +1,11 @@
+CREATE TABLE delivery_author_quotas (
+   user_id uuid PRIMARY KEY REFERENCES users (user_id) ON DELETE CASCADE,
+   last_claimed_at timestamptz NULL,
+   window_started_at timestamptz NOT NULL DEFAULT now(),
+   claimed_in_window INTEGER NOT NULL DEFAULT 0
+);
+
+INSERT INTO delivery_author_quotas (user_id)
+SELECT DISTINCT n.user_id
+FROM issue_delivery_queue q
+JOIN newsletter_issues n ON n.newsletter_issue_id = q.newsletter_issue_id;
@@ -0,0 +1,2 @@
+ALTER TABLE newsletter_issues
+  DROP COLUMN delivery_paused_at;
@@ -0,0 +1,2 @@
+ALTER TABLE newsletter_issues
+  ADD COLUMN delivery_paused_at timestamptz NULL;
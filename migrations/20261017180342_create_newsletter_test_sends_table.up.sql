@@ -0,0 +1,10 @@
+CREATE TABLE newsletter_test_sends (
+   id BIGSERIAL PRIMARY KEY,
+   user_id uuid NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+   newsletter_issue_id uuid NOT NULL REFERENCES newsletter_issues (newsletter_issue_id) ON DELETE CASCADE,
+   n_recipients INT NOT NULL,
+   sent_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX newsletter_test_sends_user_id_sent_at_idx
+  ON newsletter_test_sends (user_id, sent_at);
@@ -0,0 +1,2 @@
+ALTER TABLE user_profiles
+  ADD COLUMN email_tracking_enabled BOOLEAN NOT NULL DEFAULT FALSE;
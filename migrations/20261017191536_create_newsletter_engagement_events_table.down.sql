@@ -0,0 +1 @@
+DROP TABLE newsletter_engagement_events;
@@ -0,0 +1,11 @@
+CREATE TABLE newsletter_engagement_events (
+   id BIGSERIAL PRIMARY KEY,
+   newsletter_issue_id uuid NOT NULL REFERENCES newsletter_issues (newsletter_issue_id) ON DELETE CASCADE,
+   subscriber_id uuid NOT NULL REFERENCES subscriptions (id) ON DELETE CASCADE,
+   kind TEXT NOT NULL,
+   url TEXT NULL,
+   occurred_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX newsletter_engagement_events_newsletter_issue_id_kind_idx
+  ON newsletter_engagement_events (newsletter_issue_id, kind);
@@ -0,0 +1,2 @@
+ALTER TABLE subscriptions
+  ADD COLUMN locale TEXT NOT NULL DEFAULT 'en';
@@ -0,0 +1,7 @@
+ALTER TABLE user_profiles
+  DROP COLUMN publication_name,
+  DROP COLUMN accent_color,
+  DROP COLUMN email_logo_url,
+  DROP COLUMN email_footer_text,
+  DROP COLUMN postal_address,
+  DROP COLUMN reply_to_email;
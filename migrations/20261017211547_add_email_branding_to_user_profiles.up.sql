@@ -0,0 +1,7 @@
+ALTER TABLE user_profiles
+  ADD COLUMN publication_name TEXT NOT NULL DEFAULT '',
+  ADD COLUMN accent_color TEXT NOT NULL DEFAULT '',
+  ADD COLUMN email_logo_url TEXT NOT NULL DEFAULT '',
+  ADD COLUMN email_footer_text TEXT NOT NULL DEFAULT '',
+  ADD COLUMN postal_address TEXT NOT NULL DEFAULT '',
+  ADD COLUMN reply_to_email TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,10 @@
+CREATE TABLE confirmation_email_queue(
+   subscription_token TEXT NOT NULL
+      REFERENCES subscription_tokens (subscription_token)
+      ON DELETE CASCADE,
+   n_retries INTEGER NOT NULL DEFAULT 0,
+   execute_after timestamptz NOT NULL DEFAULT now(),
+   locked_until timestamptz NULL,
+   locked_by uuid NULL,
+   PRIMARY KEY (subscription_token)
+);
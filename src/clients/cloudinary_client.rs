@@ -69,6 +69,24 @@ impl CloudinaryClient {
         Ok(result)
     }
 
+    /// Emails are read in clients that cannot display WebP, so logos are
+    /// converted to PNG instead.
+    pub async fn upload_email_logo(
+        &self,
+        file: String,
+        user_id: &Uuid,
+    ) -> Result<CloudinaryUploadResponse, actix_web::Error> {
+        let eager: String = "c_fit,h_120,w_600".into();
+        let public_id = format!("user/email_logo/{}", user_id);
+        let transformation: String = "f_png".into();
+        let result = self
+            .upload_image(file, public_id, eager, transformation)
+            .await
+            .map_err(e500)?;
+
+        Ok(result)
+    }
+
     pub async fn upload_newsletter_issue_cover_image(
         &self,
         file: String,
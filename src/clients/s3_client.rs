@@ -75,6 +75,22 @@ impl S3Client {
         Ok(response)
     }
 
+    pub async fn put_user_email_logo(
+        &self,
+        user_id: &Uuid,
+        content: web::Bytes,
+    ) -> Result<ResponseData, anyhow::Error> {
+        let path = format!("user/email_logo/{user_id}.png");
+        let response: ResponseData = self
+            .buckets
+            .images
+            .put_object_with_content_type(path, &content[..], "image/png")
+            .await
+            .context("Failed to store image.")?;
+
+        Ok(response)
+    }
+
     async fn initialize_buckets(
         region: String,
         endpoint: String,
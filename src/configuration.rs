@@ -1,12 +1,21 @@
 use crate::clients::cloudinary_client::CloudinaryClient;
 use crate::clients::s3_client::S3Client;
 use crate::domain::SubscriberEmail;
-use crate::email_client::{EmailClient, EmailServer, deserialize_email_server_from_string};
+use crate::email_client::{
+    EMAIL_TEMPLATES_GLOB, EmailClient, EmailServer, EmailTemplates, EmailTransport,
+    MailpitTransport, OutboxTransport, PostmarkTransport, SmtpTls, SmtpTransport,
+    deserialize_email_server_from_string, deserialize_smtp_tls_from_string,
+};
+use crate::rate_limiter::RateLimiter;
+use anyhow::Context;
 use secrecy::{ExposeSecret, SecretString};
 use serde::Deserialize;
-use serde_aux::field_attributes::deserialize_number_from_string;
+use serde_aux::field_attributes::{
+    deserialize_number_from_string, deserialize_option_number_from_string,
+};
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
 use std::convert::{TryFrom, TryInto};
+use std::num::{NonZeroU32, NonZeroUsize};
 
 #[derive(Deserialize, Clone)]
 pub struct Settings {
@@ -17,6 +26,7 @@ pub struct Settings {
     pub s3_client: S3ClientSettings,
     pub hosts: HostnameSettings,
     pub redis_uri: SecretString,
+    pub worker: WorkerSettings,
 }
 
 #[derive(Deserialize, Clone)]
@@ -105,24 +115,76 @@ impl DatabaseSettings {
 pub struct EmailClientSettings {
     pub base_url: String,
     pub sender_email: String,
+    /// Most emails the workers send per second, together. Also the largest
+    /// burst they may send at once.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub sends_per_second: u32,
+    /// Most emails the workers send per UTC day, together, if the provider
+    /// enforces a daily quota.
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub sends_per_day: Option<u32>,
     pub authorization_token: SecretString,
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub timeout_milliseconds: u64,
+    /// Directory emails are written to when `server` is `outbox`.
+    pub outbox_directory: String,
     #[serde(deserialize_with = "deserialize_email_server_from_string")]
     pub server: EmailServer,
+    /// Mail server to send through when `server` is `smtp`.
+    pub smtp: SmtpSettings,
+    /// Shared secret the email provider presents when calling our webhooks.
+    pub webhook_token: SecretString,
 }
 
 impl EmailClientSettings {
-    pub fn client(self) -> EmailClient {
-        let sender_email = self.sender().expect("Invalid sender email address.");
+    /// Fails if the transport cannot be set up, or if the email templates
+    /// cannot be loaded.
+    pub fn client(self) -> Result<EmailClient, anyhow::Error> {
+        let sender_email = self
+            .sender()
+            .map_err(anyhow::Error::msg)
+            .context("Invalid sender email address.")?;
         let timeout = self.timeout();
-        EmailClient::new(
-            self.base_url,
-            sender_email,
-            self.authorization_token,
-            timeout,
-            self.server,
-        )
+        let transport = match self.server {
+            EmailServer::Mailpit => {
+                EmailTransport::Mailpit(MailpitTransport::new(self.base_url, timeout))
+            }
+            EmailServer::Outbox => EmailTransport::Outbox(
+                OutboxTransport::new(&self.outbox_directory)
+                    .context("Failed to create the outbox directory.")?,
+            ),
+            EmailServer::Postmark => EmailTransport::Postmark(PostmarkTransport::new(
+                self.base_url,
+                self.authorization_token,
+                timeout,
+            )),
+            EmailServer::Smtp => EmailTransport::Smtp(
+                SmtpTransport::new(
+                    &self.smtp.host,
+                    self.smtp.port,
+                    self.smtp.tls,
+                    &self.smtp.username,
+                    &self.smtp.password,
+                    timeout,
+                )
+                .context("Invalid SMTP settings.")?,
+            ),
+        };
+        let templates = EmailTemplates::load(EMAIL_TEMPLATES_GLOB)
+            .context("Failed to load the email templates.")?;
+
+        Ok(EmailClient::new(sender_email, transport, templates))
+    }
+
+    /// Limiter enforcing the provider quotas, shared through Redis by every
+    /// worker of every replica sending through the same server.
+    pub fn rate_limiter(&self, redis_uri: &SecretString) -> Result<RateLimiter, anyhow::Error> {
+        Ok(RateLimiter::new(
+            redis_uri,
+            format!("email_rate_limit:{}", self.server.as_str()),
+            self.sends_per_second,
+            self.sends_per_day,
+        )?)
     }
 
     pub fn sender(&self) -> Result<SubscriberEmail, String> {
@@ -134,6 +196,55 @@ impl EmailClientSettings {
     }
 }
 
+#[derive(Deserialize, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    /// Leave empty when the server does not require authentication.
+    pub password: SecretString,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    #[serde(deserialize_with = "deserialize_smtp_tls_from_string")]
+    pub tls: SmtpTls,
+    pub username: String,
+}
+
+/// Tuning for the background loops that deliver and publish newsletter issues.
+#[derive(Deserialize, Clone)]
+pub struct WorkerSettings {
+    /// Number of delivery loops sharing the connection pool.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub concurrency: NonZeroUsize,
+    /// How long a loop sleeps after finding nothing to do. Delivery loops are
+    /// also woken by notifications, so this is only a fallback for them.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub poll_interval_milliseconds: u64,
+    /// How long a loop sleeps after an unexpected error.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub error_backoff_milliseconds: u64,
+    /// How long a delivery loop may work on a claimed batch before other
+    /// loops consider it abandoned and claim it again.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub lease_seconds: u64,
+    /// Most emails a single author may have claimed for sending per minute,
+    /// so that one large list cannot hold up everybody else's issues.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub author_emails_per_minute: NonZeroU32,
+}
+
+impl WorkerSettings {
+    pub fn poll_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.poll_interval_milliseconds)
+    }
+
+    pub fn error_backoff(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.error_backoff_milliseconds)
+    }
+
+    pub fn lease_duration(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.lease_seconds)
+    }
+}
+
 #[derive(Deserialize, Clone)]
 pub struct HostnameSettings {
     pub client: String,
@@ -0,0 +1,90 @@
+/// Languages transactional emails are written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Locale {
+    #[default]
+    En,
+    Fr,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::En, Locale::Fr];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Fr => "fr",
+        }
+    }
+
+    /// Matches a language tag on its primary subtag, so `fr-CA` is French.
+    pub fn parse(tag: &str) -> Option<Self> {
+        let language = tag.trim().split(['-', '_']).next()?;
+        Self::ALL
+            .into_iter()
+            .find(|locale| locale.as_str().eq_ignore_ascii_case(language))
+    }
+
+    /// The supported locale the reader prefers most according to an
+    /// `Accept-Language` header, or English if there is none.
+    pub fn from_accept_language(header: &str) -> Self {
+        let mut preferences: Vec<(&str, f32)> = header
+            .split(',')
+            .filter_map(|preference| {
+                let mut parts = preference.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|parameter| parameter.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |quality| quality.trim().parse().ok())?;
+                (quality > 0.0).then_some((tag, quality))
+            })
+            .collect();
+        // Stable, so tags of equal quality keep the order they were listed in.
+        preferences.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        preferences
+            .into_iter()
+            .find_map(|(tag, _)| Self::parse(tag))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::Locale;
+
+    #[test]
+    fn regional_variants_match_their_language() {
+        assert_eq!(Some(Locale::Fr), Locale::parse("fr-CA"));
+        assert_eq!(Some(Locale::En), Locale::parse("EN_gb"));
+        assert_eq!(None, Locale::parse("de"));
+    }
+
+    #[test]
+    fn the_preferred_supported_language_is_chosen() {
+        assert_eq!(
+            Locale::Fr,
+            Locale::from_accept_language("de-DE, fr;q=0.9, en;q=0.8")
+        );
+        assert_eq!(
+            Locale::En,
+            Locale::from_accept_language("fr;q=0.5, en-US;q=0.7")
+        );
+    }
+
+    #[test]
+    fn languages_of_equal_quality_keep_their_order() {
+        assert_eq!(Locale::Fr, Locale::from_accept_language("fr, en"));
+    }
+
+    #[test]
+    fn refused_languages_are_skipped() {
+        assert_eq!(Locale::En, Locale::from_accept_language("fr;q=0, en;q=0.1"));
+    }
+
+    #[test]
+    fn english_is_the_fallback() {
+        assert_eq!(Locale::En, Locale::from_accept_language(""));
+        assert_eq!(Locale::En, Locale::from_accept_language("*"));
+        assert_eq!(Locale::En, Locale::from_accept_language("de, ja;q=nope"));
+    }
+}
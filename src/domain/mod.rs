@@ -1,7 +1,10 @@
 mod base64_image_url;
 mod image_url;
+mod locale;
 mod subscriber_email;
 mod subscriber_name;
+mod tracking_token;
+mod unsubscribe_token;
 
 pub mod newsletter_issue;
 pub mod user;
@@ -9,5 +12,8 @@ pub mod user_profile;
 
 pub use base64_image_url::Base64ImageUrl;
 pub use image_url::ImageUrl;
+pub use locale::Locale;
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
+pub use tracking_token::{LinkSignature, TrackingToken};
+pub use unsubscribe_token::UnsubscribeToken;
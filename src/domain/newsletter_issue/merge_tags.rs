@@ -0,0 +1,248 @@
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+use voca_rs::escape;
+
+/// Anything between double braces is meant as a merge tag.
+static MERGE_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{\{(.*?)\}\}").unwrap());
+/// `field` or `field | default: "fallback"`.
+static MERGE_TAG_BODY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^\s*([\w.]+)\s*(?:\|\s*default:\s*"([^"]*)"\s*)?$"#).unwrap());
+/// Stand-in left where a merge tag was. Letters and digits only, so that
+/// neither markdown, HTML escaping nor tag stripping alter it.
+static PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"MERGETAG(\d+)MERGETAG").unwrap());
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MergeTagField {
+    SubscriberEmail,
+    SubscriberName,
+    /// Filled in with the recipient's [`crate::domain::TrackingToken`]. Only
+    /// ever inserted by the delivery worker, never written by authors.
+    TrackingToken,
+    UnsubscribeUrl,
+}
+
+impl MergeTagField {
+    fn parse(s: &str) -> Result<MergeTagField, String> {
+        match s {
+            "subscriber.email" => Ok(Self::SubscriberEmail),
+            "subscriber.name" => Ok(Self::SubscriberName),
+            "unsubscribe_url" => Ok(Self::UnsubscribeUrl),
+            _ => Err(format!("{{{{ {} }}}} is not a known merge tag.", s)),
+        }
+    }
+}
+
+/// A `{{ field }}` in the content or subject of an issue, resolved for each
+/// recipient when the issue is delivered.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MergeTag {
+    pub field: MergeTagField,
+    /// Used in place of a missing or empty value.
+    pub fallback: String,
+}
+
+impl MergeTag {
+    /// Parses what sits between the braces of a merge tag.
+    pub fn parse(s: &str) -> Result<MergeTag, String> {
+        let captures = MERGE_TAG_BODY
+            .captures(s)
+            .ok_or_else(|| format!("{{{{{}}}}} is not a valid merge tag.", s))?;
+
+        Ok(Self {
+            field: MergeTagField::parse(&captures[1])?,
+            fallback: captures
+                .get(2)
+                .map(|fallback| fallback.as_str().to_string())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// What a recipient's merge tags resolve to.
+pub struct MergeTagValues<'a> {
+    pub subscriber_email: &'a str,
+    pub subscriber_name: Option<&'a str>,
+    pub tracking_token: Option<&'a str>,
+    pub unsubscribe_url: Option<&'a str>,
+}
+
+impl MergeTagValues<'_> {
+    fn get(&self, tag: &MergeTag) -> String {
+        let value = match tag.field {
+            MergeTagField::SubscriberEmail => Some(self.subscriber_email),
+            MergeTagField::SubscriberName => self.subscriber_name,
+            MergeTagField::TrackingToken => self.tracking_token,
+            MergeTagField::UnsubscribeUrl => self.unsubscribe_url,
+        };
+        match value {
+            Some(value) if !value.trim().is_empty() => value.to_string(),
+            _ => tag.fallback.clone(),
+        }
+    }
+}
+
+/// The merge tags taken out of an issue, in order of appearance.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MergeTags(Vec<MergeTag>);
+
+impl MergeTags {
+    /// Rejects any merge tag that is malformed or unknown.
+    pub fn validate(s: &str) -> Result<(), String> {
+        MERGE_TAG
+            .captures_iter(s)
+            .try_for_each(|captures| MergeTag::parse(&captures[1]).map(|_| ()))
+    }
+
+    /// Removes every merge tag, so that the rest of `s` can be checked on its
+    /// own.
+    pub fn strip(s: &str) -> String {
+        MERGE_TAG.replace_all(s, "").into_owned()
+    }
+
+    /// Swaps the valid merge tags of `s` for placeholders, to be resolved with
+    /// [`MergeTags::resolve`] once the content has been rendered. Invalid ones
+    /// are left as they are.
+    pub fn extract(&mut self, s: &str) -> String {
+        MERGE_TAG
+            .replace_all(s, |captures: &Captures| {
+                match MergeTag::parse(&captures[1]) {
+                    Ok(tag) => self.push(tag),
+                    Err(_) => captures[0].to_string(),
+                }
+            })
+            .into_owned()
+    }
+
+    /// Adds a merge tag that is not part of the content, returning the
+    /// placeholder to use in its place.
+    pub fn insert(&mut self, field: MergeTagField) -> String {
+        self.push(MergeTag {
+            field,
+            fallback: String::new(),
+        })
+    }
+
+    fn push(&mut self, tag: MergeTag) -> String {
+        self.0.push(tag);
+        format!("MERGETAG{}MERGETAG", self.0.len() - 1)
+    }
+
+    /// Whether `s` still holds placeholders, i.e. varies from one recipient
+    /// to the next.
+    pub fn has_placeholders(s: &str) -> bool {
+        PLACEHOLDER.is_match(s)
+    }
+
+    /// Replaces the placeholders left by [`MergeTags::extract`], escaping the
+    /// values when `s` is HTML.
+    pub fn resolve(&self, s: &str, values: &MergeTagValues, is_html: bool) -> String {
+        if self.0.is_empty() {
+            return s.to_string();
+        }
+        PLACEHOLDER
+            .replace_all(s, |captures: &Captures| {
+                let tag = captures[1]
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| self.0.get(index));
+                match tag {
+                    Some(tag) if is_html => escape::escape_html(&values.get(tag)),
+                    Some(tag) => values.get(tag),
+                    None => captures[0].to_string(),
+                }
+            })
+            .into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::newsletter_issue::{MergeTag, MergeTagField, MergeTagValues, MergeTags};
+    use claims::{assert_err, assert_ok};
+
+    fn values() -> MergeTagValues<'static> {
+        MergeTagValues {
+            subscriber_email: "ursula@example.com",
+            subscriber_name: Some("Ursula <3"),
+            tracking_token: None,
+            unsubscribe_url: Some("https://example.com/unsubscribe?token=abc&list=1"),
+        }
+    }
+
+    #[test]
+    fn known_merge_tags_are_valid() {
+        assert_ok!(MergeTags::validate(
+            "Hi {{subscriber.name}}, {{ subscriber.email }} {{ unsubscribe_url }}"
+        ));
+    }
+
+    #[test]
+    fn unknown_merge_tags_are_rejected() {
+        assert_err!(MergeTags::validate("Hi {{ subscriber.age }}"));
+    }
+
+    #[test]
+    fn malformed_merge_tags_are_rejected() {
+        assert_err!(MergeTags::validate("Hi {{ subscriber.name | upcase }}"));
+        assert_err!(MergeTags::validate("Hi {{}}"));
+    }
+
+    #[test]
+    fn a_fallback_can_be_given() {
+        let tag = MergeTag::parse(r#" subscriber.name | default: "reader" "#).unwrap();
+
+        assert_eq!(tag.field, MergeTagField::SubscriberName);
+        assert_eq!(tag.fallback, "reader");
+    }
+
+    #[test]
+    fn merge_tags_are_resolved_after_extraction() {
+        let mut tags = MergeTags::default();
+        let extracted = tags.extract("Hi {{ subscriber.name }} ({{subscriber.email}})");
+
+        assert!(!extracted.contains("{{"));
+        assert_eq!(
+            tags.resolve(&extracted, &values(), false),
+            "Hi Ursula <3 (ursula@example.com)"
+        );
+    }
+
+    #[test]
+    fn values_are_escaped_in_html() {
+        let mut tags = MergeTags::default();
+        let extracted =
+            tags.extract(r#"<a href="{{ unsubscribe_url }}">{{ subscriber.name }}</a>"#);
+
+        assert_eq!(
+            tags.resolve(&extracted, &values(), true),
+            r#"<a href="https://example.com/unsubscribe?token=abc&amp;list=1">Ursula &lt;3</a>"#
+        );
+    }
+
+    #[test]
+    fn missing_values_fall_back_to_the_default() {
+        let mut tags = MergeTags::default();
+        let extracted =
+            tags.extract(r#"Hi {{ subscriber.name | default: "reader" }}{{ unsubscribe_url }}!"#);
+        let values = MergeTagValues {
+            subscriber_email: "ursula@example.com",
+            subscriber_name: Some(" "),
+            tracking_token: None,
+            unsubscribe_url: None,
+        };
+
+        assert_eq!(tags.resolve(&extracted, &values, false), "Hi reader!");
+    }
+
+    #[test]
+    fn invalid_merge_tags_are_left_as_they_are() {
+        let mut tags = MergeTags::default();
+
+        assert_eq!(
+            tags.extract("Hi {{ subscriber.age }}"),
+            "Hi {{ subscriber.age }}"
+        );
+    }
+}
@@ -1,7 +1,11 @@
 mod content;
 mod description;
+mod merge_tags;
+mod plain_text;
 mod title;
 
 pub use content::*;
 pub use description::*;
+pub use merge_tags::*;
+pub use plain_text::*;
 pub use title::*;
@@ -0,0 +1,369 @@
+use markdown::mdast::Node;
+use markdown::{ParseOptions, to_mdast};
+use std::collections::HashMap;
+use unicode_segmentation::UnicodeSegmentation;
+use voca_rs::strip;
+
+/// Lines are wrapped at this many characters, which text-only clients display
+/// comfortably.
+pub const PLAIN_TEXT_WIDTH: usize = 72;
+
+/// Nested blocks never get narrower than this, however deep they are.
+const MIN_WIDTH: usize = 20;
+
+/// Renders markdown as plain text for readers whose email client does not
+/// display HTML. Links become numbered references listed at the end, and
+/// paragraphs are wrapped at [`PLAIN_TEXT_WIDTH`].
+pub fn markdown_to_plain_text(content: &str) -> String {
+    // Plain markdown always parses; only MDX can fail.
+    let Ok(root) = to_mdast(content, &ParseOptions::default()) else {
+        return content.to_string();
+    };
+    let mut renderer = PlainTextRenderer::default();
+    renderer.collect_definitions(&root);
+    let mut text = renderer.blocks(children(&root), PLAIN_TEXT_WIDTH, false);
+    if !renderer.references.is_empty() {
+        text.push(String::new());
+        text.push(String::from("Links:"));
+        for (i, url) in renderer.references.iter().enumerate() {
+            text.push(format!("[{}] {}", i + 1, url));
+        }
+    }
+
+    text.join("\n")
+}
+
+#[derive(Default)]
+struct PlainTextRenderer {
+    /// Destinations of reference-style links, by identifier.
+    definitions: HashMap<String, String>,
+    /// Link destinations, numbered in the order they are first met.
+    references: Vec<String>,
+}
+
+impl PlainTextRenderer {
+    fn collect_definitions(&mut self, node: &Node) {
+        if let Node::Definition(definition) = node {
+            self.definitions
+                .insert(definition.identifier.clone(), definition.url.clone());
+        }
+        for child in children(node) {
+            self.collect_definitions(child);
+        }
+    }
+
+    /// The number a link to `url` is referred to by.
+    fn reference(&mut self, url: &str) -> usize {
+        match self.references.iter().position(|known| known == url) {
+            Some(i) => i + 1,
+            None => {
+                self.references.push(url.to_string());
+                self.references.len()
+            }
+        }
+    }
+
+    /// Renders sibling blocks, separated by a blank line unless `tight`.
+    fn blocks(&mut self, nodes: &[Node], width: usize, tight: bool) -> Vec<String> {
+        let mut lines = vec![];
+        for node in nodes {
+            let block = self.block(node, width);
+            if block.is_empty() {
+                continue;
+            }
+            if !lines.is_empty() && !tight {
+                lines.push(String::new());
+            }
+            lines.extend(block);
+        }
+        lines
+    }
+
+    fn block(&mut self, node: &Node, width: usize) -> Vec<String> {
+        match node {
+            Node::Paragraph(paragraph) => wrap(&self.inline(&paragraph.children), width),
+            Node::Heading(heading) => {
+                let text = self.inline(&heading.children);
+                match heading.depth {
+                    1 | 2 => {
+                        let mut lines = wrap(&text, width);
+                        let underline = if heading.depth == 1 { "=" } else { "-" };
+                        let length = lines.iter().map(|line| len(line)).max().unwrap_or(0);
+                        lines.push(underline.repeat(length));
+                        lines
+                    }
+                    depth => wrap(&format!("{} {}", "#".repeat(depth.into()), text), width),
+                }
+            }
+            Node::Blockquote(blockquote) => {
+                let width = width.saturating_sub(2).max(MIN_WIDTH);
+                self.blocks(&blockquote.children, width, false)
+                    .into_iter()
+                    .map(|line| format!("> {line}").trim_end().to_string())
+                    .collect()
+            }
+            Node::List(list) => {
+                let start = list.start.unwrap_or(1);
+                let mut lines = vec![];
+                for (i, item) in list.children.iter().enumerate() {
+                    let Node::ListItem(item) = item else {
+                        continue;
+                    };
+                    let mut marker = if list.ordered {
+                        format!("{}. ", start as usize + i)
+                    } else {
+                        String::from("- ")
+                    };
+                    match item.checked {
+                        Some(true) => marker.push_str("[x] "),
+                        Some(false) => marker.push_str("[ ] "),
+                        None => {}
+                    }
+                    let indent = " ".repeat(len(&marker));
+                    let width = width.saturating_sub(indent.len()).max(MIN_WIDTH);
+                    let content = self.blocks(&item.children, width, !item.spread);
+                    if list.spread && !lines.is_empty() {
+                        lines.push(String::new());
+                    }
+                    for (j, line) in content.into_iter().enumerate() {
+                        let prefix = if j == 0 { &marker } else { &indent };
+                        lines.push(format!("{prefix}{line}").trim_end().to_string());
+                    }
+                }
+                lines
+            }
+            // Code keeps its line breaks and is never wrapped.
+            Node::Code(code) => code
+                .value
+                .lines()
+                .map(|line| format!("    {line}").trim_end().to_string())
+                .collect(),
+            Node::ThematicBreak(_) => vec!["-".repeat(width.min(PLAIN_TEXT_WIDTH))],
+            Node::Html(html) => wrap(strip::strip_tags(&html.value).trim(), width),
+            Node::Definition(_) => vec![],
+            node => wrap(&self.inline(std::slice::from_ref(node)), width),
+        }
+    }
+
+    /// Renders phrasing content on a single line, save for hard breaks.
+    fn inline(&mut self, nodes: &[Node]) -> String {
+        let mut text = String::new();
+        for node in nodes {
+            match node {
+                // Soft line breaks are left to the wrapping.
+                Node::Text(t) => text.push_str(&t.value.replace('\n', " ")),
+                Node::Emphasis(emphasis) => {
+                    text.push_str(&format!("_{}_", self.inline(&emphasis.children)))
+                }
+                Node::Strong(strong) => {
+                    text.push_str(&format!("*{}*", self.inline(&strong.children)))
+                }
+                Node::InlineCode(code) => {
+                    text.push_str(&format!("`{}`", code.value.replace('\n', " ")))
+                }
+                Node::Break(_) => text.push('\n'),
+                Node::Html(html) => text.push_str(&strip::strip_tags(&html.value)),
+                Node::Link(link) => {
+                    let label = self.inline(&link.children);
+                    text.push_str(&self.link(label, &link.url));
+                }
+                Node::LinkReference(link) => {
+                    let label = self.inline(&link.children);
+                    match self.definitions.get(&link.identifier).cloned() {
+                        Some(url) => text.push_str(&self.link(label, &url)),
+                        None => text.push_str(&label),
+                    }
+                }
+                Node::Image(image) => {
+                    let label = image.alt.clone();
+                    text.push_str(&self.link(label, &image.url));
+                }
+                Node::ImageReference(image) => {
+                    let label = image.alt.clone();
+                    match self.definitions.get(&image.identifier).cloned() {
+                        Some(url) => text.push_str(&self.link(label, &url)),
+                        None => text.push_str(&label),
+                    }
+                }
+                node => match node.children() {
+                    Some(children) => text.push_str(&self.inline(children)),
+                    None => text.push_str(&node.to_string()),
+                },
+            }
+        }
+        text
+    }
+
+    /// A link whose label is its own destination, as autolinks are, needs no
+    /// reference.
+    fn link(&mut self, label: String, url: &str) -> String {
+        let label = label.trim();
+        if label == url || Some(label) == url.strip_prefix("mailto:") {
+            return label.to_string();
+        }
+        let number = self.reference(url);
+        if label.is_empty() {
+            format!("[{number}]")
+        } else {
+            format!("{label} [{number}]")
+        }
+    }
+}
+
+fn children(node: &Node) -> &[Node] {
+    node.children().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn len(s: &str) -> usize {
+    s.graphemes(true).count()
+}
+
+/// Wraps each line of `text` at `width`, between words. A word longer than
+/// the width, such as a URL, gets a line of its own rather than being cut.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    if text.trim().is_empty() {
+        return lines;
+    }
+    for paragraph_line in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph_line.split_whitespace() {
+            if !line.is_empty() && len(&line) + 1 + len(word) > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::newsletter_issue::{PLAIN_TEXT_WIDTH, markdown_to_plain_text};
+
+    #[test]
+    fn links_become_numbered_references() {
+        let text = markdown_to_plain_text(
+            "Read [the post](https://example.com/post) and [the\nother](https://example.com/other), \
+             or [the post](https://example.com/post) again.",
+        );
+
+        assert_eq!(
+            "Read the post [1] and the other [2], or the post [1] again.\n\
+             \n\
+             Links:\n\
+             [1] https://example.com/post\n\
+             [2] https://example.com/other",
+            text
+        );
+    }
+
+    #[test]
+    fn reference_style_links_and_images_are_resolved() {
+        let text = markdown_to_plain_text(
+            "See [Earthsea][earthsea] and ![a map][map].\n\n\
+             [earthsea]: https://example.com/earthsea\n\
+             [map]: https://example.com/map.png",
+        );
+
+        assert_eq!(
+            "See Earthsea [1] and a map [2].\n\
+             \n\
+             Links:\n\
+             [1] https://example.com/earthsea\n\
+             [2] https://example.com/map.png",
+            text
+        );
+    }
+
+    #[test]
+    fn autolinks_are_not_repeated_as_references() {
+        let text =
+            markdown_to_plain_text("Visit <https://example.com> or write <ursula@example.com>.");
+
+        assert_eq!(
+            "Visit https://example.com or write ursula@example.com.",
+            text
+        );
+    }
+
+    #[test]
+    fn headings_stand_out() {
+        let text = markdown_to_plain_text("# Title\n\n## Section\n\n### Subsection");
+
+        assert_eq!("Title\n=====\n\nSection\n-------\n\n### Subsection", text);
+    }
+
+    #[test]
+    fn lists_keep_their_markers_and_nesting() {
+        let text =
+            markdown_to_plain_text("- one\n- two\n  1. first\n  2. second\n\n3. three\n4. four");
+
+        assert_eq!(
+            "- one\n- two\n  1. first\n  2. second\n\n3. three\n4. four",
+            text
+        );
+    }
+
+    #[test]
+    fn block_quotes_are_prefixed() {
+        let text =
+            markdown_to_plain_text("> Only in silence the word,\n>\n> only in dark the light.");
+
+        assert_eq!(
+            "> Only in silence the word,\n>\n> only in dark the light.",
+            text
+        );
+    }
+
+    #[test]
+    fn code_blocks_are_indented_and_left_unwrapped() {
+        let line = format!("let x = \"{}\";", "a".repeat(PLAIN_TEXT_WIDTH));
+        let text = markdown_to_plain_text(&format!("```rust\n{line}\n\n  indented\n```"));
+
+        assert_eq!(format!("    {line}\n\n      indented"), text);
+    }
+
+    #[test]
+    fn emphasis_and_inline_code_are_marked() {
+        let text = markdown_to_plain_text("Some _emphasis_, **strength** and `code`.");
+
+        assert_eq!("Some _emphasis_, *strength* and `code`.", text);
+    }
+
+    #[test]
+    fn paragraphs_are_wrapped_between_words() {
+        let text = markdown_to_plain_text(&"word ".repeat(40));
+
+        let lines: Vec<_> = text.lines().collect();
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|line| line.len() <= PLAIN_TEXT_WIDTH));
+        assert_eq!("word ".repeat(40).trim(), lines.join(" "));
+    }
+
+    #[test]
+    fn words_longer_than_a_line_are_not_cut() {
+        let url = format!("https://example.com/{}", "a".repeat(PLAIN_TEXT_WIDTH));
+        let text = markdown_to_plain_text(&format!("Go to {url} now"));
+
+        assert_eq!(format!("Go to\n{url}\nnow"), text);
+    }
+
+    #[test]
+    fn hard_line_breaks_are_kept() {
+        let text = markdown_to_plain_text("Ursula\\\nLe Guin");
+
+        assert_eq!("Ursula\nLe Guin", text);
+    }
+
+    #[test]
+    fn html_tags_are_stripped() {
+        let text = markdown_to_plain_text("Some <b>bold</b> text");
+
+        assert_eq!("Some bold text", text);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::domain::newsletter_issue::MergeTags;
 use crate::utils::{contains_forbidden_characters, is_empty_or_whitespace, is_too_long};
 
 #[derive(Debug)]
@@ -15,7 +16,7 @@ impl Title {
             Err(String::from("A title is required."))
         } else if is_too_long(&s, 70) {
             Err(String::from("Title exceeds character limit."))
-        } else if contains_forbidden_characters(&s) {
+        } else if contains_forbidden_characters(&MergeTags::strip(&s)) {
             Err(String::from("Title includes illegal characters."))
         } else {
             Ok(Self(s))
@@ -65,6 +66,13 @@ mod tests {
         }
     }
 
+    #[test]
+    fn names_containing_merge_tags_are_accepted() {
+        let name = r#"Hello {{ subscriber.name | default: "there" }}"#.to_string();
+
+        assert_ok!(Title::parse(name));
+    }
+
     #[test]
     fn a_valid_name_is_parsed_successfully() {
         let name = "Ursula Le Guin".to_string();
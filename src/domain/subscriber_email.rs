@@ -11,6 +11,12 @@ impl SubscriberEmail {
             Err(format!("{} is not a valid subscriber email.", s))
         }
     }
+
+    /// The address in lowercase, the form suppressions are keyed on so that
+    /// a change of letter case cannot get around one.
+    pub fn normalised(&self) -> String {
+        self.0.to_lowercase()
+    }
 }
 
 impl AsRef<str> for SubscriberEmail {
@@ -52,6 +58,12 @@ mod tests {
         assert_err!(SubscriberEmail::parse(email));
     }
 
+    #[test]
+    fn the_normalised_address_is_lowercase() {
+        let email = SubscriberEmail::parse("Ursula.Le.Guin@Example.COM".to_string()).unwrap();
+        assert_eq!("ursula.le.guin@example.com", email.normalised());
+    }
+
     #[derive(Debug, Clone)]
     struct ValidEmailFixture(pub String);
 
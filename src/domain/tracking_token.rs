@@ -0,0 +1,164 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::Sha256;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Stateless token embedded in the open and click tracking links of an email.
+/// It carries the issue and subscriber ids alongside an HMAC signature.
+#[derive(Debug)]
+pub struct TrackingToken(String);
+
+impl TrackingToken {
+    pub fn generate(
+        newsletter_issue_id: &Uuid,
+        subscriber_id: &Uuid,
+        hmac_secret: &SecretString,
+    ) -> Self {
+        let signature = signer(newsletter_issue_id, subscriber_id, hmac_secret)
+            .finalize()
+            .into_bytes();
+
+        Self(format!(
+            "{}.{}.{}",
+            newsletter_issue_id.simple(),
+            subscriber_id.simple(),
+            URL_SAFE_NO_PAD.encode(signature)
+        ))
+    }
+
+    /// Returns the issue and subscriber ids carried by the token if its
+    /// signature is valid.
+    pub fn parse(s: &str, hmac_secret: &SecretString) -> Result<(Uuid, Uuid), String> {
+        let invalid = || String::from("Invalid tracking token.");
+        let mut parts = s.split('.');
+        let (Some(newsletter_issue_id), Some(subscriber_id), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        let newsletter_issue_id = Uuid::try_parse(newsletter_issue_id).map_err(|_| invalid())?;
+        let subscriber_id = Uuid::try_parse(subscriber_id).map_err(|_| invalid())?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid())?;
+
+        signer(&newsletter_issue_id, &subscriber_id, hmac_secret)
+            .verify_slice(&signature)
+            .map_err(|_| invalid())?;
+
+        Ok((newsletter_issue_id, subscriber_id))
+    }
+}
+
+impl AsRef<str> for TrackingToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Signature of the destination of a tracked link, so that the click endpoint
+/// only ever redirects to URLs found in an issue.
+#[derive(Debug)]
+pub struct LinkSignature(String);
+
+impl LinkSignature {
+    pub fn generate(url: &str, hmac_secret: &SecretString) -> Self {
+        let signature = link_signer(url, hmac_secret).finalize().into_bytes();
+
+        Self(URL_SAFE_NO_PAD.encode(signature))
+    }
+
+    pub fn verify(url: &str, signature: &str, hmac_secret: &SecretString) -> Result<(), String> {
+        let invalid = || String::from("Invalid link signature.");
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid())?;
+
+        link_signer(url, hmac_secret)
+            .verify_slice(&signature)
+            .map_err(|_| invalid())
+    }
+}
+
+impl AsRef<str> for LinkSignature {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+fn signer(
+    newsletter_issue_id: &Uuid,
+    subscriber_id: &Uuid,
+    hmac_secret: &SecretString,
+) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(hmac_secret.expose_secret().as_bytes())
+        .expect("HMAC can take a key of any size.");
+    mac.update(b"tracking:");
+    mac.update(newsletter_issue_id.as_bytes());
+    mac.update(subscriber_id.as_bytes());
+    mac
+}
+
+fn link_signer(url: &str, hmac_secret: &SecretString) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(hmac_secret.expose_secret().as_bytes())
+        .expect("HMAC can take a key of any size.");
+    mac.update(b"link:");
+    mac.update(url.as_bytes());
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::{LinkSignature, TrackingToken};
+    use claims::{assert_err, assert_ok, assert_ok_eq};
+    use secrecy::SecretString;
+    use uuid::Uuid;
+
+    fn secret() -> SecretString {
+        SecretString::from("super-long-and-secret-random-key")
+    }
+
+    #[test]
+    fn a_generated_token_is_parsed_back_into_its_ids() {
+        let newsletter_issue_id = Uuid::new_v4();
+        let subscriber_id = Uuid::new_v4();
+        let token = TrackingToken::generate(&newsletter_issue_id, &subscriber_id, &secret());
+
+        assert_ok_eq!(
+            TrackingToken::parse(token.as_ref(), &secret()),
+            (newsletter_issue_id, subscriber_id)
+        );
+    }
+
+    #[test]
+    fn a_token_for_a_different_issue_is_rejected() {
+        let token = TrackingToken::generate(&Uuid::new_v4(), &Uuid::new_v4(), &secret());
+        let (_, rest) = token.as_ref().split_once('.').unwrap();
+        let forged = format!("{}.{}", Uuid::new_v4().simple(), rest);
+
+        assert_err!(TrackingToken::parse(&forged, &secret()));
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected() {
+        for token in ["", "not-a-token", "abc.def", "..", "a.b.c.d"] {
+            assert_err!(TrackingToken::parse(token, &secret()));
+        }
+    }
+
+    #[test]
+    fn a_link_signature_only_verifies_its_own_url() {
+        let signature = LinkSignature::generate("https://example.com", &secret());
+
+        assert_ok!(LinkSignature::verify(
+            "https://example.com",
+            signature.as_ref(),
+            &secret()
+        ));
+        assert_err!(LinkSignature::verify(
+            "https://evil.example.com",
+            signature.as_ref(),
+            &secret()
+        ));
+    }
+}
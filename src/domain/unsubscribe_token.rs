@@ -0,0 +1,102 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::Sha256;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Stateless token embedded in unsubscribe links. It carries the subscriber
+/// id alongside an HMAC signature, so it can be verified without a lookup.
+#[derive(Debug)]
+pub struct UnsubscribeToken(String);
+
+impl UnsubscribeToken {
+    pub fn generate(subscriber_id: &Uuid, hmac_secret: &SecretString) -> Self {
+        let signature = signer(subscriber_id, hmac_secret).finalize().into_bytes();
+
+        Self(format!(
+            "{}.{}",
+            subscriber_id.simple(),
+            URL_SAFE_NO_PAD.encode(signature)
+        ))
+    }
+
+    /// Returns the subscriber id carried by the token if its signature is valid.
+    pub fn parse(s: &str, hmac_secret: &SecretString) -> Result<Uuid, String> {
+        let invalid = || String::from("Invalid unsubscribe token.");
+        let (subscriber_id, signature) = s.split_once('.').ok_or_else(invalid)?;
+        let subscriber_id = Uuid::try_parse(subscriber_id).map_err(|_| invalid())?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid())?;
+
+        signer(&subscriber_id, hmac_secret)
+            .verify_slice(&signature)
+            .map_err(|_| invalid())?;
+
+        Ok(subscriber_id)
+    }
+}
+
+impl AsRef<str> for UnsubscribeToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+fn signer(subscriber_id: &Uuid, hmac_secret: &SecretString) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(hmac_secret.expose_secret().as_bytes())
+        .expect("HMAC can take a key of any size.");
+    mac.update(b"unsubscribe:");
+    mac.update(subscriber_id.as_bytes());
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::UnsubscribeToken;
+    use claims::{assert_err, assert_ok_eq};
+    use secrecy::SecretString;
+    use uuid::Uuid;
+
+    fn secret() -> SecretString {
+        SecretString::from("super-long-and-secret-random-key")
+    }
+
+    #[test]
+    fn a_generated_token_is_parsed_back_into_its_subscriber_id() {
+        let subscriber_id = Uuid::new_v4();
+        let token = UnsubscribeToken::generate(&subscriber_id, &secret());
+
+        assert_ok_eq!(
+            UnsubscribeToken::parse(token.as_ref(), &secret()),
+            subscriber_id
+        );
+    }
+
+    #[test]
+    fn a_token_signed_with_another_secret_is_rejected() {
+        let token = UnsubscribeToken::generate(
+            &Uuid::new_v4(),
+            &SecretString::from("another-secret-random-key"),
+        );
+
+        assert_err!(UnsubscribeToken::parse(token.as_ref(), &secret()));
+    }
+
+    #[test]
+    fn a_token_for_a_different_subscriber_is_rejected() {
+        let token = UnsubscribeToken::generate(&Uuid::new_v4(), &secret());
+        let (_, signature) = token.as_ref().split_once('.').unwrap();
+        let forged = format!("{}.{}", Uuid::new_v4().simple(), signature);
+
+        assert_err!(UnsubscribeToken::parse(&forged, &secret()));
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected() {
+        for token in ["", "not-a-token", "abc.def", "."] {
+            assert_err!(UnsubscribeToken::parse(token, &secret()));
+        }
+    }
+}
@@ -0,0 +1,50 @@
+#[derive(Debug)]
+pub struct AccentColor(String);
+
+impl AsRef<str> for AccentColor {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AccentColor {
+    /// A `#rrggbb` hex color, or an empty string for the default one. Only
+    /// this form is accepted since it ends up inside a stylesheet.
+    pub fn parse(s: String) -> Result<AccentColor, String> {
+        let is_hex_color = s
+            .strip_prefix('#')
+            .is_some_and(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()));
+        if s.is_empty() || is_hex_color {
+            Ok(Self(s.to_lowercase()))
+        } else {
+            Err(String::from(
+                "Accent color must be a hex color such as #1d4ed8.",
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::user_profile::AccentColor;
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn empty_string_is_accepted() {
+        assert_ok!(AccentColor::parse("".to_string()));
+    }
+
+    #[test]
+    fn a_hex_color_is_parsed_in_lowercase() {
+        let color = AccentColor::parse("#1D4ED8".to_string()).unwrap();
+
+        assert_eq!("#1d4ed8", color.as_ref());
+    }
+
+    #[test]
+    fn anything_but_a_six_digit_hex_color_is_rejected() {
+        for color in ["1d4ed8", "#fff", "#1d4ed8ff", "#gggggg", "red", "#1d4ed8;}"] {
+            assert_err!(AccentColor::parse(color.to_string()));
+        }
+    }
+}
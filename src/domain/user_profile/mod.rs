@@ -1,5 +1,9 @@
+mod accent_color;
 mod description;
 mod display_name;
+mod publication_name;
 
+pub use accent_color::AccentColor;
 pub use description::Description;
 pub use display_name::DisplayName;
+pub use publication_name::PublicationName;
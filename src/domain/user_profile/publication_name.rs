@@ -0,0 +1,57 @@
+use crate::utils::{contains_forbidden_characters, is_too_long};
+
+#[derive(Debug)]
+pub struct PublicationName(String);
+
+impl AsRef<str> for PublicationName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PublicationName {
+    /// Shown as the `From` name of issues, so it must fit on a header line.
+    pub fn parse(s: String) -> Result<PublicationName, String> {
+        if is_too_long(&s, 70) {
+            Err(String::from("Publication name exceeds character limit."))
+        } else if contains_forbidden_characters(&s) || s.chars().any(char::is_control) {
+            Err(String::from(
+                "Publication name includes illegal characters.",
+            ))
+        } else {
+            Ok(Self(s.trim().to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::user_profile::PublicationName;
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn a_70_grapheme_long_name_is_valid() {
+        let name = "ё".repeat(70);
+
+        assert_ok!(PublicationName::parse(name));
+    }
+
+    #[test]
+    fn a_name_longer_than_70_graphemes_is_rejected() {
+        let name = "a".repeat(71);
+
+        assert_err!(PublicationName::parse(name));
+    }
+
+    #[test]
+    fn names_breaking_out_of_a_header_are_rejected() {
+        for name in ["The\r\nBcc: someone", "\"Quoted\"", "<Tagged>"] {
+            assert_err!(PublicationName::parse(name.to_string()));
+        }
+    }
+
+    #[test]
+    fn a_valid_name_is_parsed_successfully() {
+        assert_ok!(PublicationName::parse("Earthsea Dispatches".to_string()));
+    }
+}
@@ -1,311 +0,0 @@
-use crate::domain::SubscriberEmail;
-use reqwest::Client;
-use secrecy::{ExposeSecret, SecretString};
-use serde::{Deserialize, Deserializer, Serialize};
-use tera::{Context, Tera};
-
-pub struct EmailClient {
-    http_client: Client,
-    base_url: String,
-    sender: SubscriberEmail,
-    authorization_token: SecretString,
-    pub server: EmailServer,
-}
-
-impl EmailClient {
-    pub fn new(
-        base_url: String,
-        sender: SubscriberEmail,
-        authorization_token: SecretString,
-        timeout: std::time::Duration,
-        server: EmailServer,
-    ) -> Self {
-        let http_client = Client::builder().timeout(timeout).build().unwrap();
-        Self {
-            http_client,
-            base_url,
-            sender,
-            authorization_token,
-            server,
-        }
-    }
-
-    pub async fn send_email(
-        &self,
-        recipient: &str,
-        subject: &str,
-        html_content: &str,
-        text_content: &str,
-    ) -> Result<(), reqwest::Error> {
-        let tera = Tera::new("templates/**/*.{html, txt}").unwrap();
-        let mut context = Context::new();
-        context.insert("html_content", html_content);
-        context.insert("subject", subject);
-
-        let html_body = tera
-            .render("email/newsletters/newsletter_issue.html", &context)
-            .expect("Failed to render template.");
-        let url: String = self.server.url(&self.base_url);
-        let request_body = SendEmailRequest {
-            from: self.sender.as_ref(),
-            to: recipient,
-            subject,
-            html_body: &html_body,
-            text_body: text_content,
-        };
-        let builder = self.http_client.post(&url).header(
-            "X-Postmark-Server-Token",
-            self.authorization_token.expose_secret(),
-        );
-
-        match self.server {
-            EmailServer::Mailpit => builder.json(&MailpitSendEmailRequest::from(request_body)),
-            EmailServer::Postmark => builder.json(&request_body),
-        }
-        .send()
-        .await?
-        .error_for_status()?;
-
-        Ok(())
-    }
-}
-
-#[derive(serde::Serialize)]
-#[serde(rename_all = "PascalCase")]
-struct SendEmailRequest<'a> {
-    from: &'a str,
-    to: &'a str,
-    subject: &'a str,
-    html_body: &'a str,
-    text_body: &'a str,
-}
-
-impl From<SendEmailRequest<'_>> for MailpitSendEmailRequest {
-    fn from(email_request: SendEmailRequest) -> Self {
-        MailpitSendEmailRequest {
-            from: MailpitContact {
-                email: email_request.from.to_string(),
-                name: None,
-            },
-            to: vec![MailpitContact {
-                email: email_request.to.to_string(),
-                name: None,
-            }],
-            subject: email_request.subject.to_string(),
-            text: email_request.text_body.to_string(),
-            html: email_request.html_body.to_string(),
-        }
-    }
-}
-
-#[derive(Serialize, Debug)]
-#[serde(rename_all = "PascalCase")]
-pub struct MailpitContact {
-    pub email: String,
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub name: Option<String>,
-}
-
-#[derive(Serialize, Debug)]
-#[serde(rename_all = "PascalCase")]
-pub struct MailpitSendEmailRequest {
-    pub from: MailpitContact,
-    pub to: Vec<MailpitContact>,
-    pub subject: String,
-    pub text: String,
-    pub html: String,
-}
-
-/// The possible email services for our application.
-#[derive(Clone)]
-pub enum EmailServer {
-    Postmark,
-    Mailpit,
-}
-
-impl EmailServer {
-    pub fn as_str(&self) -> &'static str {
-        match self {
-            EmailServer::Postmark => "postmark",
-            EmailServer::Mailpit => "mailpit",
-        }
-    }
-
-    pub fn url(&self, base_url: &str) -> String {
-        match self {
-            EmailServer::Postmark => format!("{}/email", base_url),
-            EmailServer::Mailpit => format!("{}/api/v1/send", base_url),
-        }
-    }
-}
-
-impl TryFrom<String> for EmailServer {
-    type Error = String;
-
-    fn try_from(s: String) -> Result<Self, Self::Error> {
-        match s.to_lowercase().as_str() {
-            "postmark" => Ok(Self::Postmark),
-            "mailpit" => Ok(Self::Mailpit),
-            other => Err(format!(
-                "{} is not a supported email server. Use either `postmark` or `mailpit`.",
-                other
-            )),
-        }
-    }
-}
-
-pub fn deserialize_email_server_from_string<'de, D>(
-    deserializer: D,
-) -> Result<EmailServer, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let email_server: String = Deserialize::deserialize(deserializer)?;
-
-    EmailServer::try_from(email_server).map_err(serde::de::Error::custom)
-}
-
-#[cfg(test)]
-mod tests {
-    use crate::domain::SubscriberEmail;
-    use crate::email_client::{EmailClient, EmailServer};
-    use claims::{assert_err, assert_ok};
-    use fake::faker::internet::en::SafeEmail;
-    use fake::faker::lorem::en::{Paragraph, Sentence};
-    use fake::{Fake, Faker};
-    use secrecy::SecretString;
-    use wiremock::matchers::{any, header, header_exists, method, path};
-    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
-
-    struct SendEmailBodyMatcher;
-
-    impl wiremock::Match for SendEmailBodyMatcher {
-        fn matches(&self, request: &Request) -> bool {
-            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
-            if let Ok(body) = result {
-                body.get("From").is_some()
-                    && body.get("To").is_some()
-                    && body.get("Subject").is_some()
-                    && body.get("HtmlBody").is_some()
-                    && body.get("TextBody").is_some()
-            } else {
-                false
-            }
-        }
-    }
-
-    /// Generate a random email subject
-    fn subject() -> String {
-        Sentence(1..2).fake()
-    }
-
-    /// Generate a random email content
-    fn content() -> String {
-        Paragraph(1..10).fake()
-    }
-
-    /// Generate a random subscriber email
-    fn email() -> SubscriberEmail {
-        SubscriberEmail::parse(SafeEmail().fake()).unwrap()
-    }
-
-    /// Get a test instance of `EmailClient`.
-    fn email_client(base_url: String) -> EmailClient {
-        EmailClient::new(
-            base_url,
-            email(),
-            SecretString::from(Faker.fake::<String>()),
-            std::time::Duration::from_millis(200),
-            EmailServer::Postmark,
-        )
-    }
-
-    #[tokio::test]
-    async fn send_email_sends_the_expected_request() {
-        // Arrange
-        let mock_server = MockServer::start().await;
-        let email_client = email_client(mock_server.uri());
-
-        Mock::given(header_exists("X-Postmark-Server-Token"))
-            .and(header("Content-Type", "application/json"))
-            .and(path("/email"))
-            .and(method("POST"))
-            .and(SendEmailBodyMatcher)
-            .respond_with(ResponseTemplate::new(200))
-            .expect(1)
-            .mount(&mock_server)
-            .await;
-
-        // Act
-        let _ = email_client
-            .send_email(email().as_ref(), &subject(), &content(), &content())
-            .await;
-
-        // Assert
-    }
-
-    #[tokio::test]
-    async fn send_email_succeeds_if_the_server_returns_200() {
-        // Arrange
-        let mock_server = MockServer::start().await;
-        let email_client = email_client(mock_server.uri());
-
-        Mock::given(any())
-            .respond_with(ResponseTemplate::new(200))
-            .expect(1)
-            .mount(&mock_server)
-            .await;
-
-        // Act
-        let outcome = email_client
-            .send_email(email().as_ref(), &subject(), &content(), &content())
-            .await;
-
-        // Assert
-        assert_ok!(outcome);
-    }
-
-    #[tokio::test]
-    async fn send_email_fails_if_the_server_returns_500() {
-        // Arrange
-        let mock_server = MockServer::start().await;
-        let email_client = email_client(mock_server.uri());
-
-        Mock::given(any())
-            // Not a 200 anymore!
-            .respond_with(ResponseTemplate::new(500))
-            .expect(1)
-            .mount(&mock_server)
-            .await;
-
-        // Act
-        let outcome = email_client
-            .send_email(email().as_ref(), &subject(), &content(), &content())
-            .await;
-
-        // Assert
-        assert_err!(outcome);
-    }
-
-    #[tokio::test]
-    async fn send_email_times_out_if_the_server_takes_too_long() {
-        // Arrange
-        let mock_server = MockServer::start().await;
-        let email_client = email_client(mock_server.uri());
-
-        let response = ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(180));
-        Mock::given(any())
-            .respond_with(response)
-            .expect(1)
-            .mount(&mock_server)
-            .await;
-
-        // Act
-        let outcome = email_client
-            .send_email(email().as_ref(), &subject(), &content(), &content())
-            .await;
-
-        // Assert
-        assert_err!(outcome);
-    }
-}
@@ -0,0 +1,98 @@
+use serde::Serialize;
+
+/// How an author dresses up their issues. Every field is empty when unset,
+/// in which case the layout falls back to its defaults.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct EmailBranding {
+    /// A `#rrggbb` color for the headings and links.
+    pub accent_color: String,
+    pub footer_text: String,
+    pub logo_url: String,
+    /// Commercial emails must carry one to comply with CAN-SPAM.
+    pub postal_address: String,
+    /// Shown atop every issue and as the `From` name.
+    pub publication_name: String,
+    pub reply_to: String,
+}
+
+impl EmailBranding {
+    pub(crate) fn sender_name(&self) -> Option<String> {
+        non_empty(&self.publication_name)
+    }
+
+    pub(crate) fn reply_to(&self) -> Option<String> {
+        non_empty(&self.reply_to)
+    }
+
+    /// The footer of the plain-text body, empty when there is nothing to put
+    /// in it.
+    pub(crate) fn text_footer(&self) -> String {
+        [self.footer_text.trim(), self.postal_address.trim()]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .map(|part| format!("\n\n{part}"))
+            .collect()
+    }
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::email_client::{EmailBranding, test_email_client};
+
+    fn branding() -> EmailBranding {
+        EmailBranding {
+            accent_color: String::from("#1d4ed8"),
+            footer_text: String::from("Thanks for reading."),
+            logo_url: String::from("https://cdn.test/logo.png"),
+            postal_address: String::from("1 Library Lane"),
+            publication_name: String::from("Earthsea & Co"),
+            reply_to: String::from("ursula@example.com"),
+        }
+    }
+
+    #[test]
+    fn the_branding_is_applied_to_every_recipient() {
+        let email = test_email_client()
+            .render_newsletter("Subject", "<p>Body</p>", "Body", false, &branding())
+            .unwrap()
+            .personalise("reader@example.com", None, None, None);
+
+        assert_eq!(Some("Earthsea & Co"), email.from_name());
+        assert_eq!(Some("ursula@example.com"), email.reply_to());
+        assert!(email.html_body().contains("color: #1d4ed8;"));
+        assert!(
+            email
+                .html_body()
+                .contains(r#"src="https://cdn.test/logo.png""#)
+        );
+        assert!(email.html_body().contains(r#"alt="Earthsea &amp; Co""#));
+        assert_eq!(
+            "Body\n\nThanks for reading.\n\n1 Library Lane",
+            email.text_body()
+        );
+    }
+
+    #[test]
+    fn without_branding_the_layout_is_left_bare() {
+        let email = test_email_client()
+            .render_newsletter(
+                "Subject",
+                "<p>Body</p>",
+                "Body",
+                false,
+                &EmailBranding::default(),
+            )
+            .unwrap()
+            .personalise("reader@example.com", None, None, None);
+
+        assert_eq!(None, email.from_name());
+        assert_eq!(None, email.reply_to());
+        assert!(!email.html_body().contains("<img"));
+        assert!(!email.html_body().contains("color: #"));
+        assert_eq!("Body", email.text_body());
+    }
+}
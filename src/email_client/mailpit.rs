@@ -0,0 +1,95 @@
+use crate::email_client::{OutgoingEmail, SendEmailError, Transport, check_status};
+use reqwest::Client;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Sends through the HTTP API of a Mailpit instance, which catches every
+/// email instead of delivering it.
+pub struct MailpitTransport {
+    base_url: String,
+    http_client: Client,
+    timeout: Duration,
+}
+
+impl MailpitTransport {
+    pub fn new(base_url: String, timeout: Duration) -> Self {
+        Self {
+            base_url,
+            http_client: Client::builder().timeout(timeout).build().unwrap(),
+            timeout,
+        }
+    }
+}
+
+impl Transport for MailpitTransport {
+    async fn send(&self, sender: &str, email: &OutgoingEmail) -> Result<(), SendEmailError> {
+        let url = format!("{}/api/v1/send", self.base_url);
+        let response = self
+            .http_client
+            .post(&url)
+            .json(&MailpitSendEmailRequest::new(sender, email))
+            .send()
+            .await?;
+        check_status(response)?;
+
+        Ok(())
+    }
+
+    fn max_send_duration(&self) -> Option<Duration> {
+        Some(self.timeout)
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct MailpitContact {
+    pub email: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct MailpitSendEmailRequest {
+    pub from: MailpitContact,
+    pub to: Vec<MailpitContact>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub reply_to: Vec<MailpitContact>,
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+}
+
+impl MailpitSendEmailRequest {
+    fn new(from: &str, email: &OutgoingEmail) -> Self {
+        Self {
+            from: MailpitContact {
+                email: from.to_string(),
+                name: email.from_name.clone(),
+            },
+            to: vec![MailpitContact {
+                email: email.to.clone(),
+                name: None,
+            }],
+            reply_to: email
+                .reply_to
+                .iter()
+                .map(|reply_to| MailpitContact {
+                    email: reply_to.clone(),
+                    name: None,
+                })
+                .collect(),
+            subject: email.subject.clone(),
+            text: email.text_body.clone(),
+            html: email.html_body.clone(),
+            headers: email
+                .headers
+                .iter()
+                .map(|header| (header.name.to_string(), header.value.clone()))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,948 @@
+mod branding;
+mod mailpit;
+mod outbox;
+mod postmark;
+mod smtp;
+mod templates;
+mod transactional;
+
+pub use branding::*;
+pub use mailpit::*;
+pub use outbox::*;
+pub use postmark::*;
+pub use smtp::*;
+pub use templates::*;
+pub use transactional::*;
+
+use crate::domain::newsletter_issue::{MergeTagValues, MergeTags};
+use crate::domain::{Locale, SubscriberEmail};
+use crate::rate_limiter::{RateLimiter, Reservation};
+use crate::utils::error_chain_fmt;
+use chrono::{DateTime, Utc};
+use lettre::Message;
+use lettre::message::header::{HeaderName, HeaderValue};
+use lettre::message::{Mailbox, MultiPart};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Response, StatusCode};
+use serde::{Deserialize, Deserializer};
+use std::time::Duration;
+use tera::Context;
+
+pub struct EmailClient {
+    sender: SubscriberEmail,
+    rate_limiter: Option<RateLimiter>,
+    templates: EmailTemplates,
+    transport: EmailTransport,
+}
+
+impl EmailClient {
+    pub fn new(
+        sender: SubscriberEmail,
+        transport: EmailTransport,
+        templates: EmailTemplates,
+    ) -> Self {
+        Self {
+            sender,
+            rate_limiter: None,
+            templates,
+            transport,
+        }
+    }
+
+    /// The kind of server emails are sent through.
+    pub fn server(&self) -> EmailServer {
+        match self.transport {
+            EmailTransport::Mailpit(_) => EmailServer::Mailpit,
+            EmailTransport::Outbox(_) => EmailServer::Outbox,
+            EmailTransport::Postmark(_) => EmailServer::Postmark,
+            EmailTransport::Smtp(_) => EmailServer::Smtp,
+        }
+    }
+
+    /// The outbox emails are written to, when sending through one.
+    pub fn outbox(&self) -> Option<&OutboxTransport> {
+        match &self.transport {
+            EmailTransport::Outbox(transport) => Some(transport),
+            _ => None,
+        }
+    }
+
+    /// Makes [`EmailClient::reserve_sends`] enforce the provider quotas.
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Largest batch worth preparing: a rate limiter never grants more than
+    /// its burst size at once, and a transport sending one email at a time
+    /// must get through the batch within half of `lease_duration`, leaving
+    /// the rest to acknowledge it before another worker can claim it again.
+    pub fn max_batch_size(&self, lease_duration: Duration) -> usize {
+        let mut max_batch_size = MAX_BATCH_SIZE;
+        if let Some(rate_limiter) = &self.rate_limiter {
+            max_batch_size = max_batch_size.min(rate_limiter.burst_size());
+        }
+        let max_send_duration = match &self.transport {
+            EmailTransport::Mailpit(transport) => transport.max_send_duration(),
+            EmailTransport::Outbox(transport) => transport.max_send_duration(),
+            EmailTransport::Postmark(transport) => transport.max_send_duration(),
+            EmailTransport::Smtp(transport) => transport.max_send_duration(),
+        };
+        if let Some(max_send_duration) = max_send_duration {
+            let n_sends = (lease_duration / 2).as_secs_f64() / max_send_duration.as_secs_f64();
+            max_batch_size = max_batch_size.min(n_sends as usize).max(1);
+        }
+        max_batch_size
+    }
+
+    /// Asks the rate limiter, if any, how many of `requested` emails may be
+    /// sent right now. Should the limiter be unreachable every send is
+    /// allowed, leaving the provider to push back with 429s.
+    pub async fn reserve_sends(&self, requested: usize) -> Reservation {
+        let Some(rate_limiter) = &self.rate_limiter else {
+            return Reservation {
+                granted: requested,
+                retry_after: Duration::ZERO,
+            };
+        };
+        match rate_limiter.acquire(requested).await {
+            Ok(reservation) => reservation,
+            Err(e) => {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to reserve sends with the rate limiter. Sending anyway.",
+                );
+                Reservation {
+                    granted: requested,
+                    retry_after: Duration::ZERO,
+                }
+            }
+        }
+    }
+
+    pub async fn send_email(
+        &self,
+        recipient: &str,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        unsubscribe_links: Option<&UnsubscribeLinks>,
+    ) -> Result<(), SendEmailError> {
+        let email = self
+            .render_newsletter(
+                subject,
+                html_content,
+                text_content,
+                unsubscribe_links.is_some(),
+                &EmailBranding::default(),
+            )?
+            .personalise(recipient, None, None, unsubscribe_links);
+        self.send_outgoing_email(&email).await
+    }
+
+    /// Renders a transactional email in `locale` and sends it.
+    pub async fn send_transactional_email<E: TransactionalEmail>(
+        &self,
+        recipient: &str,
+        email: &E,
+        locale: Locale,
+    ) -> Result<(), SendEmailError> {
+        let email = self.render_transactional_email(recipient, email, locale)?;
+        self.send_outgoing_email(&email).await
+    }
+
+    /// Sends a single email that has already been rendered and personalised.
+    pub async fn send_outgoing_email(&self, email: &OutgoingEmail) -> Result<(), SendEmailError> {
+        let sender = self.sender.as_ref();
+        match &self.transport {
+            EmailTransport::Mailpit(transport) => transport.send(sender, email).await,
+            EmailTransport::Outbox(transport) => transport.send(sender, email).await,
+            EmailTransport::Postmark(transport) => transport.send(sender, email).await,
+            EmailTransport::Smtp(transport) => transport.send(sender, email).await,
+        }
+    }
+
+    /// Sends up to [`MAX_BATCH_SIZE`] emails, returning one result per email in
+    /// the same order. The outer error means nothing was accepted, while an
+    /// inner error only concerns its own recipient.
+    ///
+    /// A 429 response is reported as [`SendEmailError::RateLimited`], along
+    /// with how long the provider asked us to wait.
+    pub async fn send_email_batch(
+        &self,
+        emails: &[OutgoingEmail],
+    ) -> Result<Vec<Result<(), SendEmailError>>, SendEmailError> {
+        let sender = self.sender.as_ref();
+        match &self.transport {
+            EmailTransport::Mailpit(transport) => transport.send_batch(sender, emails).await,
+            EmailTransport::Outbox(transport) => transport.send_batch(sender, emails).await,
+            EmailTransport::Postmark(transport) => transport.send_batch(sender, emails).await,
+            EmailTransport::Smtp(transport) => transport.send_batch(sender, emails).await,
+        }
+    }
+
+    /// Renders the newsletter layout once in the author's `branding`, leaving
+    /// a placeholder for the recipient's unsubscribe link when
+    /// `with_unsubscribe_link` is set.
+    pub fn render_newsletter(
+        &self,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        with_unsubscribe_link: bool,
+        branding: &EmailBranding,
+    ) -> Result<RenderedNewsletter, tera::Error> {
+        let mut context = Context::new();
+        context.insert("branding", branding);
+        context.insert("html_content", html_content);
+        context.insert("subject", subject);
+        if with_unsubscribe_link {
+            context.insert("unsubscribe_url", UNSUBSCRIBE_URL_PLACEHOLDER);
+        }
+
+        let html_body = self.templates.render(NEWSLETTER_ISSUE_TEMPLATE, &context)?;
+
+        Ok(RenderedNewsletter {
+            from_name: branding.sender_name(),
+            html_body,
+            merge_tags: MergeTags::default(),
+            reply_to: branding.reply_to(),
+            subject: subject.to_string(),
+            text_body: format!("{}{}", text_content, branding.text_footer()),
+        })
+    }
+
+    pub fn render_transactional_email<E: TransactionalEmail>(
+        &self,
+        recipient: &str,
+        email: &E,
+        locale: Locale,
+    ) -> Result<OutgoingEmail, tera::Error> {
+        let mut context = Context::from_serialize(email)?;
+        context.insert("lang", locale.as_str());
+        let render = |file: &str| {
+            self.templates
+                .render(&transactional_template(E::NAME, locale, file), &context)
+        };
+
+        Ok(OutgoingEmail {
+            from_name: None,
+            headers: vec![],
+            html_body: render(TRANSACTIONAL_HTML_BODY)?,
+            reply_to: None,
+            // Template files end with a line break, which has no place in a
+            // subject.
+            subject: render(TRANSACTIONAL_SUBJECT)?.trim().to_string(),
+            text_body: render(TRANSACTIONAL_TEXT_BODY)?,
+            to: recipient.to_string(),
+        })
+    }
+}
+
+/// Turns error statuses into a [`SendEmailError`], reading the delay the
+/// provider asks for when it is rate limiting us.
+fn check_status(response: Response) -> Result<Response, SendEmailError> {
+    if response.status() == StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, Utc::now()));
+        return Err(SendEmailError::RateLimited { retry_after });
+    }
+    Ok(response.error_for_status()?)
+}
+
+/// `Retry-After` holds either a number of seconds or an HTTP date.
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let retry_at = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (retry_at.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// Builds the MIME message the SMTP and outbox transports hand over.
+fn build_message(sender: &str, email: &OutgoingEmail) -> Result<Message, SendEmailError> {
+    let invalid = |e: &dyn std::error::Error| SendEmailError::InvalidMessage(e.to_string());
+    let from = Mailbox::new(
+        email.from_name.clone(),
+        sender.parse().map_err(|e| invalid(&e))?,
+    );
+    let to: Mailbox = email.to.parse().map_err(|e| invalid(&e))?;
+    let mut builder = Message::builder()
+        .from(from)
+        .to(to)
+        .subject(email.subject.clone());
+    if let Some(reply_to) = &email.reply_to {
+        builder = builder.reply_to(reply_to.parse().map_err(|e| invalid(&e))?);
+    }
+    for header in &email.headers {
+        builder = builder.raw_header(HeaderValue::new(
+            HeaderName::new_from_ascii_str(header.name),
+            header.value.clone(),
+        ));
+    }
+
+    builder
+        .multipart(MultiPart::alternative_plain_html(
+            email.text_body.clone(),
+            email.html_body.clone(),
+        ))
+        .map_err(|e| invalid(&e))
+}
+
+/// Largest number of emails Postmark accepts in a single batch request.
+pub const MAX_BATCH_SIZE: usize = 500;
+
+const UNSUBSCRIBE_URL_PLACEHOLDER: &str = "%%unsubscribe_url%%";
+
+/// A newsletter layout rendered once and shared by every recipient of a batch.
+pub struct RenderedNewsletter {
+    from_name: Option<String>,
+    html_body: String,
+    merge_tags: MergeTags,
+    reply_to: Option<String>,
+    subject: String,
+    text_body: String,
+}
+
+impl RenderedNewsletter {
+    /// Resolves these merge tags, left as placeholders in the subject and
+    /// bodies, for each recipient.
+    pub fn with_merge_tags(self, merge_tags: MergeTags) -> Self {
+        Self { merge_tags, ..self }
+    }
+
+    pub fn personalise(
+        &self,
+        recipient: &str,
+        recipient_name: Option<&str>,
+        tracking_token: Option<&str>,
+        unsubscribe_links: Option<&UnsubscribeLinks>,
+    ) -> OutgoingEmail {
+        let mut headers = vec![];
+        let values = MergeTagValues {
+            subscriber_email: recipient,
+            subscriber_name: recipient_name,
+            tracking_token,
+            unsubscribe_url: unsubscribe_links.map(|links| links.page_url.as_str()),
+        };
+        let mut html_body = self.merge_tags.resolve(&self.html_body, &values, true);
+        let mut text_body = self.merge_tags.resolve(&self.text_body, &values, false);
+
+        if let Some(links) = unsubscribe_links {
+            html_body = html_body.replace(UNSUBSCRIBE_URL_PLACEHOLDER, &links.page_url);
+            text_body.push_str(&format!("\n\nUnsubscribe: {}", links.page_url));
+            headers.push(EmailHeader {
+                name: "List-Unsubscribe",
+                value: format!("<{}>", links.one_click_url),
+            });
+            headers.push(EmailHeader {
+                name: "List-Unsubscribe-Post",
+                value: String::from("List-Unsubscribe=One-Click"),
+            });
+        }
+
+        OutgoingEmail {
+            from_name: self.from_name.clone(),
+            headers,
+            html_body,
+            reply_to: self.reply_to.clone(),
+            subject: self.merge_tags.resolve(&self.subject, &values, false),
+            text_body,
+            to: recipient.to_string(),
+        }
+    }
+}
+
+/// Something emails can be handed over to. Unless a transport knows better,
+/// a batch is sent one email at a time.
+pub(crate) trait Transport {
+    async fn send(&self, sender: &str, email: &OutgoingEmail) -> Result<(), SendEmailError>;
+
+    /// Longest a single send can take, which bounds how long a batch sent one
+    /// email at a time lasts. `None` when the whole batch goes out at once.
+    fn max_send_duration(&self) -> Option<Duration>;
+
+    async fn send_batch(
+        &self,
+        sender: &str,
+        emails: &[OutgoingEmail],
+    ) -> Result<Vec<Result<(), SendEmailError>>, SendEmailError> {
+        let mut results = Vec::with_capacity(emails.len());
+        for email in emails {
+            results.push(self.send(sender, email).await);
+        }
+        Ok(results)
+    }
+}
+
+/// The transports an [`EmailClient`] can send through.
+pub enum EmailTransport {
+    Mailpit(MailpitTransport),
+    Outbox(OutboxTransport),
+    Postmark(PostmarkTransport),
+    Smtp(SmtpTransport),
+}
+
+/// A fully rendered email addressed to a single recipient.
+pub struct OutgoingEmail {
+    /// Display name the sender address is shown under.
+    from_name: Option<String>,
+    headers: Vec<EmailHeader>,
+    html_body: String,
+    reply_to: Option<String>,
+    subject: String,
+    text_body: String,
+    to: String,
+}
+
+impl OutgoingEmail {
+    pub fn from_name(&self) -> Option<&str> {
+        self.from_name.as_deref()
+    }
+
+    pub fn html_body(&self) -> &str {
+        &self.html_body
+    }
+
+    pub fn reply_to(&self) -> Option<&str> {
+        self.reply_to.as_deref()
+    }
+
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    pub fn text_body(&self) -> &str {
+        &self.text_body
+    }
+
+    /// Size of the subject, bodies and headers in bytes. The provider's MIME
+    /// encoding adds a little on top of it.
+    pub fn size(&self) -> usize {
+        self.subject.len()
+            + self.html_body.len()
+            + self.text_body.len()
+            + self
+                .headers
+                .iter()
+                .map(|header| header.name.len() + header.value.len())
+                .sum::<usize>()
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum SendEmailError {
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+    #[error("{message} (error code {error_code})")]
+    Rejected { error_code: i64, message: String },
+    #[error("The email provider is rate limiting us")]
+    RateLimited { retry_after: Option<Duration> },
+    #[error(transparent)]
+    Smtp(#[from] lettre::transport::smtp::Error),
+    #[error("The email could not be built: {0}")]
+    InvalidMessage(String),
+    #[error("The email could not be written to the outbox")]
+    Outbox(#[from] std::io::Error),
+    #[error("The email could not be rendered")]
+    Template(#[from] tera::Error),
+}
+
+impl std::fmt::Debug for SendEmailError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl SendEmailError {
+    /// Timeouts, connection errors, rate limiting and server errors are worth
+    /// retrying; a rejected message or any other client error will fail again
+    /// the same way.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            SendEmailError::Request(e) => match e.status() {
+                Some(status) => status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error(),
+                None => true,
+            },
+            SendEmailError::Rejected { .. } => false,
+            SendEmailError::RateLimited { .. } => true,
+            // Connection and timeout errors come without a reply code.
+            SendEmailError::Smtp(e) => !e.is_permanent(),
+            SendEmailError::InvalidMessage(_) => false,
+            // A full disk or a directory being remounted may well recover.
+            SendEmailError::Outbox(_) => true,
+            SendEmailError::Template(_) => false,
+        }
+    }
+}
+
+/// Links a recipient can use to leave the list an email was sent through.
+pub struct UnsubscribeLinks {
+    /// RFC 8058 one-click endpoint, advertised through `List-Unsubscribe`.
+    pub one_click_url: String,
+    /// Page linked from the email body, meant to be opened in a browser.
+    pub page_url: String,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct EmailHeader {
+    name: &'static str,
+    value: String,
+}
+
+/// The possible email services for our application.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EmailServer {
+    Postmark,
+    Mailpit,
+    Smtp,
+    Outbox,
+}
+
+impl EmailServer {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailServer::Postmark => "postmark",
+            EmailServer::Mailpit => "mailpit",
+            EmailServer::Smtp => "smtp",
+            EmailServer::Outbox => "outbox",
+        }
+    }
+}
+
+impl TryFrom<String> for EmailServer {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.to_lowercase().as_str() {
+            "postmark" => Ok(Self::Postmark),
+            "mailpit" => Ok(Self::Mailpit),
+            "smtp" => Ok(Self::Smtp),
+            "outbox" => Ok(Self::Outbox),
+            other => Err(format!(
+                "{} is not a supported email server. Use either `postmark`, `mailpit`, `smtp` or `outbox`.",
+                other
+            )),
+        }
+    }
+}
+
+pub fn deserialize_email_server_from_string<'de, D>(
+    deserializer: D,
+) -> Result<EmailServer, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let email_server: String = Deserialize::deserialize(deserializer)?;
+
+    EmailServer::try_from(email_server).map_err(serde::de::Error::custom)
+}
+
+/// Client for unit tests that render emails without sending them anywhere.
+#[cfg(test)]
+pub(crate) fn test_email_client() -> EmailClient {
+    EmailClient::new(
+        SubscriberEmail::parse(String::from("sender@example.com")).unwrap(),
+        EmailTransport::Mailpit(MailpitTransport::new(
+            String::from("http://localhost"),
+            Duration::from_millis(200),
+        )),
+        EmailTemplates::load(EMAIL_TEMPLATES_GLOB).unwrap(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::SubscriberEmail;
+    use crate::email_client::{
+        EMAIL_TEMPLATES_GLOB, EmailBranding, EmailClient, EmailTemplates, EmailTransport,
+        MAX_BATCH_SIZE, MailpitTransport, OutgoingEmail, PostmarkTransport, SendEmailError,
+        UnsubscribeLinks, parse_retry_after,
+    };
+    use chrono::{TimeZone, Utc};
+    use claims::{assert_err, assert_ok};
+    use fake::faker::internet::en::SafeEmail;
+    use fake::faker::lorem::en::{Paragraph, Sentence};
+    use fake::{Fake, Faker};
+    use secrecy::SecretString;
+    use wiremock::matchers::{any, header, header_exists, method, path};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    struct SendEmailBodyMatcher;
+
+    impl wiremock::Match for SendEmailBodyMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+            if let Ok(body) = result {
+                body.get("From").is_some()
+                    && body.get("To").is_some()
+                    && body.get("Subject").is_some()
+                    && body.get("HtmlBody").is_some()
+                    && body.get("TextBody").is_some()
+            } else {
+                false
+            }
+        }
+    }
+
+    struct ListUnsubscribeHeadersMatcher;
+
+    impl wiremock::Match for ListUnsubscribeHeadersMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+            if let Ok(body) = result {
+                let headers = body["Headers"].as_array().cloned().unwrap_or_default();
+                let has_header = |name: &str, value: &str| {
+                    headers
+                        .iter()
+                        .any(|header| header["Name"] == name && header["Value"] == value)
+                };
+
+                has_header("List-Unsubscribe", "<https://api.test/unsubscribe>")
+                    && has_header("List-Unsubscribe-Post", "List-Unsubscribe=One-Click")
+                    && body["HtmlBody"]
+                        .as_str()
+                        .is_some_and(|html| html.contains("https://client.test/unsubscribe"))
+                    && body["TextBody"]
+                        .as_str()
+                        .is_some_and(|text| text.contains("https://client.test/unsubscribe"))
+            } else {
+                false
+            }
+        }
+    }
+
+    /// Generate a random email subject
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    /// Generate a random email content
+    fn content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    /// Generate a random subscriber email
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).unwrap()
+    }
+
+    /// Get a test instance of `EmailClient`.
+    fn email_client(base_url: String) -> EmailClient {
+        EmailClient::new(
+            email(),
+            EmailTransport::Postmark(PostmarkTransport::new(
+                base_url,
+                SecretString::from(Faker.fake::<String>()),
+                std::time::Duration::from_millis(200),
+            )),
+            EmailTemplates::load(EMAIL_TEMPLATES_GLOB).unwrap(),
+        )
+    }
+
+    /// Generate a rendered email for a random recipient
+    fn outgoing_email(email_client: &EmailClient) -> OutgoingEmail {
+        email_client
+            .render_newsletter(
+                &subject(),
+                &content(),
+                &content(),
+                false,
+                &EmailBranding::default(),
+            )
+            .unwrap()
+            .personalise(email().as_ref(), None, None, None)
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_the_expected_request() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(header_exists("X-Postmark-Server-Token"))
+            .and(header("Content-Type", "application/json"))
+            .and(path("/email"))
+            .and(method("POST"))
+            .and(SendEmailBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let _ = email_client
+            .send_email(email().as_ref(), &subject(), &content(), &content(), None)
+            .await;
+
+        // Assert
+    }
+
+    #[tokio::test]
+    async fn send_email_advertises_one_click_unsubscribe_when_links_are_provided() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let unsubscribe_links = UnsubscribeLinks {
+            one_click_url: String::from("https://api.test/unsubscribe"),
+            page_url: String::from("https://client.test/unsubscribe"),
+        };
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(ListUnsubscribeHeadersMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let _ = email_client
+            .send_email(
+                email().as_ref(),
+                &subject(),
+                &content(),
+                &content(),
+                Some(&unsubscribe_links),
+            )
+            .await;
+
+        // Assert
+    }
+
+    #[tokio::test]
+    async fn send_email_succeeds_if_the_server_returns_200() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(email().as_ref(), &subject(), &content(), &content(), None)
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_returns_500() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            // Not a 200 anymore!
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(email().as_ref(), &subject(), &content(), &content(), None)
+            .await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_the_server_takes_too_long() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        let response = ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(180));
+        Mock::given(any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(email().as_ref(), &subject(), &content(), &content(), None)
+            .await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_batch_sends_every_email_in_a_single_request() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let emails = vec![outgoing_email(&email_client), outgoing_email(&email_client)];
+
+        Mock::given(header_exists("X-Postmark-Server-Token"))
+            .and(path("/email/batch"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "ErrorCode": 0, "Message": "OK" },
+                { "ErrorCode": 0, "Message": "OK" },
+            ])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client.send_email_batch(&emails).await;
+
+        // Assert
+        let results = outcome.unwrap();
+        assert_eq!(2, results.len());
+        assert!(results.iter().all(|result| result.is_ok()));
+        let requests = mock_server.received_requests().await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(2, body.as_array().unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn send_email_batch_reports_rejected_emails_individually() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let emails = vec![outgoing_email(&email_client), outgoing_email(&email_client)];
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "ErrorCode": 0, "Message": "OK" },
+                { "ErrorCode": 406, "Message": "Inactive recipient" },
+            ])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let results = email_client.send_email_batch(&emails).await.unwrap();
+
+        // Assert
+        assert_ok!(&results[0]);
+        let error = results[1].as_ref().unwrap_err();
+        assert!(!error.is_transient());
+        assert!(error.to_string().contains("Inactive recipient"));
+    }
+
+    #[tokio::test]
+    async fn send_email_batch_fails_as_a_whole_if_the_server_returns_500() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let emails = vec![outgoing_email(&email_client)];
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client.send_email_batch(&emails).await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_batch_reports_how_long_a_rate_limiting_server_asks_us_to_wait() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let emails = vec![outgoing_email(&email_client)];
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "30"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client.send_email_batch(&emails).await;
+
+        // Assert
+        match outcome {
+            Err(SendEmailError::RateLimited { retry_after }) => {
+                assert_eq!(Some(std::time::Duration::from_secs(30)), retry_after)
+            }
+            _ => panic!("A 429 should be reported as rate limiting."),
+        }
+    }
+
+    #[test]
+    fn retry_after_accepts_a_number_of_seconds() {
+        assert_eq!(
+            Some(std::time::Duration::from_secs(120)),
+            parse_retry_after(" 120 ", Utc::now())
+        );
+    }
+
+    #[test]
+    fn retry_after_accepts_an_http_date() {
+        let now = Utc.with_ymd_and_hms(2015, 10, 21, 7, 27, 0).unwrap();
+        assert_eq!(
+            Some(std::time::Duration::from_secs(60)),
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now)
+        );
+    }
+
+    #[test]
+    fn retry_after_in_the_past_means_no_wait() {
+        let now = Utc.with_ymd_and_hms(2015, 10, 21, 8, 0, 0).unwrap();
+        assert_eq!(
+            Some(std::time::Duration::ZERO),
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now)
+        );
+    }
+
+    #[test]
+    fn invalid_retry_after_is_ignored() {
+        assert_eq!(None, parse_retry_after("soon", Utc::now()));
+    }
+
+    #[test]
+    fn batches_sent_in_a_single_request_are_not_limited_by_the_lease() {
+        let email_client = email_client(String::from("http://localhost"));
+        assert_eq!(
+            MAX_BATCH_SIZE,
+            email_client.max_batch_size(std::time::Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn batches_sent_one_email_at_a_time_fit_in_half_the_lease() {
+        let email_client = EmailClient::new(
+            email(),
+            EmailTransport::Mailpit(MailpitTransport::new(
+                String::from("http://localhost"),
+                std::time::Duration::from_secs(10),
+            )),
+            EmailTemplates::load(EMAIL_TEMPLATES_GLOB).unwrap(),
+        );
+        assert_eq!(
+            15,
+            email_client.max_batch_size(std::time::Duration::from_secs(300))
+        );
+        assert_eq!(
+            1,
+            email_client.max_batch_size(std::time::Duration::from_secs(5))
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use crate::email_client::{OutgoingEmail, SendEmailError, Transport, build_message};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// RFC 2047 encoded word, which is how headers that are not plain ASCII get
+/// written.
+static ENCODED_WORD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)=\?utf-8\?b\?([^?]*)\?=").unwrap());
+
+/// Format of the timestamp opening every message id, which makes ids sort in
+/// the order messages were written.
+const ID_TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%6fZ";
+
+/// Generous bound on writing one email to a local directory.
+const MAX_WRITE_DURATION: Duration = Duration::from_secs(1);
+
+/// Writes every email as an `.eml` file into a directory instead of sending
+/// it, so the app can run without any mail server.
+pub struct OutboxTransport {
+    directory: PathBuf,
+}
+
+impl OutboxTransport {
+    /// Creates `directory` if it does not exist yet.
+    pub fn new(directory: impl Into<PathBuf>) -> Result<Self, std::io::Error> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)?;
+
+        Ok(Self { directory })
+    }
+
+    /// The `limit` most recently written messages, newest first.
+    pub async fn list(&self, limit: usize) -> Result<Vec<OutboxMessage>, std::io::Error> {
+        let mut ids = vec![];
+        let mut entries = tokio::fs::read_dir(&self.directory).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let file_name = entry.file_name();
+            let Some(id) = file_name
+                .to_str()
+                .and_then(|name| name.strip_suffix(".eml"))
+            else {
+                continue;
+            };
+            if is_valid_id(id) {
+                ids.push(id.to_string());
+            }
+        }
+        ids.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut messages = Vec::with_capacity(limit.min(ids.len()));
+        for id in ids.into_iter().take(limit) {
+            let Some(raw) = self.read(&id).await? else {
+                continue;
+            };
+            if let Some(message) = OutboxMessage::parse(id, &raw) {
+                messages.push(message);
+            }
+        }
+
+        Ok(messages)
+    }
+
+    /// The raw content of a message, if there is one with this id.
+    pub async fn read(&self, id: &str) -> Result<Option<Vec<u8>>, std::io::Error> {
+        // Ids never contain anything that could escape the directory.
+        if !is_valid_id(id) {
+            return Ok(None);
+        }
+        match tokio::fs::read(self.path(id)).await {
+            Ok(raw) => Ok(Some(raw)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.directory.join(format!("{id}.eml"))
+    }
+}
+
+impl Transport for OutboxTransport {
+    async fn send(&self, sender: &str, email: &OutgoingEmail) -> Result<(), SendEmailError> {
+        let message = build_message(sender, email)?;
+        let id = format!(
+            "{}-{}",
+            Utc::now().format(ID_TIMESTAMP_FORMAT),
+            Uuid::new_v4().simple()
+        );
+        tokio::fs::write(self.path(&id), message.formatted()).await?;
+
+        Ok(())
+    }
+
+    fn max_send_duration(&self) -> Option<Duration> {
+        Some(MAX_WRITE_DURATION)
+    }
+}
+
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// Summary of an email written to the outbox.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OutboxMessage {
+    pub created_at: DateTime<Utc>,
+    pub from: String,
+    pub id: String,
+    pub subject: String,
+    pub to: String,
+}
+
+impl OutboxMessage {
+    /// Reads the summary from the headers of a message, or `None` if it was
+    /// not written by the outbox.
+    fn parse(id: String, raw: &[u8]) -> Option<Self> {
+        let (timestamp, _) = id.split_once('-')?;
+        let created_at = NaiveDateTime::parse_from_str(timestamp, ID_TIMESTAMP_FORMAT)
+            .ok()?
+            .and_utc();
+        let raw = String::from_utf8_lossy(raw);
+        let header = |name: &str| header_value(&raw, name).unwrap_or_default();
+
+        Some(Self {
+            created_at,
+            from: header("From"),
+            subject: header("Subject"),
+            to: header("To"),
+            id,
+        })
+    }
+}
+
+/// Unfolds and decodes the first header named `name`.
+fn header_value(raw: &str, name: &str) -> Option<String> {
+    let mut value: Option<String> = None;
+    for line in raw.lines() {
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with([' ', '\t']) {
+            if let Some(value) = value.as_mut() {
+                value.push_str(line);
+            }
+            continue;
+        }
+        if value.is_some() {
+            break;
+        }
+        if let Some((header_name, header_value)) = line.split_once(':')
+            && header_name.eq_ignore_ascii_case(name)
+        {
+            value = Some(header_value.to_string());
+        }
+    }
+
+    value.map(|value| decode_encoded_words(value.trim()))
+}
+
+/// Decodes RFC 2047 encoded words. Whitespace between two adjacent encoded
+/// words is dropped, as a character may be split across them.
+fn decode_encoded_words(value: &str) -> String {
+    let mut decoded = String::new();
+    let mut pending: Vec<u8> = vec![];
+    let mut last_end = 0;
+    for captures in ENCODED_WORD.captures_iter(value) {
+        let word = captures.get(0).unwrap();
+        let gap = &value[last_end..word.start()];
+        if pending.is_empty() || !gap.trim().is_empty() {
+            decoded.push_str(&String::from_utf8_lossy(&pending));
+            pending.clear();
+            decoded.push_str(gap);
+        }
+        pending.extend(STANDARD.decode(&captures[1]).unwrap_or_default());
+        last_end = word.end();
+    }
+    decoded.push_str(&String::from_utf8_lossy(&pending));
+    decoded.push_str(&value[last_end..]);
+
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::SubscriberEmail;
+    use crate::email_client::outbox::decode_encoded_words;
+    use crate::email_client::{
+        EMAIL_TEMPLATES_GLOB, EmailClient, EmailTemplates, EmailTransport, OutboxTransport,
+        UnsubscribeLinks,
+    };
+    use claims::{assert_none, assert_ok, assert_some};
+    use fake::Fake;
+    use fake::faker::internet::en::SafeEmail;
+    use std::path::PathBuf;
+    use uuid::Uuid;
+
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).unwrap()
+    }
+
+    fn outbox_directory() -> PathBuf {
+        std::env::temp_dir().join(format!("outbox-{}", Uuid::new_v4()))
+    }
+
+    fn email_client(directory: &PathBuf) -> EmailClient {
+        EmailClient::new(
+            email(),
+            EmailTransport::Outbox(OutboxTransport::new(directory).unwrap()),
+            EmailTemplates::load(EMAIL_TEMPLATES_GLOB).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn emails_are_written_with_both_bodies_and_their_headers() {
+        // Arrange
+        let directory = outbox_directory();
+        let email_client = email_client(&directory);
+        let recipient = email();
+        let unsubscribe_links = UnsubscribeLinks {
+            one_click_url: String::from("https://api.test/unsubscribe"),
+            page_url: String::from("https://client.test/unsubscribe"),
+        };
+
+        // Act
+        let outcome = email_client
+            .send_email(
+                recipient.as_ref(),
+                "Newsletter subject",
+                "<p>Newsletter body</p>",
+                "Newsletter body",
+                Some(&unsubscribe_links),
+            )
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+        let outbox = email_client.outbox().unwrap();
+        let messages = outbox.list(10).await.unwrap();
+        assert_eq!(1, messages.len());
+        assert_eq!(recipient.as_ref(), messages[0].to);
+        assert_eq!("Newsletter subject", messages[0].subject);
+        let raw = assert_some!(outbox.read(&messages[0].id).await.unwrap());
+        let raw = String::from_utf8(raw).unwrap();
+        assert!(raw.contains("List-Unsubscribe: <https://api.test/unsubscribe>"));
+        assert!(raw.contains("Content-Type: multipart/alternative"));
+        assert!(raw.contains("<p>Newsletter body</p>"));
+        assert!(directory.join(format!("{}.eml", messages[0].id)).exists());
+    }
+
+    #[tokio::test]
+    async fn messages_are_listed_newest_first_up_to_the_limit() {
+        // Arrange
+        let email_client = email_client(&outbox_directory());
+        for subject in ["First", "Second", "Troisième ✉"] {
+            email_client
+                .send_email(email().as_ref(), subject, "<p>Body</p>", "Body", None)
+                .await
+                .unwrap();
+        }
+
+        // Act
+        let messages = email_client.outbox().unwrap().list(2).await.unwrap();
+
+        // Assert
+        let subjects: Vec<_> = messages.iter().map(|m| m.subject.as_str()).collect();
+        assert_eq!(vec!["Troisième ✉", "Second"], subjects);
+    }
+
+    #[tokio::test]
+    async fn ids_cannot_escape_the_outbox_directory() {
+        let directory = outbox_directory();
+        let outbox = OutboxTransport::new(directory.join("nested")).unwrap();
+        std::fs::write(directory.join("secret.eml"), "Subject: secret").unwrap();
+
+        for id in ["../secret", "..", "", "nested/../../secret"] {
+            assert_none!(outbox.read(id).await.unwrap());
+        }
+    }
+
+    #[test]
+    fn adjacent_encoded_words_are_decoded_together() {
+        // "é" split across two encoded words.
+        assert_eq!(
+            "Caf\u{e9} au lait",
+            decode_encoded_words("=?utf-8?b?Q2Fmww==?= =?UTF-8?B?qQ==?= au lait")
+        );
+        assert_eq!("Plain subject", decode_encoded_words("Plain subject"));
+    }
+}
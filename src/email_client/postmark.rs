@@ -0,0 +1,119 @@
+use crate::email_client::{EmailHeader, OutgoingEmail, SendEmailError, Transport, check_status};
+use reqwest::Client;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Sends through Postmark's HTTP API.
+pub struct PostmarkTransport {
+    authorization_token: SecretString,
+    base_url: String,
+    http_client: Client,
+}
+
+impl PostmarkTransport {
+    pub fn new(base_url: String, authorization_token: SecretString, timeout: Duration) -> Self {
+        Self {
+            authorization_token,
+            base_url,
+            http_client: Client::builder().timeout(timeout).build().unwrap(),
+        }
+    }
+
+    fn request(&self, url: &str) -> reqwest::RequestBuilder {
+        self.http_client.post(url).header(
+            "X-Postmark-Server-Token",
+            self.authorization_token.expose_secret(),
+        )
+    }
+}
+
+impl Transport for PostmarkTransport {
+    async fn send(&self, sender: &str, email: &OutgoingEmail) -> Result<(), SendEmailError> {
+        let url = format!("{}/email", self.base_url);
+        let response = self
+            .request(&url)
+            .json(&SendEmailRequest::new(sender, email))
+            .send()
+            .await?;
+        check_status(response)?;
+
+        Ok(())
+    }
+
+    fn max_send_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Postmark accepts the whole batch in a single request, then reports on
+    /// each email.
+    async fn send_batch(
+        &self,
+        sender: &str,
+        emails: &[OutgoingEmail],
+    ) -> Result<Vec<Result<(), SendEmailError>>, SendEmailError> {
+        let url = format!("{}/email/batch", self.base_url);
+        let request_body: Vec<SendEmailRequest> = emails
+            .iter()
+            .map(|email| SendEmailRequest::new(sender, email))
+            .collect();
+        let response = self.request(&url).json(&request_body).send().await?;
+        let responses: Vec<PostmarkBatchResponse> = check_status(response)?.json().await?;
+
+        Ok(responses
+            .into_iter()
+            .map(|response| match response.error_code {
+                0 => Ok(()),
+                error_code => Err(SendEmailError::Rejected {
+                    error_code,
+                    message: response.message,
+                }),
+            })
+            .collect())
+    }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_to: Option<&'a str>,
+    to: &'a str,
+    subject: &'a str,
+    html_body: &'a str,
+    text_body: &'a str,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    headers: &'a [EmailHeader],
+}
+
+impl<'a> SendEmailRequest<'a> {
+    fn new(from: &'a str, email: &'a OutgoingEmail) -> Self {
+        let from = match &email.from_name {
+            // Backslashes and quotes are escaped so the name stays within its
+            // quoted string.
+            Some(name) => format!(
+                "\"{}\" <{}>",
+                name.replace('\\', "\\\\").replace('"', "\\\""),
+                from
+            ),
+            None => from.to_string(),
+        };
+        Self {
+            from,
+            reply_to: email.reply_to.as_deref(),
+            to: &email.to,
+            subject: &email.subject,
+            html_body: &email.html_body,
+            text_body: &email.text_body,
+            headers: &email.headers,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PostmarkBatchResponse {
+    error_code: i64,
+    message: String,
+}
@@ -0,0 +1,309 @@
+use crate::email_client::{OutgoingEmail, SendEmailError, Transport, build_message};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Deserializer};
+use std::time::Duration;
+
+/// Sends through any mail server speaking SMTP, over a pool of connections.
+pub struct SmtpTransport {
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+    timeout: Duration,
+}
+
+impl SmtpTransport {
+    /// Credentials are only presented when `username` is set.
+    pub fn new(
+        host: &str,
+        port: u16,
+        tls: SmtpTls,
+        username: &str,
+        password: &SecretString,
+        timeout: Duration,
+    ) -> Result<Self, lettre::transport::smtp::Error> {
+        let mut builder = match tls {
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+            SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?,
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host)?,
+        }
+        .port(port)
+        .timeout(Some(timeout));
+        if !username.is_empty() {
+            builder = builder.credentials(Credentials::new(
+                username.to_string(),
+                password.expose_secret().to_string(),
+            ));
+        }
+
+        Ok(Self {
+            mailer: builder.build(),
+            timeout,
+        })
+    }
+}
+
+impl Transport for SmtpTransport {
+    async fn send(&self, sender: &str, email: &OutgoingEmail) -> Result<(), SendEmailError> {
+        self.mailer.send(build_message(sender, email)?).await?;
+
+        Ok(())
+    }
+
+    fn max_send_duration(&self) -> Option<Duration> {
+        Some(self.timeout)
+    }
+}
+
+/// How the connection to the SMTP server is secured.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SmtpTls {
+    /// Plain text, only fit for a server on the same host or network.
+    None,
+    /// Upgrades a plain text connection, usually on port 587.
+    Starttls,
+    /// TLS from the start, usually on port 465.
+    Tls,
+}
+
+impl TryFrom<String> for SmtpTls {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "starttls" => Ok(Self::Starttls),
+            "tls" => Ok(Self::Tls),
+            other => Err(format!(
+                "{} is not a supported SMTP encryption. Use either `none`, `starttls` or `tls`.",
+                other
+            )),
+        }
+    }
+}
+
+pub fn deserialize_smtp_tls_from_string<'de, D>(deserializer: D) -> Result<SmtpTls, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let tls: String = Deserialize::deserialize(deserializer)?;
+
+    SmtpTls::try_from(tls).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::SubscriberEmail;
+    use crate::email_client::{
+        EMAIL_TEMPLATES_GLOB, EmailBranding, EmailClient, EmailTemplates, EmailTransport, SmtpTls,
+        SmtpTransport, UnsubscribeLinks,
+    };
+    use claims::{assert_err, assert_ok};
+    use fake::Fake;
+    use fake::faker::internet::en::SafeEmail;
+    use secrecy::SecretString;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// What a [`SmtpSink`] was told.
+    #[derive(Default)]
+    struct Received {
+        auth: Vec<String>,
+        messages: Vec<String>,
+    }
+
+    /// A bare SMTP server accepting every email, except that it answers
+    /// `RCPT TO` with `rcpt_reply`.
+    struct SmtpSink {
+        port: u16,
+        received: Arc<Mutex<Received>>,
+    }
+
+    impl SmtpSink {
+        fn start(rcpt_reply: &'static str) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let received = Arc::new(Mutex::new(Received::default()));
+            let sink = Arc::clone(&received);
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let mut stream = stream.unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    stream.write_all(b"220 localhost ESMTP\r\n").unwrap();
+                    let mut line = String::new();
+                    while reader.read_line(&mut line).unwrap_or(0) > 0 {
+                        let command = line.trim_end().to_string();
+                        line.clear();
+                        let upper = command.to_uppercase();
+                        let reply = if upper.starts_with("EHLO") {
+                            "250-localhost\r\n250 AUTH PLAIN LOGIN"
+                        } else if upper.starts_with("AUTH") {
+                            sink.lock().unwrap().auth.push(command);
+                            "235 Authenticated"
+                        } else if upper.starts_with("RCPT") {
+                            rcpt_reply
+                        } else if upper == "DATA" {
+                            stream.write_all(b"354 Go ahead\r\n").unwrap();
+                            let mut message = String::new();
+                            while reader.read_line(&mut line).unwrap() > 0 && line != ".\r\n" {
+                                message.push_str(&line);
+                                line.clear();
+                            }
+                            line.clear();
+                            sink.lock().unwrap().messages.push(message);
+                            "250 Queued"
+                        } else if upper == "QUIT" {
+                            let _ = stream.write_all(b"221 Bye\r\n");
+                            break;
+                        } else {
+                            "250 OK"
+                        };
+                        stream.write_all(format!("{reply}\r\n").as_bytes()).unwrap();
+                    }
+                }
+            });
+
+            Self { port, received }
+        }
+
+        fn client(&self, username: &str) -> EmailClient {
+            let transport = SmtpTransport::new(
+                "127.0.0.1",
+                self.port,
+                SmtpTls::None,
+                username,
+                &SecretString::from("smtp-password"),
+                Duration::from_secs(2),
+            )
+            .unwrap();
+
+            EmailClient::new(
+                email(),
+                EmailTransport::Smtp(transport),
+                EmailTemplates::load(EMAIL_TEMPLATES_GLOB).unwrap(),
+            )
+        }
+    }
+
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn emails_are_sent_with_both_bodies_and_their_headers() {
+        // Arrange
+        let sink = SmtpSink::start("250 OK");
+        let email_client = sink.client("");
+        let recipient = email();
+        let unsubscribe_links = UnsubscribeLinks {
+            one_click_url: String::from("https://api.test/unsubscribe"),
+            page_url: String::from("https://client.test/unsubscribe"),
+        };
+
+        // Act
+        let outcome = email_client
+            .send_email(
+                recipient.as_ref(),
+                "Newsletter subject",
+                "<p>Newsletter body</p>",
+                "Newsletter body",
+                Some(&unsubscribe_links),
+            )
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+        let received = sink.received.lock().unwrap();
+        assert!(received.auth.is_empty());
+        let message = &received.messages[0];
+        assert!(message.contains(&format!("To: {}", recipient.as_ref())));
+        assert!(message.contains("Subject: Newsletter subject"));
+        assert!(message.contains("List-Unsubscribe: <https://api.test/unsubscribe>"));
+        assert!(message.contains("Content-Type: multipart/alternative"));
+        assert!(message.contains("<p>Newsletter body</p>"));
+    }
+
+    #[tokio::test]
+    async fn credentials_are_presented_when_a_username_is_set() {
+        // Arrange
+        let sink = SmtpSink::start("250 OK");
+        let email_client = sink.client("smtp-user");
+
+        // Act
+        let outcome = email_client
+            .send_email(email().as_ref(), "Subject", "<p>Body</p>", "Body", None)
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+        assert_eq!(1, sink.received.lock().unwrap().auth.len());
+    }
+
+    #[tokio::test]
+    async fn a_batch_reports_on_each_email() {
+        // Arrange
+        let sink = SmtpSink::start("250 OK");
+        let email_client = sink.client("");
+        let emails: Vec<_> = (0..3)
+            .map(|_| {
+                email_client
+                    .render_newsletter(
+                        "Subject",
+                        "<p>Body</p>",
+                        "Body",
+                        false,
+                        &EmailBranding::default(),
+                    )
+                    .unwrap()
+                    .personalise(email().as_ref(), None, None, None)
+            })
+            .collect();
+
+        // Act
+        let results = email_client.send_email_batch(&emails).await.unwrap();
+
+        // Assert
+        assert_eq!(3, results.len());
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_eq!(3, sink.received.lock().unwrap().messages.len());
+    }
+
+    #[tokio::test]
+    async fn permanently_rejected_recipients_are_not_retried() {
+        // Arrange
+        let sink = SmtpSink::start("550 No such user");
+        let email_client = sink.client("");
+
+        // Act
+        let outcome = email_client
+            .send_email(email().as_ref(), "Subject", "<p>Body</p>", "Body", None)
+            .await;
+
+        // Assert
+        let error = assert_err!(outcome);
+        assert!(!error.is_transient());
+    }
+
+    #[tokio::test]
+    async fn temporarily_rejected_recipients_are_retried() {
+        // Arrange
+        let sink = SmtpSink::start("451 Try again later");
+        let email_client = sink.client("");
+
+        // Act
+        let outcome = email_client
+            .send_email(email().as_ref(), "Subject", "<p>Body</p>", "Body", None)
+            .await;
+
+        // Assert
+        let error = assert_err!(outcome);
+        assert!(error.is_transient());
+    }
+
+    #[test]
+    fn unknown_encryption_modes_are_rejected() {
+        assert_ok!(SmtpTls::try_from(String::from("STARTTLS")));
+        assert_err!(SmtpTls::try_from(String::from("ssl")));
+    }
+}
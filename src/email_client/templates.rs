@@ -0,0 +1,127 @@
+use crate::domain::Locale;
+use crate::email_client::TRANSACTIONAL_EMAILS;
+use tera::{Context, Tera};
+
+/// Where email templates are loaded from, relative to the working directory.
+pub const EMAIL_TEMPLATES_GLOB: &str = "templates/**/*.{html,txt}";
+
+pub(crate) const NEWSLETTER_ISSUE_TEMPLATE: &str = "email/newsletters/newsletter_issue.html";
+
+/// Templates the emails cannot be rendered without.
+const REQUIRED_TEMPLATES: &[&str] = &[NEWSLETTER_ISSUE_TEMPLATE];
+
+/// Templates each transactional email has in every locale.
+pub(crate) const TRANSACTIONAL_SUBJECT: &str = "subject.txt";
+pub(crate) const TRANSACTIONAL_HTML_BODY: &str = "body.html";
+pub(crate) const TRANSACTIONAL_TEXT_BODY: &str = "body.txt";
+
+pub(crate) fn transactional_template(email: &str, locale: Locale, file: &str) -> String {
+    format!("email/transactional/{}/{}/{}", email, locale.as_str(), file)
+}
+
+/// Email templates, parsed once and shared by every email sent.
+#[derive(Debug)]
+pub struct EmailTemplates {
+    tera: Tera,
+}
+
+impl EmailTemplates {
+    /// Fails if any template does not parse, or if one the emails need is
+    /// missing, including a transactional email in any locale.
+    pub fn load(glob: &str) -> Result<Self, tera::Error> {
+        let tera = Tera::new(glob)?;
+        for name in REQUIRED_TEMPLATES {
+            tera.get_template(name)?;
+        }
+        for email in TRANSACTIONAL_EMAILS {
+            for locale in Locale::ALL {
+                for file in [
+                    TRANSACTIONAL_SUBJECT,
+                    TRANSACTIONAL_HTML_BODY,
+                    TRANSACTIONAL_TEXT_BODY,
+                ] {
+                    tera.get_template(&transactional_template(email, locale, file))?;
+                }
+            }
+        }
+
+        Ok(Self { tera })
+    }
+
+    pub(crate) fn render(&self, name: &str, context: &Context) -> Result<String, tera::Error> {
+        self.tera.render(name, context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::email_client::{EMAIL_TEMPLATES_GLOB, EmailTemplates};
+    use claims::{assert_err, assert_ok};
+    use std::path::{Path, PathBuf};
+    use uuid::Uuid;
+
+    /// A templates directory holding only `files`, as (name, content) pairs.
+    fn templates_directory(files: &[(&str, &str)]) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("templates-{}", Uuid::new_v4()));
+        for (name, content) in files {
+            let path = directory.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        directory
+    }
+
+    fn glob(directory: &Path) -> String {
+        format!("{}/**/*.{{html,txt}}", directory.display())
+    }
+
+    #[test]
+    fn the_shipped_templates_load() {
+        assert_ok!(EmailTemplates::load(EMAIL_TEMPLATES_GLOB));
+    }
+
+    #[test]
+    fn a_missing_directory_is_rejected() {
+        let directory = std::env::temp_dir().join(format!("templates-{}", Uuid::new_v4()));
+
+        assert_err!(EmailTemplates::load(&glob(&directory)));
+    }
+
+    #[test]
+    fn a_missing_required_template_is_rejected() {
+        let directory = templates_directory(&[("email/base.html", "<html></html>")]);
+
+        assert_err!(EmailTemplates::load(&glob(&directory)));
+    }
+
+    #[test]
+    fn a_transactional_email_missing_from_a_locale_is_rejected() {
+        let mut files = vec![(
+            String::from("email/newsletters/newsletter_issue.html"),
+            "{{ subject }}",
+        )];
+        for file in ["subject.txt", "body.html", "body.txt"] {
+            files.push((
+                format!("email/transactional/subscription_confirmation/en/{file}"),
+                "{{ author_name }}",
+            ));
+        }
+        let files: Vec<_> = files
+            .iter()
+            .map(|(name, content)| (name.as_str(), *content))
+            .collect();
+        let directory = templates_directory(&files);
+
+        assert_err!(EmailTemplates::load(&glob(&directory)));
+    }
+
+    #[test]
+    fn a_template_that_does_not_parse_is_rejected() {
+        let directory = templates_directory(&[(
+            "email/newsletters/newsletter_issue.html",
+            "{% block content %}{{ subject }",
+        )]);
+
+        assert_err!(EmailTemplates::load(&glob(&directory)));
+    }
+}
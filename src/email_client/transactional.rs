@@ -0,0 +1,88 @@
+use serde::Serialize;
+
+/// An email the app sends on its own behalf, as opposed to an author's issue.
+/// Rendered from `subject.txt`, `body.html` and `body.txt` in
+/// `email/transactional/{NAME}/{locale}/`, with itself as the context.
+pub trait TransactionalEmail: Serialize {
+    const NAME: &'static str;
+}
+
+/// Every transactional email, whose templates must exist in every locale.
+pub(crate) const TRANSACTIONAL_EMAILS: &[&str] = &[SubscriptionConfirmation::NAME];
+
+/// Asks a new subscriber to confirm their address.
+#[derive(Serialize)]
+pub struct SubscriptionConfirmation<'a> {
+    /// Empty when the author has not uploaded an avatar.
+    pub author_avatar_url: &'a str,
+    pub author_name: &'a str,
+    pub confirmation_link: &'a str,
+    pub subscriber_name: &'a str,
+}
+
+impl TransactionalEmail for SubscriptionConfirmation<'_> {
+    const NAME: &'static str = "subscription_confirmation";
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::Locale;
+    use crate::email_client::{SubscriptionConfirmation, test_email_client};
+
+    fn confirmation(author_avatar_url: &str) -> SubscriptionConfirmation<'_> {
+        SubscriptionConfirmation {
+            author_avatar_url,
+            author_name: "Ursula",
+            confirmation_link: "https://api.test/subscriptions/confirm?subscription_token=abc",
+            subscriber_name: "<Le Guin>",
+        }
+    }
+
+    #[test]
+    fn every_locale_renders_the_confirmation_link_in_both_bodies() {
+        for locale in Locale::ALL {
+            let email = test_email_client()
+                .render_transactional_email("reader@example.com", &confirmation(""), locale)
+                .unwrap();
+
+            assert!(email.subject().contains("Ursula"));
+            assert!(!email.subject().ends_with('\n'));
+            for body in [email.html_body(), email.text_body()] {
+                assert!(
+                    body.contains("https://api.test/subscriptions/confirm?subscription_token=abc")
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn values_are_escaped_in_the_html_body_only() {
+        let email = test_email_client()
+            .render_transactional_email("reader@example.com", &confirmation(""), Locale::En)
+            .unwrap();
+
+        assert!(email.html_body().contains("Hi &lt;Le Guin&gt;,"));
+        assert!(email.text_body().contains("Hi <Le Guin>,"));
+    }
+
+    #[test]
+    fn the_avatar_is_only_shown_when_there_is_one() {
+        let client = test_email_client();
+        let render = |avatar_url| {
+            client
+                .render_transactional_email(
+                    "reader@example.com",
+                    &confirmation(avatar_url),
+                    Locale::En,
+                )
+                .unwrap()
+        };
+
+        assert!(!render("").html_body().contains("<img"));
+        assert!(
+            render("https://cdn.test/avatar.webp")
+                .html_body()
+                .contains(r#"src="https://cdn.test/avatar.webp""#)
+        );
+    }
+}
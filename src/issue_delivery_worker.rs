@@ -1,137 +1,1187 @@
-use crate::models::{NewsletterIssue, NewsletterIssueEmail};
-use crate::{configuration::Settings, startup::get_connection_pool};
-use crate::{domain::SubscriberEmail, email_client::EmailClient};
+use crate::configuration::{Settings, WorkerSettings};
+use crate::domain::{Locale, SubscriberEmail, TrackingToken};
+use crate::email_client::{EmailClient, OutgoingEmail, RenderedNewsletter, SendEmailError};
+use crate::models::{
+    DELIVERY_QUEUE_CHANNEL, DeliveryOutcome, EmailAuthor, EmailBrandingSettings, EmailSuppression,
+    IssueDeliveryLog, NewsletterIssue, NewsletterIssueEmail, Subscription, SubscriptionStatus,
+    UserProfile,
+};
+use crate::startup::get_connection_pool;
+use rand::{Rng, thread_rng};
+use secrecy::SecretString;
+use sqlx::postgres::PgListener;
 use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
 use tracing::{Span, field::display};
 use uuid::Uuid;
 
-pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
+/// Application settings needed to claim queued tasks and to build the links
+/// embedded in each delivery.
+#[derive(Clone)]
+pub struct DeliveryContext {
+    pub base_url: String,
+    pub client_base_url: String,
+    pub hmac_secret: SecretString,
+    /// How long claimed tasks stay hidden from other workers.
+    pub lease_duration: Duration,
+    pub author_emails_per_minute: u32,
+}
+
+impl DeliveryContext {
+    pub fn new(configuration: &Settings) -> Self {
+        Self {
+            base_url: configuration.application.base_url.clone(),
+            client_base_url: configuration.hosts.client.clone(),
+            hmac_secret: configuration.application.hmac_secret.clone(),
+            lease_duration: configuration.worker.lease_duration(),
+            author_emails_per_minute: configuration.worker.author_emails_per_minute.get(),
+        }
+    }
+}
+
+pub async fn run_worker_until_stopped(
+    configuration: Settings,
+    shutdown: CancellationToken,
+) -> Result<(), anyhow::Error> {
     let connection_pool = get_connection_pool(&configuration.database);
-    let email_client = configuration.email_client.client();
-    worker_loop(connection_pool, email_client).await
+    let context = DeliveryContext::new(&configuration);
+    let rate_limiter = configuration
+        .email_client
+        .rate_limiter(&configuration.redis_uri)?;
+    let email_client = Arc::new(
+        configuration
+            .email_client
+            .client()?
+            .with_rate_limiter(rate_limiter),
+    );
+    run_workers(
+        connection_pool,
+        email_client,
+        context,
+        configuration.worker,
+        shutdown,
+    )
+    .await
 }
 
-async fn worker_loop(pool: PgPool, email_client: EmailClient) -> Result<(), anyhow::Error> {
-    loop {
-        match try_execute_task(&pool, &email_client).await {
+/// Runs `settings.concurrency` delivery loops until `shutdown` is cancelled.
+/// Each loop finishes, and acknowledges, the batch it is working on before
+/// exiting, so a shutdown never leaves a sent email in the queue.
+///
+/// Idle loops are woken as soon as new tasks are queued, through a single
+/// listener on [`DELIVERY_QUEUE_CHANNEL`]. The poll interval only matters for
+/// retries coming due and for notifications missed while reconnecting.
+pub async fn run_workers(
+    pool: PgPool,
+    email_client: Arc<EmailClient>,
+    context: DeliveryContext,
+    settings: WorkerSettings,
+    shutdown: CancellationToken,
+) -> Result<(), anyhow::Error> {
+    let wakeup = Arc::new(Notify::new());
+    let mut workers = JoinSet::new();
+    workers.spawn(listen_for_new_tasks(
+        pool.clone(),
+        wakeup.clone(),
+        settings.clone(),
+        shutdown.clone(),
+    ));
+    for _ in 0..settings.concurrency.get() {
+        workers.spawn(worker_loop(
+            pool.clone(),
+            email_client.clone(),
+            context.clone(),
+            settings.clone(),
+            wakeup.clone(),
+            shutdown.clone(),
+        ));
+    }
+    while let Some(outcome) = workers.join_next().await {
+        outcome?;
+    }
+    Ok(())
+}
+
+async fn worker_loop(
+    pool: PgPool,
+    email_client: Arc<EmailClient>,
+    context: DeliveryContext,
+    settings: WorkerSettings,
+    wakeup: Arc<Notify>,
+    shutdown: CancellationToken,
+) {
+    while !shutdown.is_cancelled() {
+        // Register interest before looking at the queue, so that a task queued
+        // right after we found it empty still wakes us up.
+        let notified = wakeup.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        match try_execute_task(&pool, &email_client, &context).await {
+            Ok(ExecutionOutcome::TaskCompleted) => {}
+            Ok(ExecutionOutcome::RateLimited { retry_after }) => {
+                tokio::select! {
+                    _ = shutdown.cancelled() => {}
+                    _ = tokio::time::sleep(retry_after) => {}
+                }
+            }
             Ok(ExecutionOutcome::EmptyQueue) => {
-                tokio::time::sleep(Duration::from_secs(10)).await;
+                tokio::select! {
+                    _ = shutdown.cancelled() => {}
+                    _ = notified => {}
+                    _ = tokio::time::sleep(settings.poll_interval()) => {}
+                }
             }
             Err(_) => {
-                tokio::time::sleep(Duration::from_secs(1)).await;
+                tokio::select! {
+                    _ = shutdown.cancelled() => {}
+                    _ = tokio::time::sleep(settings.error_backoff()) => {}
+                }
             }
-            Ok(ExecutionOutcome::TaskCompleted) => {}
         }
     }
 }
 
+/// Relays notifications on [`DELIVERY_QUEUE_CHANNEL`] to the idle worker
+/// loops. When the connection drops every loop is woken, since notifications
+/// sent in the meantime are lost, and the listener reconnects.
+async fn listen_for_new_tasks(
+    pool: PgPool,
+    wakeup: Arc<Notify>,
+    settings: WorkerSettings,
+    shutdown: CancellationToken,
+) {
+    let mut listener = None;
+    while !shutdown.is_cancelled() {
+        if listener.is_none() {
+            listener = match connect_listener(&pool).await {
+                Ok(connected) => Some(connected),
+                Err(e) => {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "Failed to listen for newly queued deliveries.",
+                    );
+                    None
+                }
+            };
+        }
+        let Some(connected) = listener.as_mut() else {
+            tokio::select! {
+                _ = shutdown.cancelled() => {}
+                _ = tokio::time::sleep(settings.error_backoff()) => {}
+            }
+            continue;
+        };
+        tokio::select! {
+            _ = shutdown.cancelled() => {}
+            notification = connected.try_recv() => match notification {
+                Ok(Some(_)) => wakeup.notify_waiters(),
+                Ok(None) => {
+                    tracing::warn!(
+                        "Lost the connection listening for newly queued deliveries. \
+                            Reconnecting."
+                    );
+                    wakeup.notify_waiters();
+                }
+                Err(e) => {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "Failed to receive a notification about newly queued deliveries.",
+                    );
+                    wakeup.notify_waiters();
+                    listener = None;
+                }
+            },
+        }
+    }
+}
+
+async fn connect_listener(pool: &PgPool) -> Result<PgListener, sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(DELIVERY_QUEUE_CHANNEL).await?;
+    Ok(listener)
+}
+
 pub enum ExecutionOutcome {
     TaskCompleted,
     EmptyQueue,
+    /// Part of the batch was put back in the queue, either by our own rate
+    /// limiter or by the email provider, until `retry_after` has elapsed.
+    RateLimited {
+        retry_after: Duration,
+    },
 }
 
 #[tracing::instrument(
     skip_all,
     fields(
         newsletter_issue_id=tracing::field::Empty,
-        subscriber_email=tracing::field::Empty
+        n_tasks=tracing::field::Empty
     ),
     err
 )]
 pub async fn try_execute_task(
     pool: &PgPool,
     email_client: &EmailClient,
+    context: &DeliveryContext,
 ) -> Result<ExecutionOutcome, anyhow::Error> {
-    let task = dequeue_task(pool).await?;
-    if task.is_none() {
+    if let Some(outcome) = try_send_confirmation_emails(pool, email_client, context).await? {
+        return Ok(outcome);
+    }
+    let tasks = claim_batch(
+        pool,
+        context,
+        email_client.max_batch_size(context.lease_duration),
+    )
+    .await?;
+    if tasks.is_empty() {
         return Ok(ExecutionOutcome::EmptyQueue);
     }
-    let (transaction, issue_id, email) = task.unwrap();
+    let newsletter_issue_id = tasks[0].newsletter_issue_id;
     Span::current()
-        .record("newsletter_issue_id", display(issue_id))
-        .record("subscriber_email", display(&email));
-    match SubscriberEmail::parse(email.clone()) {
-        Ok(email) => {
-            let issue = NewsletterIssue::find_by_newsletter_issue_id(issue_id, pool).await?;
-            let issue: NewsletterIssueEmail = issue.into();
-            if let Err(e) = email_client
-                .send_email(
-                    email.as_ref(),
-                    &issue.title,
-                    &issue.html_content,
-                    &issue.text_content,
+        .record("newsletter_issue_id", display(newsletter_issue_id))
+        .record("n_tasks", tasks.len());
+
+    let PreparedBatch {
+        newsletter,
+        subscriptions,
+        suppressed,
+    } = match prepare_batch(&tasks, pool, email_client, context).await {
+        Ok(batch) => batch,
+        Err(e) => {
+            release_batch(pool, &tasks, &e).await?;
+            return Err(e);
+        }
+    };
+
+    let mut deliverable = vec![];
+    let mut outgoing = vec![];
+    let mut skipped = vec![];
+    for task in tasks {
+        let subscription = subscriptions.get(&task.subscriber_email);
+        let is_suppressed = suppressed.contains(&task.subscriber_email);
+        match prepare_email(&task, subscription, is_suppressed, &newsletter, context) {
+            Ok(email) => {
+                deliverable.push(task);
+                outgoing.push(email);
+            }
+            Err(outcome) => skipped.push((task, outcome)),
+        }
+    }
+
+    // Whatever the rate limiter does not allow right now goes back in the
+    // queue, untouched, until it does.
+    let reservation = email_client.reserve_sends(outgoing.len()).await;
+    let mut retry_after = None;
+    let throttled = if reservation.granted < outgoing.len() {
+        outgoing.truncate(reservation.granted);
+        retry_after = Some(reservation.retry_after);
+        deliverable.split_off(reservation.granted)
+    } else {
+        vec![]
+    };
+
+    let results: Vec<Result<(), SendFailure>> = if outgoing.is_empty() {
+        vec![]
+    } else {
+        match email_client.send_email_batch(&outgoing).await {
+            Ok(results) => results
+                .into_iter()
+                .map(|result| result.map_err(SendFailure::from))
+                .collect(),
+            Err(e) => vec![Err(SendFailure::from(e)); outgoing.len()],
+        }
+    };
+    // Acknowledge the whole batch in one short transaction.
+    let mut transaction = pool.begin().await?;
+    for (task, outcome) in skipped {
+        if delete_task(&mut transaction, &task).await? {
+            log_attempt(&mut transaction, &task, outcome, None).await?;
+        }
+    }
+    for task in &throttled {
+        defer_task(&mut transaction, task, reservation.retry_after).await?;
+    }
+    let mut results = results.into_iter();
+    for task in &deliverable {
+        let result = results.next().unwrap_or_else(|| {
+            Err(SendFailure {
+                error: String::from("The email provider did not report on this message."),
+                is_transient: true,
+                defer_for: None,
+            })
+        });
+        if let Err(SendFailure {
+            defer_for: Some(delay),
+            ..
+        }) = &result
+        {
+            retry_after = retry_after.max(Some(*delay));
+        }
+        complete_task(&mut transaction, task, result).await?;
+    }
+    transaction.commit().await?;
+    Ok(match retry_after {
+        Some(retry_after) => ExecutionOutcome::RateLimited { retry_after },
+        None => ExecutionOutcome::TaskCompleted,
+    })
+}
+
+/// What a claimed batch needs before any of it can be sent.
+struct PreparedBatch {
+    newsletter: RenderedNewsletter,
+    subscriptions: HashMap<String, Subscription>,
+    suppressed: HashSet<String>,
+}
+
+/// Renders the issue the batch belongs to and looks up its recipients.
+async fn prepare_batch(
+    tasks: &[DeliveryTask],
+    pool: &PgPool,
+    email_client: &EmailClient,
+    context: &DeliveryContext,
+) -> Result<PreparedBatch, anyhow::Error> {
+    let mut issue: NewsletterIssueEmail =
+        NewsletterIssue::find_by_newsletter_issue_id(tasks[0].newsletter_issue_id, pool)
+            .await?
+            .into();
+    if UserProfile::email_tracking_enabled(&issue.user_id, pool).await? {
+        issue = issue.with_tracking(&context.base_url, &context.hmac_secret);
+    }
+    let branding =
+        EmailBrandingSettings::find_email_branding_by_user_id(&issue.user_id, pool).await?;
+    let newsletter = email_client
+        .render_newsletter(
+            &issue.title,
+            &issue.html_content,
+            &issue.text_content,
+            true,
+            &branding,
+        )?
+        .with_merge_tags(issue.merge_tags);
+    let emails: Vec<String> = tasks
+        .iter()
+        .map(|task| task.subscriber_email.clone())
+        .collect();
+    let subscriptions = Subscription::find_by_emails_and_user_id(&emails, &issue.user_id, pool)
+        .await?
+        .into_iter()
+        .map(|subscription| (subscription.email.clone(), subscription))
+        .collect();
+    let suppressed = EmailSuppression::find_suppressed(&emails, pool)
+        .await?
+        .into_iter()
+        .collect();
+    Ok(PreparedBatch {
+        newsletter,
+        subscriptions,
+        suppressed,
+    })
+}
+
+/// Hands a batch that could not be prepared back to the queue as a failed
+/// attempt, so that it is retried and eventually dead-lettered like any other
+/// failure rather than waiting out its lease. Nothing was sent, so the claim
+/// no longer counts against the author's sending limit.
+#[tracing::instrument(skip_all)]
+async fn release_batch(
+    pool: &PgPool,
+    tasks: &[DeliveryTask],
+    error: &anyhow::Error,
+) -> Result<(), anyhow::Error> {
+    let failure = SendFailure {
+        error: format!("Failed to prepare the email: {error:#}"),
+        is_transient: true,
+        defer_for: None,
+    };
+    let mut transaction = pool.begin().await?;
+    for task in tasks {
+        complete_task(&mut transaction, task, Err(failure.clone())).await?;
+    }
+    sqlx::query!(
+        r#"
+        UPDATE delivery_author_quotas
+        SET claimed_in_window = GREATEST(claimed_in_window - $2, 0)
+        WHERE user_id = (
+            SELECT user_id
+            FROM newsletter_issues
+            WHERE newsletter_issue_id = $1
+        )
+        "#,
+        tasks[0].newsletter_issue_id,
+        tasks.len() as i32,
+    )
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+/// A confirmation email queued by a subscriber import, along with the
+/// subscription it is for.
+struct ConfirmationTask {
+    locked_by: Uuid,
+    n_retries: i32,
+    subscription_token: String,
+    subscriber_id: Uuid,
+    email: String,
+    locale: String,
+    name: String,
+    status: String,
+    user_id: Uuid,
+}
+
+/// Sends a batch of queued confirmation emails, if any are due, within the
+/// same rate limits as issues. They go first: there are few of them and
+/// readers are waiting on them to finish subscribing.
+#[tracing::instrument(skip_all, fields(n_tasks=tracing::field::Empty), err)]
+async fn try_send_confirmation_emails(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    context: &DeliveryContext,
+) -> Result<Option<ExecutionOutcome>, anyhow::Error> {
+    let tasks = claim_confirmation_emails(
+        pool,
+        context,
+        email_client.max_batch_size(context.lease_duration),
+    )
+    .await?;
+    if tasks.is_empty() {
+        return Ok(None);
+    }
+    Span::current().record("n_tasks", tasks.len());
+
+    let mut authors: HashMap<Uuid, EmailAuthor> = HashMap::new();
+    let mut deliverable = vec![];
+    let mut outgoing = vec![];
+    let mut unsendable = vec![];
+    let mut skipped = vec![];
+    for task in tasks {
+        if task.status != SubscriptionStatus::PendingConfirmation.as_str() {
+            skipped.push(task);
+            continue;
+        }
+        match prepare_confirmation_email(&task, &mut authors, pool, email_client, context).await {
+            Ok(email) => {
+                deliverable.push(task);
+                outgoing.push(email);
+            }
+            Err(failure) => unsendable.push((task, failure)),
+        }
+    }
+
+    let reservation = email_client.reserve_sends(outgoing.len()).await;
+    let mut retry_after = None;
+    let throttled = if reservation.granted < outgoing.len() {
+        outgoing.truncate(reservation.granted);
+        retry_after = Some(reservation.retry_after);
+        deliverable.split_off(reservation.granted)
+    } else {
+        vec![]
+    };
+
+    let results: Vec<Result<(), SendFailure>> = if outgoing.is_empty() {
+        vec![]
+    } else {
+        match email_client.send_email_batch(&outgoing).await {
+            Ok(results) => results
+                .into_iter()
+                .map(|result| result.map_err(SendFailure::from))
+                .collect(),
+            Err(e) => vec![Err(SendFailure::from(e)); outgoing.len()],
+        }
+    };
+    let mut transaction = pool.begin().await?;
+    for task in &skipped {
+        delete_confirmation_email(&mut transaction, task).await?;
+    }
+    for task in &throttled {
+        requeue_confirmation_email(&mut transaction, task, reservation.retry_after, false).await?;
+    }
+    for (task, failure) in unsendable {
+        complete_confirmation_email(&mut transaction, &task, Err(failure)).await?;
+    }
+    let mut results = results.into_iter();
+    for task in &deliverable {
+        let result = results.next().unwrap_or_else(|| {
+            Err(SendFailure {
+                error: String::from("The email provider did not report on this message."),
+                is_transient: true,
+                defer_for: None,
+            })
+        });
+        if let Err(SendFailure {
+            defer_for: Some(delay),
+            ..
+        }) = &result
+        {
+            retry_after = retry_after.max(Some(*delay));
+        }
+        complete_confirmation_email(&mut transaction, task, result).await?;
+    }
+    transaction.commit().await?;
+    Ok(Some(match retry_after {
+        Some(retry_after) => ExecutionOutcome::RateLimited { retry_after },
+        None => ExecutionOutcome::TaskCompleted,
+    }))
+}
+
+async fn prepare_confirmation_email(
+    task: &ConfirmationTask,
+    authors: &mut HashMap<Uuid, EmailAuthor>,
+    pool: &PgPool,
+    email_client: &EmailClient,
+    context: &DeliveryContext,
+) -> Result<OutgoingEmail, SendFailure> {
+    if let Entry::Vacant(entry) = authors.entry(task.user_id) {
+        let author = EmailAuthor::find_by_user_id(&task.user_id, pool)
+            .await
+            .map_err(|e| SendFailure {
+                error: e.to_string(),
+                is_transient: true,
+                defer_for: None,
+            })?;
+        entry.insert(author);
+    }
+    let subscription = Subscription {
+        id: task.subscriber_id,
+        email: task.email.clone(),
+        name: task.name.clone(),
+        status: task.status.clone(),
+    };
+    subscription
+        .render_confirmation_email(
+            email_client,
+            &context.client_base_url,
+            &task.subscription_token,
+            &authors[&task.user_id],
+            Locale::parse(&task.locale).unwrap_or_default(),
+        )
+        .map_err(|e| SendFailure {
+            error: e.to_string(),
+            is_transient: false,
+            defer_for: None,
+        })
+}
+
+/// Removes, retries or gives up on a confirmation email. Those given up on
+/// are not kept: subscribing again sends a new one.
+async fn complete_confirmation_email(
+    transaction: &mut PgTransaction,
+    task: &ConfirmationTask,
+    result: Result<(), SendFailure>,
+) -> Result<(), anyhow::Error> {
+    match result {
+        Ok(()) => {
+            delete_confirmation_email(transaction, task).await?;
+        }
+        Err(SendFailure {
+            defer_for: Some(delay),
+            ..
+        }) => {
+            requeue_confirmation_email(transaction, task, delay, false).await?;
+        }
+        Err(failure) if failure.is_transient && task.n_retries < MAX_RETRIES => {
+            let delay = retry_delay(task.n_retries);
+            tracing::warn!(
+                error.message = %failure.error,
+                subscriber_email = %task.email,
+                n_retries = task.n_retries,
+                "Failed to send a confirmation email. Retrying in {} seconds.",
+                delay.as_secs(),
+            );
+            requeue_confirmation_email(transaction, task, delay, true).await?;
+        }
+        Err(failure) => {
+            tracing::error!(
+                error.message = %failure.error,
+                subscriber_email = %task.email,
+                n_retries = task.n_retries,
+                "Failed to send a confirmation email. Giving up.",
+            );
+            delete_confirmation_email(transaction, task).await?;
+        }
+    }
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn claim_confirmation_emails(
+    pool: &PgPool,
+    context: &DeliveryContext,
+    max_tasks: usize,
+) -> Result<Vec<ConfirmationTask>, anyhow::Error> {
+    let tasks = sqlx::query_as!(
+        ConfirmationTask,
+        r#"
+        WITH claimable AS (
+            SELECT subscription_token
+            FROM confirmation_email_queue
+            WHERE
+                execute_after <= now() AND
+                (locked_until IS NULL OR locked_until < now())
+            ORDER BY execute_after
+            FOR UPDATE
+            SKIP LOCKED
+            LIMIT $1
+        )
+        UPDATE confirmation_email_queue q
+        SET
+            locked_until = now() + make_interval(secs => $2),
+            locked_by = $3
+        FROM claimable, subscription_tokens t, subscriptions s
+        WHERE
+            q.subscription_token = claimable.subscription_token AND
+            t.subscription_token = q.subscription_token AND
+            s.id = t.subscriber_id
+        RETURNING
+            q.locked_by AS "locked_by!",
+            q.n_retries,
+            q.subscription_token,
+            s.id AS subscriber_id,
+            s.email,
+            s.locale,
+            s.name,
+            s.status,
+            s.user_id
+        "#,
+        i64::try_from(max_tasks).unwrap_or(i64::MAX),
+        context.lease_duration.as_secs_f64(),
+        Uuid::new_v4(),
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(tasks)
+}
+
+#[tracing::instrument(skip_all)]
+async fn delete_confirmation_email(
+    transaction: &mut PgTransaction,
+    task: &ConfirmationTask,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM confirmation_email_queue
+        WHERE subscription_token = $1 AND locked_by = $2
+        "#,
+        task.subscription_token,
+        task.locked_by
+    )
+    .execute(&mut **transaction)
+    .await?;
+    Ok(())
+}
+
+/// Puts the email back in the queue for `delay`, counting a retry unless it
+/// was only held back by rate limits.
+#[tracing::instrument(skip_all)]
+async fn requeue_confirmation_email(
+    transaction: &mut PgTransaction,
+    task: &ConfirmationTask,
+    delay: Duration,
+    count_retry: bool,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE confirmation_email_queue
+        SET
+            n_retries = n_retries + $3,
+            execute_after = now() + make_interval(secs => $4),
+            locked_until = NULL,
+            locked_by = NULL
+        WHERE subscription_token = $1 AND locked_by = $2
+        "#,
+        task.subscription_token,
+        task.locked_by,
+        i32::from(count_retry),
+        delay.as_secs_f64()
+    )
+    .execute(&mut **transaction)
+    .await?;
+    Ok(())
+}
+
+/// Builds the email for a task, or explains why the subscriber must be skipped.
+fn prepare_email(
+    task: &DeliveryTask,
+    subscription: Option<&Subscription>,
+    is_suppressed: bool,
+    newsletter: &RenderedNewsletter,
+    context: &DeliveryContext,
+) -> Result<OutgoingEmail, DeliveryOutcome> {
+    if let Err(e) = SubscriberEmail::parse(task.subscriber_email.clone()) {
+        tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            subscriber_email = %task.subscriber_email,
+            "Skipping a confirmed subscriber. \
+                Their stored contact details are invalid",
+        );
+        return Err(DeliveryOutcome::SkippedInvalid);
+    }
+    match subscription {
+        Some(_) if is_suppressed => {
+            tracing::info!(
+                subscriber_email = %task.subscriber_email,
+                "Skipping a subscriber whose address has been suppressed \
+                    after a bounce or spam complaint.",
+            );
+            Err(DeliveryOutcome::Suppressed)
+        }
+        Some(subscription) if subscription.is_confirmed() => {
+            let unsubscribe_links = subscription.unsubscribe_links(
+                &context.base_url,
+                &context.client_base_url,
+                &context.hmac_secret,
+            );
+            let tracking_token = TrackingToken::generate(
+                &task.newsletter_issue_id,
+                &subscription.id,
+                &context.hmac_secret,
+            );
+            Ok(newsletter.personalise(
+                &task.subscriber_email,
+                Some(&subscription.name),
+                Some(tracking_token.as_ref()),
+                Some(&unsubscribe_links),
+            ))
+        }
+        _ => {
+            tracing::info!(
+                subscriber_email = %task.subscriber_email,
+                "Skipping a subscriber who is no longer confirmed \
+                    since the issue was published.",
+            );
+            Err(DeliveryOutcome::SkippedUnconfirmed)
+        }
+    }
+}
+
+/// Why a single email of a batch could not be delivered.
+#[derive(Clone)]
+struct SendFailure {
+    error: String,
+    is_transient: bool,
+    /// Set when the provider is rate limiting us: how long to put the task
+    /// aside for, without counting the attempt against its retries.
+    defer_for: Option<Duration>,
+}
+
+impl From<SendEmailError> for SendFailure {
+    fn from(e: SendEmailError) -> Self {
+        let defer_for = match &e {
+            SendEmailError::RateLimited { retry_after } => {
+                Some(retry_after.unwrap_or(BASE_RETRY_DELAY).min(MAX_RETRY_DELAY))
+            }
+            _ => None,
+        };
+        Self {
+            is_transient: e.is_transient(),
+            error: e.to_string(),
+            defer_for,
+        }
+    }
+}
+
+/// Records the outcome of a send and removes, reschedules or dead-letters
+/// the task accordingly. Nothing is recorded if the lease expired and another
+/// worker claimed the task in the meantime.
+async fn complete_task(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    result: Result<(), SendFailure>,
+) -> Result<(), anyhow::Error> {
+    match result {
+        Ok(()) => {
+            if delete_task(transaction, task).await? {
+                log_attempt(transaction, task, DeliveryOutcome::Sent, None).await?;
+            }
+        }
+        Err(SendFailure {
+            error,
+            defer_for: Some(delay),
+            ..
+        }) => {
+            tracing::warn!(
+                error.message = %error,
+                subscriber_email = %task.subscriber_email,
+                "The email provider is rate limiting us. \
+                    Retrying in {} seconds.",
+                delay.as_secs(),
+            );
+            if defer_task(transaction, task, delay).await? {
+                log_attempt(transaction, task, DeliveryOutcome::Retried, Some(&error)).await?;
+            }
+        }
+        Err(failure) if failure.is_transient && task.n_retries < MAX_RETRIES => {
+            let delay = retry_delay(task.n_retries);
+            tracing::warn!(
+                error.message = %failure.error,
+                subscriber_email = %task.subscriber_email,
+                n_retries = task.n_retries,
+                "Failed to deliver issue to a confirmed subscriber. \
+                    Retrying in {} seconds.",
+                delay.as_secs(),
+            );
+            if reschedule_task(transaction, task, delay).await? {
+                log_attempt(
+                    transaction,
+                    task,
+                    DeliveryOutcome::Retried,
+                    Some(&failure.error),
                 )
-                .await
-            {
-                tracing::error!(
-                    error.cause_chain = ?e,
-                    error.message = %e,
-                    "Failed to deliver issue to a confirmed subscriber. \
-                        Skipping.",
-                );
+                .await?;
             }
         }
-        Err(e) => {
+        Err(failure) => {
             tracing::error!(
-                error.cause_chain = ?e,
-                error.message = %e,
-                "Skipping a confirmed subscriber. \
-                    Their stored contact details are invalid",
+                error.message = %failure.error,
+                subscriber_email = %task.subscriber_email,
+                n_retries = task.n_retries,
+                "Failed to deliver issue to a confirmed subscriber. \
+                    Giving up.",
             );
+            if fail_task(transaction, task, &failure.error).await? {
+                log_attempt(
+                    transaction,
+                    task,
+                    DeliveryOutcome::Failed,
+                    Some(&failure.error),
+                )
+                .await?;
+            }
         }
     }
-    delete_task(transaction, issue_id, &email).await?;
-    Ok(ExecutionOutcome::TaskCompleted)
+    Ok(())
+}
+
+/// Number of times a transient failure is retried before the task is moved to
+/// `issue_delivery_failures`.
+const MAX_RETRIES: i32 = 5;
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(30);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// Exponential backoff with up to 50% random jitter, so that a provider outage
+/// does not see every task retry at the same instant.
+fn retry_delay(n_retries: i32) -> Duration {
+    let exponential = BASE_RETRY_DELAY.saturating_mul(2u32.saturating_pow(n_retries as u32));
+    let delay = exponential.min(MAX_RETRY_DELAY);
+    let jitter = thread_rng().gen_range(0.0..0.5);
+    delay + delay.mul_f64(jitter)
 }
 
 type PgTransaction = Transaction<'static, Postgres>;
 
+struct DeliveryTask {
+    /// Identifies the claim, so that a worker whose lease expired cannot
+    /// acknowledge a task another worker has since claimed.
+    locked_by: Uuid,
+    n_retries: i32,
+    newsletter_issue_id: Uuid,
+    subscriber_email: String,
+}
+
+/// Leases up to `max_tasks` due tasks, all belonging to the same issue
+/// so that it only has to be rendered once. The claim commits straight away:
+/// no lock is held while the emails are sent, and the tasks become claimable
+/// again once the lease expires, in case this worker dies before
+/// acknowledging them. Issues whose delivery is paused are skipped.
+///
+/// Authors take turns: the batch goes to the author who was served least
+/// recently and still has room under their per-minute sending limit. Locking
+/// the author's quota row also keeps concurrent workers on different authors.
 #[tracing::instrument(skip_all)]
-async fn dequeue_task(
+async fn claim_batch(
     pool: &PgPool,
-) -> Result<Option<(PgTransaction, Uuid, String)>, anyhow::Error> {
+    context: &DeliveryContext,
+    max_tasks: usize,
+) -> Result<Vec<DeliveryTask>, anyhow::Error> {
+    let emails_per_minute = i32::try_from(context.author_emails_per_minute).unwrap_or(i32::MAX);
     let mut transaction = pool.begin().await?;
-    let r = sqlx::query!(
+    let Some(author) = sqlx::query!(
         r#"
-        SELECT newsletter_issue_id, subscriber_email
-        FROM issue_delivery_queue
+        SELECT
+            a.user_id,
+            CASE
+                WHEN a.window_started_at <= now() - interval '1 minute' THEN 0
+                ELSE a.claimed_in_window
+            END AS "claimed_in_window!"
+        FROM delivery_author_quotas a
+        WHERE
+            (
+                a.window_started_at <= now() - interval '1 minute' OR
+                a.claimed_in_window < $1
+            ) AND
+            EXISTS (
+                SELECT 1
+                FROM issue_delivery_queue q
+                JOIN newsletter_issues n
+                    ON n.newsletter_issue_id = q.newsletter_issue_id
+                WHERE
+                    n.user_id = a.user_id AND
+                    n.delivery_paused_at IS NULL AND
+                    q.execute_after <= now() AND
+                    (q.locked_until IS NULL OR q.locked_until < now())
+            )
+        ORDER BY a.last_claimed_at NULLS FIRST
         FOR UPDATE
         SKIP LOCKED
         LIMIT 1
         "#,
+        emails_per_minute,
     )
     .fetch_optional(&mut *transaction)
+    .await?
+    else {
+        return Ok(vec![]);
+    };
+    let allowance = (emails_per_minute - author.claimed_in_window)
+        .min(i32::try_from(max_tasks).unwrap_or(i32::MAX));
+
+    let tasks = sqlx::query_as!(
+        DeliveryTask,
+        r#"
+        WITH claimable AS (
+            SELECT newsletter_issue_id, subscriber_email
+            FROM issue_delivery_queue
+            WHERE
+                execute_after <= now() AND
+                (locked_until IS NULL OR locked_until < now()) AND
+                newsletter_issue_id = (
+                    SELECT q.newsletter_issue_id
+                    FROM issue_delivery_queue q
+                    JOIN newsletter_issues n
+                        ON n.newsletter_issue_id = q.newsletter_issue_id
+                    WHERE
+                        n.user_id = $4 AND
+                        n.delivery_paused_at IS NULL AND
+                        q.execute_after <= now() AND
+                        (q.locked_until IS NULL OR q.locked_until < now())
+                    ORDER BY n.published_at
+                    LIMIT 1
+                )
+            FOR UPDATE
+            SKIP LOCKED
+            LIMIT $1
+        )
+        UPDATE issue_delivery_queue q
+        SET
+            locked_until = now() + make_interval(secs => $2),
+            locked_by = $3
+        FROM claimable
+        WHERE
+            q.newsletter_issue_id = claimable.newsletter_issue_id AND
+            q.subscriber_email = claimable.subscriber_email
+        RETURNING
+            q.locked_by AS "locked_by!",
+            q.n_retries,
+            q.newsletter_issue_id,
+            q.subscriber_email
+        "#,
+        i64::from(allowance),
+        context.lease_duration.as_secs_f64(),
+        Uuid::new_v4(),
+        author.user_id,
+    )
+    .fetch_all(&mut *transaction)
     .await?;
-    if let Some(r) = r {
-        Ok(Some((
-            transaction,
-            r.newsletter_issue_id,
-            r.subscriber_email,
-        )))
-    } else {
-        Ok(None)
-    }
+
+    sqlx::query!(
+        r#"
+        UPDATE delivery_author_quotas
+        SET
+            last_claimed_at = now(),
+            claimed_in_window = CASE
+                WHEN window_started_at <= now() - interval '1 minute' THEN $2
+                ELSE claimed_in_window + $2
+            END,
+            window_started_at = CASE
+                WHEN window_started_at <= now() - interval '1 minute' THEN now()
+                ELSE window_started_at
+            END
+        WHERE user_id = $1
+        "#,
+        author.user_id,
+        tasks.len() as i32,
+    )
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+    Ok(tasks)
+}
+
+async fn log_attempt(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    outcome: DeliveryOutcome,
+    error: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    IssueDeliveryLog::record(
+        &task.newsletter_issue_id,
+        &task.subscriber_email,
+        outcome,
+        error,
+        transaction,
+    )
+    .await?;
+    Ok(())
 }
 
 #[tracing::instrument(skip_all)]
 async fn delete_task(
-    mut transaction: PgTransaction,
-    issue_id: Uuid,
-    email: &str,
-) -> Result<(), anyhow::Error> {
-    sqlx::query!(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
         r#"
         DELETE FROM issue_delivery_queue
         WHERE 
             newsletter_issue_id = $1 AND
-            subscriber_email = $2 
+            subscriber_email = $2 AND
+            locked_by = $3
         "#,
-        issue_id,
-        email
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        task.locked_by
     )
-    .execute(&mut *transaction)
+    .execute(&mut **transaction)
     .await?;
-    transaction.commit().await?;
-    Ok(())
+    Ok(is_lease_held(task, result.rows_affected()))
+}
+
+/// Whether an acknowledgement found the task still leased to us.
+fn is_lease_held(task: &DeliveryTask, rows_affected: u64) -> bool {
+    if rows_affected == 0 {
+        tracing::warn!(
+            subscriber_email = %task.subscriber_email,
+            "The lease on a delivery expired before it was acknowledged. \
+                Another worker may deliver it again.",
+        );
+    }
+    rows_affected > 0
+}
+
+#[tracing::instrument(skip_all)]
+async fn reschedule_task(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    delay: Duration,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue
+        SET
+            n_retries = n_retries + 1,
+            execute_after = now() + make_interval(secs => $3),
+            locked_until = NULL,
+            locked_by = NULL
+        WHERE
+            newsletter_issue_id = $1 AND
+            subscriber_email = $2 AND
+            locked_by = $4
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        delay.as_secs_f64(),
+        task.locked_by
+    )
+    .execute(&mut **transaction)
+    .await?;
+    Ok(is_lease_held(task, result.rows_affected()))
+}
+
+/// Puts the task back in the queue for `delay` without counting a retry, for
+/// sends that were held back or refused because of rate limits.
+#[tracing::instrument(skip_all)]
+async fn defer_task(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    delay: Duration,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue
+        SET
+            execute_after = now() + make_interval(secs => $3),
+            locked_until = NULL,
+            locked_by = NULL
+        WHERE
+            newsletter_issue_id = $1 AND
+            subscriber_email = $2 AND
+            locked_by = $4
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        delay.as_secs_f64(),
+        task.locked_by
+    )
+    .execute(&mut **transaction)
+    .await?;
+    Ok(is_lease_held(task, result.rows_affected()))
+}
+
+/// Moves the task to the dead-letter table, where authors can inspect and
+/// requeue it.
+#[tracing::instrument(skip_all)]
+async fn fail_task(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    error: &str,
+) -> Result<bool, anyhow::Error> {
+    if !delete_task(transaction, task).await? {
+        return Ok(false);
+    }
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_failures (
+            newsletter_issue_id,
+            subscriber_email,
+            n_retries,
+            error
+        )
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (newsletter_issue_id, subscriber_email) DO UPDATE
+        SET
+            n_retries = EXCLUDED.n_retries,
+            error = EXCLUDED.error,
+            failed_at = now()
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        task.n_retries,
+        error
+    )
+    .execute(&mut **transaction)
+    .await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BASE_RETRY_DELAY, MAX_RETRY_DELAY, retry_delay};
+
+    #[test]
+    fn retry_delay_grows_exponentially_with_bounded_jitter() {
+        for n_retries in 0..4 {
+            let expected = BASE_RETRY_DELAY * 2u32.pow(n_retries as u32);
+            let delay = retry_delay(n_retries);
+            assert!(delay >= expected);
+            assert!(delay <= expected.mul_f64(1.5));
+        }
+    }
+
+    #[test]
+    fn retry_delay_is_capped() {
+        let delay = retry_delay(30);
+        assert!(delay >= MAX_RETRY_DELAY);
+        assert!(delay <= MAX_RETRY_DELAY.mul_f64(1.5));
+    }
 }
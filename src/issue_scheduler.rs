@@ -0,0 +1,78 @@
+use crate::configuration::{Settings, WorkerSettings};
+use crate::models::NewsletterIssue;
+use crate::startup::get_connection_pool;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+use tracing::{Span, field::display};
+
+pub async fn run_scheduler_until_stopped(
+    configuration: Settings,
+    shutdown: CancellationToken,
+) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database);
+    scheduler_loop(connection_pool, configuration.worker, shutdown).await;
+    Ok(())
+}
+
+pub enum SchedulerOutcome {
+    TaskCompleted,
+    EmptyQueue,
+}
+
+async fn scheduler_loop(pool: PgPool, settings: WorkerSettings, shutdown: CancellationToken) {
+    while !shutdown.is_cancelled() {
+        let pause = match try_publish_scheduled_issue(&pool).await {
+            Ok(SchedulerOutcome::EmptyQueue) => settings.poll_interval(),
+            Err(_) => settings.error_backoff(),
+            Ok(SchedulerOutcome::TaskCompleted) => continue,
+        };
+        tokio::select! {
+            _ = shutdown.cancelled() => {}
+            _ = tokio::time::sleep(pause) => {}
+        }
+    }
+}
+
+/// Publishes the most overdue scheduled issue, if any, exactly like
+/// `PUT /admin/newsletters/{id}/publish` would. The issue row stays locked
+/// until the transaction commits, so concurrent schedulers cannot enqueue it
+/// twice. Issues that no longer pass validation have their schedule cleared
+/// and remain drafts.
+#[tracing::instrument(skip_all, fields(newsletter_issue_id=tracing::field::Empty), err)]
+pub async fn try_publish_scheduled_issue(pool: &PgPool) -> Result<SchedulerOutcome, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let Some(issue) = NewsletterIssue::find_due_scheduled_txn(&mut transaction).await? else {
+        return Ok(SchedulerOutcome::EmptyQueue);
+    };
+    let newsletter_issue_id = issue.newsletter_issue_id;
+    let user_id = issue.user_id;
+    Span::current().record("newsletter_issue_id", display(newsletter_issue_id));
+
+    match issue.validate_for_publish() {
+        Ok(issue) => {
+            issue.publish_newsletter(&mut transaction).await?;
+            NewsletterIssue::enqueue_delivery_tasks(
+                &mut transaction,
+                newsletter_issue_id,
+                &user_id,
+            )
+            .await?;
+        }
+        Err(e) => {
+            tracing::error!(
+                error.message = %e,
+                "Cancelling the schedule of an issue that can no longer be published.",
+            );
+            NewsletterIssue::find_by_user_id_and_newsletter_issue_id_txn(
+                user_id,
+                &newsletter_issue_id,
+                &mut transaction,
+            )
+            .await?
+            .schedule(None, &mut transaction)
+            .await?;
+        }
+    }
+    transaction.commit().await?;
+    Ok(SchedulerOutcome::TaskCompleted)
+}
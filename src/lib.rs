@@ -6,7 +6,9 @@ pub mod domain;
 pub mod email_client;
 pub mod idempotency;
 pub mod issue_delivery_worker;
+pub mod issue_scheduler;
 pub mod models;
+pub mod rate_limiter;
 pub mod routes;
 pub mod session_state;
 pub mod startup;
@@ -1,9 +1,12 @@
 use newsletter_api::configuration::get_configuration;
 use newsletter_api::issue_delivery_worker::run_worker_until_stopped;
+use newsletter_api::issue_scheduler::run_scheduler_until_stopped;
 use newsletter_api::startup::Application;
 use newsletter_api::telemetry::{get_subscriber, init_subscriber};
 use std::fmt::{Debug, Display};
-use tokio::task::JoinError;
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::task::{JoinError, JoinHandle};
+use tokio_util::sync::CancellationToken;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -11,18 +14,46 @@ async fn main() -> anyhow::Result<()> {
     init_subscriber(subscriber);
 
     let configuration = get_configuration().expect("Failed to read configuration.");
+    let shutdown = CancellationToken::new();
+    tokio::spawn(cancel_on_shutdown_signal(shutdown.clone()));
+
     let application = Application::build(configuration.clone()).await?;
-    let application_task = tokio::spawn(application.run_until_stopped());
-    let worker_task = tokio::spawn(run_worker_until_stopped(configuration));
+    let application_task = tokio::spawn(application.run_until_stopped(shutdown.clone()));
+    let worker_task = tokio::spawn(run_worker_until_stopped(
+        configuration.clone(),
+        shutdown.clone(),
+    ));
+    let scheduler_task = tokio::spawn(run_scheduler_until_stopped(configuration, shutdown.clone()));
 
-    tokio::select! {
-        o = application_task => report_exit("API", o),
-        o = worker_task =>  report_exit("Background worker", o),
-    };
+    // Whichever task exits first brings the others down with it, but each one
+    // is given the chance to finish its in-flight work before the process ends.
+    tokio::join!(
+        supervise("API", application_task, shutdown.clone()),
+        supervise("Background worker", worker_task, shutdown.clone()),
+        supervise("Publishing scheduler", scheduler_task, shutdown),
+    );
 
     Ok(())
 }
 
+async fn cancel_on_shutdown_signal(shutdown: CancellationToken) {
+    let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM.");
+    tokio::select! {
+        _ = terminate.recv() => tracing::info!("Received SIGTERM, shutting down"),
+        _ = tokio::signal::ctrl_c() => tracing::info!("Received SIGINT, shutting down"),
+    }
+    shutdown.cancel();
+}
+
+async fn supervise<E: Debug + Display>(
+    task_name: &str,
+    task: JoinHandle<Result<(), E>>,
+    shutdown: CancellationToken,
+) {
+    report_exit(task_name, task.await);
+    shutdown.cancel();
+}
+
 fn report_exit(task_name: &str, outcome: Result<Result<(), impl Debug + Display>, JoinError>) {
     match outcome {
         Ok(Ok(())) => {
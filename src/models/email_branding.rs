@@ -0,0 +1,242 @@
+use crate::domain::user_profile::{AccentColor, PublicationName};
+use crate::domain::{ImageUrl, SubscriberEmail};
+use crate::email_client::EmailBranding;
+use crate::utils::{e400, e500, is_too_long};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+/// The branding settings an author edits, stored with their profile. The logo
+/// is uploaded separately.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EmailBrandingSettings {
+    pub accent_color: String,
+    pub footer_text: String,
+    pub postal_address: String,
+    pub publication_name: String,
+    /// Empty to have replies go to the sender address.
+    pub reply_to_email: String,
+    pub user_id: Uuid,
+}
+
+impl EmailBrandingSettings {
+    pub fn validate(self) -> Result<EmailBrandingSettings, String> {
+        let accent_color = AccentColor::parse(self.accent_color)?.as_ref().to_string();
+        let publication_name = PublicationName::parse(self.publication_name)?
+            .as_ref()
+            .to_string();
+        if is_too_long(&self.footer_text, 500) {
+            return Err(String::from("Footer text exceeds character limit."));
+        }
+        if is_too_long(&self.postal_address, 200) {
+            return Err(String::from("Postal address exceeds character limit."));
+        }
+        let reply_to_email = match self.reply_to_email.trim() {
+            "" => String::new(),
+            email => SubscriberEmail::parse(email.to_string())?
+                .as_ref()
+                .to_string(),
+        };
+
+        Ok(Self {
+            accent_color,
+            footer_text: self.footer_text,
+            postal_address: self.postal_address,
+            publication_name,
+            reply_to_email,
+            user_id: self.user_id,
+        })
+    }
+
+    pub async fn update(&self, db_pool: &PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+              UPDATE user_profiles
+              SET accent_color = $1,
+                  email_footer_text = $2,
+                  postal_address = $3,
+                  publication_name = $4,
+                  reply_to_email = $5
+              WHERE user_id = $6
+            "#,
+            self.accent_color,
+            self.footer_text,
+            self.postal_address,
+            self.publication_name,
+            self.reply_to_email,
+            self.user_id
+        )
+        .execute(db_pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn find_email_branding_api_by_user_id(
+        user_id: &Uuid,
+        pool: &PgPool,
+    ) -> Result<EmailBrandingAPI, sqlx::Error> {
+        sqlx::query_as!(
+            EmailBrandingAPI,
+            r#"
+              SELECT
+                accent_color,
+                email_footer_text AS footer_text,
+                email_logo_url AS logo_url,
+                postal_address,
+                publication_name,
+                reply_to_email
+              FROM user_profiles
+              WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_one(pool)
+        .await
+    }
+
+    /// The branding issues are rendered in. Issues are sent under the
+    /// author's name until they set a publication name, or their username
+    /// should they have no profile yet.
+    pub async fn find_email_branding_by_user_id(
+        user_id: &Uuid,
+        pool: &PgPool,
+    ) -> Result<EmailBranding, sqlx::Error> {
+        sqlx::query_as!(
+            EmailBranding,
+            r#"
+              SELECT
+                COALESCE(accent_color, '') AS "accent_color!",
+                COALESCE(email_footer_text, '') AS "footer_text!",
+                COALESCE(email_logo_url, '') AS "logo_url!",
+                COALESCE(postal_address, '') AS "postal_address!",
+                COALESCE(
+                  NULLIF(publication_name, ''),
+                  NULLIF(display_name, ''),
+                  username
+                ) AS "publication_name!",
+                COALESCE(reply_to_email, '') AS "reply_to!"
+              FROM users
+              LEFT JOIN user_profiles ON users.user_id = user_profiles.user_id
+              WHERE users.user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_one(pool)
+        .await
+    }
+
+    pub async fn set_logo(
+        user_id: &Uuid,
+        s3_base_url: &str,
+        pool: &PgPool,
+    ) -> Result<(), actix_web::Error> {
+        let logo_url = format!("{s3_base_url}/images/user/email_logo/{user_id}.png");
+        let logo_url = ImageUrl::parse(logo_url)
+            .map_err(e400)?
+            .as_ref()
+            .to_string();
+
+        Self::update_logo(user_id, &logo_url, pool)
+            .await
+            .context("Failed to update the email logo.")
+            .map_err(e500)?;
+
+        Ok(())
+    }
+
+    async fn update_logo(
+        user_id: &Uuid,
+        logo_url: &String,
+        db_pool: &PgPool,
+    ) -> Result<(), sqlx::Error> {
+        let timestamp: u64 = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_secs();
+        let logo_url = format!("{logo_url}?v={timestamp}");
+
+        sqlx::query!(
+            r#"
+              UPDATE user_profiles
+              SET email_logo_url = $1
+              WHERE user_id = $2
+            "#,
+            logo_url,
+            user_id
+        )
+        .execute(db_pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EmailBrandingAPI {
+    pub accent_color: String,
+    pub footer_text: String,
+    pub logo_url: String,
+    pub postal_address: String,
+    pub publication_name: String,
+    pub reply_to_email: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::EmailBrandingSettings;
+    use claims::{assert_err, assert_ok};
+    use uuid::Uuid;
+
+    fn settings() -> EmailBrandingSettings {
+        EmailBrandingSettings {
+            accent_color: "#1d4ed8".to_string(),
+            footer_text: "Thanks for reading.".to_string(),
+            postal_address: "1 Library Lane, Portland, OR".to_string(),
+            publication_name: "Earthsea Dispatches".to_string(),
+            reply_to_email: "ursula@example.com".to_string(),
+            user_id: Uuid::new_v4(),
+        }
+    }
+
+    #[test]
+    fn valid_settings_are_accepted() {
+        assert_ok!(settings().validate());
+    }
+
+    #[test]
+    fn every_setting_may_be_left_empty() {
+        let settings = EmailBrandingSettings {
+            accent_color: "".to_string(),
+            footer_text: "".to_string(),
+            postal_address: "".to_string(),
+            publication_name: "".to_string(),
+            reply_to_email: " ".to_string(),
+            ..settings()
+        };
+
+        assert_eq!("", settings.validate().unwrap().reply_to_email);
+    }
+
+    #[test]
+    fn an_invalid_reply_to_address_is_rejected() {
+        let settings = EmailBrandingSettings {
+            reply_to_email: "ursula.example.com".to_string(),
+            ..settings()
+        };
+
+        assert_err!(settings.validate());
+    }
+
+    #[test]
+    fn an_overly_long_footer_is_rejected() {
+        let settings = EmailBrandingSettings {
+            footer_text: "a".repeat(501),
+            ..settings()
+        };
+
+        assert_err!(settings.validate());
+    }
+}
@@ -0,0 +1,101 @@
+use crate::domain::SubscriberEmail;
+use crate::models::SubscriptionStatus;
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+
+pub enum SuppressionReason {
+    Bounced,
+    Complained,
+}
+
+impl SuppressionReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuppressionReason::Bounced => "bounced",
+            SuppressionReason::Complained => "complained",
+        }
+    }
+
+    fn subscription_status(&self) -> SubscriptionStatus {
+        match self {
+            SuppressionReason::Bounced => SubscriptionStatus::Bounced,
+            SuppressionReason::Complained => SubscriptionStatus::Complained,
+        }
+    }
+}
+
+/// An address the email provider told us to stop sending to. Addresses are
+/// stored and compared in their normalised form.
+pub struct EmailSuppression {
+    pub details: Option<String>,
+    pub email: SubscriberEmail,
+    pub reason: SuppressionReason,
+}
+
+impl EmailSuppression {
+    pub async fn is_suppressed(email: &str, pool: &PgPool) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"
+              SELECT EXISTS(
+                SELECT 1 FROM email_suppressions WHERE email = LOWER($1)
+              ) AS "exists!"
+            "#,
+            email
+        )
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Returns which of the given addresses are suppressed, as they were given.
+    pub async fn find_suppressed(
+        emails: &[String],
+        pool: &PgPool,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"
+              SELECT email AS "email!"
+              FROM UNNEST($1::text[]) AS given(email)
+              WHERE LOWER(email) IN (SELECT email FROM email_suppressions)
+            "#,
+            emails
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Records the suppression and flags every subscription using the address,
+    /// across all authors, with the matching status.
+    pub async fn store(
+        &self,
+        transaction: &mut Transaction<'_, Postgres>,
+    ) -> Result<(), sqlx::Error> {
+        let email = self.email.normalised();
+        transaction
+            .execute(sqlx::query!(
+                r#"
+                  INSERT INTO email_suppressions (email, reason, details)
+                  VALUES ($1, $2, $3)
+                  ON CONFLICT (email) DO UPDATE
+                  SET reason = EXCLUDED.reason,
+                    details = EXCLUDED.details,
+                    suppressed_at = now()
+                "#,
+                &email,
+                self.reason.as_str(),
+                self.details.as_deref()
+            ))
+            .await?;
+        transaction
+            .execute(sqlx::query!(
+                r#"
+                  UPDATE subscriptions
+                  SET status = $1
+                  WHERE LOWER(email) = $2
+                "#,
+                self.reason.subscription_status().as_str(),
+                &email
+            ))
+            .await?;
+
+        Ok(())
+    }
+}
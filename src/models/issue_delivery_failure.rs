@@ -0,0 +1,92 @@
+use crate::models::{DeliveryOutcome, NewsletterIssue};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// A delivery the worker gave up on, either because the email provider
+/// rejected it outright or because it kept failing after every retry.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IssueDeliveryFailureAPI {
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+    pub n_retries: i32,
+    pub subscriber_email: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IssueDeliveryRequeueAPI {
+    pub requeued: u64,
+}
+
+impl IssueDeliveryFailureAPI {
+    pub async fn get_by_newsletter_issue_id(
+        newsletter_issue_id: &Uuid,
+        user_id: &Uuid,
+        pool: &PgPool,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            IssueDeliveryFailureAPI,
+            r#"
+              SELECT f.error, f.failed_at, f.n_retries, f.subscriber_email
+              FROM issue_delivery_failures f
+              JOIN newsletter_issues n
+                ON n.newsletter_issue_id = f.newsletter_issue_id
+              WHERE f.newsletter_issue_id = $1 AND n.user_id = $2
+              ORDER BY f.failed_at DESC, f.subscriber_email
+            "#,
+            newsletter_issue_id,
+            user_id
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Moves every failed delivery of the issue back onto the queue with a
+    /// fresh retry budget, logging each one as requeued. Returns how many
+    /// deliveries were requeued.
+    pub async fn requeue(
+        newsletter_issue_id: &Uuid,
+        user_id: &Uuid,
+        transaction: &mut Transaction<'_, Postgres>,
+    ) -> Result<u64, sqlx::Error> {
+        let requeued = transaction
+            .execute(sqlx::query!(
+                r#"
+                  WITH requeued AS (
+                    DELETE FROM issue_delivery_failures f
+                    USING newsletter_issues n
+                    WHERE n.newsletter_issue_id = f.newsletter_issue_id
+                      AND f.newsletter_issue_id = $1
+                      AND n.user_id = $2
+                    RETURNING f.newsletter_issue_id, f.subscriber_email
+                  ),
+                  queued AS (
+                    INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_email)
+                    SELECT newsletter_issue_id, subscriber_email
+                    FROM requeued
+                    ON CONFLICT DO NOTHING
+                    RETURNING newsletter_issue_id, subscriber_email
+                  )
+                  INSERT INTO issue_delivery_log (
+                    newsletter_issue_id,
+                    subscriber_email,
+                    outcome
+                  )
+                  SELECT newsletter_issue_id, subscriber_email, $3
+                  FROM queued
+                "#,
+                newsletter_issue_id,
+                user_id,
+                DeliveryOutcome::Requeued.as_str()
+            ))
+            .await?
+            .rows_affected();
+        if requeued > 0 {
+            NewsletterIssue::register_delivery_author(transaction, user_id).await?;
+            NewsletterIssue::notify_delivery_workers(transaction, newsletter_issue_id).await?;
+        }
+
+        Ok(requeued)
+    }
+}
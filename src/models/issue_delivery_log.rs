@@ -0,0 +1,198 @@
+use crate::utils::Pagination;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// What happened when the worker picked up a queued delivery.
+pub enum DeliveryOutcome {
+    /// The provider accepted the email.
+    Sent,
+    /// The provider rejected the email for good, or it ran out of retries.
+    Failed,
+    /// The provider failed transiently; the delivery was rescheduled.
+    Retried,
+    /// The stored address could not be parsed.
+    SkippedInvalid,
+    /// The subscriber is no longer confirmed.
+    SkippedUnconfirmed,
+    /// The address is on the suppression list.
+    Suppressed,
+    /// The author cancelled the delivery before it was attempted.
+    Cancelled,
+    /// The author put a failed delivery back in the queue.
+    Requeued,
+}
+
+impl DeliveryOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryOutcome::Sent => "sent",
+            DeliveryOutcome::Failed => "failed",
+            DeliveryOutcome::Retried => "retried",
+            DeliveryOutcome::SkippedInvalid => "skipped_invalid",
+            DeliveryOutcome::SkippedUnconfirmed => "skipped_unconfirmed",
+            DeliveryOutcome::Suppressed => "suppressed",
+            DeliveryOutcome::Cancelled => "cancelled",
+            DeliveryOutcome::Requeued => "requeued",
+        }
+    }
+}
+
+pub struct IssueDeliveryLog;
+
+impl IssueDeliveryLog {
+    pub async fn record(
+        newsletter_issue_id: &Uuid,
+        subscriber_email: &str,
+        outcome: DeliveryOutcome,
+        error: Option<&str>,
+        transaction: &mut Transaction<'_, Postgres>,
+    ) -> Result<(), sqlx::Error> {
+        transaction
+            .execute(sqlx::query!(
+                r#"
+                  INSERT INTO issue_delivery_log (
+                    newsletter_issue_id,
+                    subscriber_email,
+                    outcome,
+                    error
+                  )
+                  VALUES ($1, $2, $3, $4)
+                "#,
+                newsletter_issue_id,
+                subscriber_email,
+                outcome.as_str(),
+                error
+            ))
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeliveryAttemptAPI {
+    pub attempted_at: DateTime<Utc>,
+    pub error: Option<String>,
+    pub subscriber_email: String,
+}
+
+/// Delivery progress of a published issue, counting each recipient by the
+/// latest outcome of their delivery. `failures` is one page of the recipients
+/// whose delivery failed, `failed` their total count.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IssueDeliveryReportAPI {
+    pub cancelled: i64,
+    pub failed: i64,
+    pub failures: Vec<DeliveryAttemptAPI>,
+    pub first_sent_at: Option<DateTime<Utc>>,
+    pub last_sent_at: Option<DateTime<Utc>>,
+    pub page: i64,
+    /// Set while the author has paused the delivery of the remaining emails.
+    pub paused_at: Option<DateTime<Utc>>,
+    pub per_page: i64,
+    pub queued: i64,
+    pub sent: i64,
+    pub skipped: i64,
+    pub suppressed: i64,
+}
+
+impl IssueDeliveryReportAPI {
+    /// Callers are expected to have checked that the issue belongs to the
+    /// current user.
+    pub async fn find_by_newsletter_issue_id(
+        newsletter_issue_id: &Uuid,
+        pagination: &Pagination,
+        pool: &PgPool,
+    ) -> Result<Self, sqlx::Error> {
+        let summary = sqlx::query!(
+            r#"
+              WITH latest AS (
+                SELECT DISTINCT ON (subscriber_email) outcome, attempted_at
+                FROM issue_delivery_log
+                WHERE newsletter_issue_id = $1
+                ORDER BY subscriber_email, attempted_at DESC, id DESC
+              )
+              SELECT
+                COUNT(*) FILTER (WHERE outcome = 'sent') AS "sent!",
+                COUNT(*) FILTER (WHERE outcome = 'failed') AS "failed!",
+                COUNT(*) FILTER (
+                  WHERE outcome IN ('skipped_invalid', 'skipped_unconfirmed')
+                ) AS "skipped!",
+                COUNT(*) FILTER (WHERE outcome = 'suppressed') AS "suppressed!",
+                COUNT(*) FILTER (WHERE outcome = 'cancelled') AS "cancelled!",
+                MIN(attempted_at) FILTER (WHERE outcome = 'sent') AS first_sent_at,
+                MAX(attempted_at) FILTER (WHERE outcome = 'sent') AS last_sent_at
+              FROM latest
+            "#,
+            newsletter_issue_id
+        )
+        .fetch_one(pool)
+        .await?;
+        let paused_at = sqlx::query_scalar!(
+            r#"
+              SELECT delivery_paused_at
+              FROM newsletter_issues
+              WHERE newsletter_issue_id = $1
+            "#,
+            newsletter_issue_id
+        )
+        .fetch_one(pool)
+        .await?;
+        let queued = sqlx::query_scalar!(
+            r#"
+              SELECT COUNT(*) AS "count!"
+              FROM issue_delivery_queue
+              WHERE newsletter_issue_id = $1
+            "#,
+            newsletter_issue_id
+        )
+        .fetch_one(pool)
+        .await?;
+        let failures = sqlx::query_as!(
+            DeliveryAttemptAPI,
+            r#"
+              SELECT
+                attempted_at AS "attempted_at!",
+                error,
+                subscriber_email AS "subscriber_email!"
+              FROM (
+                SELECT DISTINCT ON (subscriber_email)
+                  attempted_at,
+                  error,
+                  id,
+                  outcome,
+                  subscriber_email
+                FROM issue_delivery_log
+                WHERE newsletter_issue_id = $1
+                ORDER BY subscriber_email, attempted_at DESC, id DESC
+              ) latest
+              WHERE outcome = 'failed'
+              ORDER BY attempted_at DESC, id DESC
+              LIMIT $2
+              OFFSET $3
+            "#,
+            newsletter_issue_id,
+            pagination.per_page,
+            pagination.offset()
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(Self {
+            cancelled: summary.cancelled,
+            failed: summary.failed,
+            failures,
+            first_sent_at: summary.first_sent_at,
+            last_sent_at: summary.last_sent_at,
+            page: pagination.page,
+            paused_at,
+            per_page: pagination.per_page,
+            queued,
+            sent: summary.sent,
+            skipped: summary.skipped,
+            suppressed: summary.suppressed,
+        })
+    }
+}
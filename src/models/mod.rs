@@ -1,9 +1,21 @@
+mod email_branding;
+mod email_suppression;
+mod issue_delivery_failure;
+mod issue_delivery_log;
 mod newsletter;
+mod newsletter_engagement;
+mod newsletter_test_send;
 mod subscription;
 mod user;
 mod user_profile;
 
+pub use email_branding::*;
+pub use email_suppression::*;
+pub use issue_delivery_failure::*;
+pub use issue_delivery_log::*;
 pub use newsletter::*;
+pub use newsletter_engagement::*;
+pub use newsletter_test_send::*;
 pub use subscription::*;
 pub use user::*;
 pub use user_profile::*;
@@ -1,17 +1,26 @@
 use crate::clients::cloudinary_client::CloudinaryClient;
 use crate::clients::s3_client::S3Client;
-use crate::domain::newsletter_issue::{Content, Description, Title};
-use crate::domain::{Base64ImageUrl, ImageUrl};
-use crate::models::AssociatedUser;
+use crate::domain::newsletter_issue::{
+    Content, Description, MergeTagField, MergeTags, Title, markdown_to_plain_text,
+};
+use crate::domain::{Base64ImageUrl, ImageUrl, LinkSignature};
+use crate::email_client::OutgoingEmail;
+use crate::models::{AssociatedUser, DeliveryOutcome};
 use crate::utils::{e500, is_empty_or_whitespace};
 use anyhow::Context;
 use chrono::{DateTime, Utc};
+use regex::{Captures, Regex};
+use secrecy::SecretString;
 use serde::{Deserialize, Serialize, Serializer};
 use sqlx::postgres::PgRow;
 use sqlx::{Executor, PgPool, Postgres, Row, Transaction};
+use std::sync::LazyLock;
 use std::time::{SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
-use voca_rs::strip;
+use voca_rs::escape;
+
+/// Channel delivery workers `LISTEN` on to learn about newly queued tasks.
+pub const DELIVERY_QUEUE_CHANNEL: &str = "issue_delivery_queue";
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct NewsletterIssue {
@@ -21,6 +30,7 @@ pub struct NewsletterIssue {
     pub description: String,
     pub newsletter_issue_id: Uuid,
     pub published_at: Option<DateTime<Utc>>,
+    pub scheduled_for: Option<DateTime<Utc>>,
     pub slug: String,
     pub title: String,
     pub user_id: Uuid,
@@ -37,6 +47,7 @@ impl TryFrom<PgRow> for NewsletterIssue {
             description: row.try_get("description")?,
             newsletter_issue_id: row.try_get("newsletter_issue_id")?,
             published_at: row.try_get("published_at")?,
+            scheduled_for: row.try_get("scheduled_for")?,
             slug: row.try_get("slug")?,
             title: row.try_get("title")?,
             user_id: row.try_get("user_id")?,
@@ -60,6 +71,7 @@ impl NewsletterIssue {
                 description,
                 newsletter_issue_id,
                 published_at,
+                scheduled_for,
                 slug,
                 title,
                 user_id
@@ -92,6 +104,7 @@ impl NewsletterIssue {
                     description,
                     newsletter_issue_id,
                     published_at,
+                    scheduled_for,
                     slug,
                     title,
                     user_id
@@ -122,6 +135,7 @@ impl NewsletterIssue {
                 description,
                 newsletter_issue_id,
                 published_at,
+                scheduled_for,
                 slug,
                 title,
                 user_id
@@ -151,6 +165,7 @@ impl NewsletterIssue {
                 description,
                 newsletter_issue_id,
                 published_at,
+                scheduled_for,
                 slug,
                 title,
                 user_id
@@ -181,11 +196,12 @@ impl NewsletterIssue {
                 description,
                 newsletter_issue_id,
                 published_at,
+                scheduled_for,
                 slug,
                 title,
                 user_id
               FROM newsletter_issues
-              WHERE user_id = $1 AND published_at IS NULL
+              WHERE user_id = $1 AND published_at IS NULL AND scheduled_for IS NULL
               ORDER BY created_at DESC
               LIMIT 10
             "#,
@@ -197,6 +213,67 @@ impl NewsletterIssue {
         Ok(newsletter_issues)
     }
 
+    pub async fn get_scheduled_by_user_id(
+        user_id: Uuid,
+        pool: &PgPool,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let newsletter_issues = sqlx::query_as!(
+            NewsletterIssue,
+            r#"
+              SELECT
+                content,
+                cover_image_url,
+                created_at,
+                description,
+                newsletter_issue_id,
+                published_at,
+                scheduled_for,
+                slug,
+                title,
+                user_id
+              FROM newsletter_issues
+              WHERE user_id = $1 AND published_at IS NULL AND scheduled_for IS NOT NULL
+              ORDER BY scheduled_for ASC
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(newsletter_issues)
+    }
+
+    /// Picks the unpublished issue whose schedule is the most overdue, locking
+    /// it so that concurrent schedulers skip it rather than publish it twice.
+    pub async fn find_due_scheduled_txn(
+        transaction: &mut Transaction<'_, Postgres>,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            NewsletterIssue,
+            r#"
+              SELECT
+                content,
+                cover_image_url,
+                created_at,
+                description,
+                newsletter_issue_id,
+                published_at,
+                scheduled_for,
+                slug,
+                title,
+                user_id
+              FROM newsletter_issues
+              WHERE published_at IS NULL AND scheduled_for <= now()
+              ORDER BY scheduled_for ASC
+              FOR UPDATE
+              SKIP LOCKED
+              LIMIT 1
+            "#,
+        )
+        .fetch_optional(&mut **transaction)
+        .await
+    }
+
     pub async fn update(
         self,
         transaction: &mut Transaction<'_, Postgres>,
@@ -232,7 +309,8 @@ impl NewsletterIssue {
             .execute(sqlx::query!(
                 r#"
                   UPDATE newsletter_issues
-                  SET published_at = now()
+                  SET published_at = now(),
+                      scheduled_for = NULL
                   WHERE newsletter_issue_id = $1
                     AND user_id = $2
                 "#,
@@ -244,6 +322,184 @@ impl NewsletterIssue {
         Ok(self.newsletter_issue_id)
     }
 
+    /// Sets (`Some`) or clears (`None`) the time the issue is due to be
+    /// published by the scheduler.
+    pub async fn schedule(
+        mut self,
+        scheduled_for: Option<DateTime<Utc>>,
+        transaction: &mut Transaction<'_, Postgres>,
+    ) -> Result<Self, sqlx::Error> {
+        transaction
+            .execute(sqlx::query!(
+                r#"
+                  UPDATE newsletter_issues
+                  SET scheduled_for = $1
+                  WHERE newsletter_issue_id = $2
+                    AND user_id = $3
+                "#,
+                scheduled_for,
+                &self.newsletter_issue_id,
+                self.user_id,
+            ))
+            .await?;
+        self.scheduled_for = scheduled_for;
+
+        Ok(self)
+    }
+
+    /// Queues the issue for every confirmed, non-suppressed subscriber of its
+    /// author.
+    #[tracing::instrument(skip_all)]
+    pub async fn enqueue_delivery_tasks(
+        transaction: &mut Transaction<'_, Postgres>,
+        newsletter_issue_id: Uuid,
+        user_id: &Uuid,
+    ) -> Result<(), sqlx::Error> {
+        transaction
+            .execute(sqlx::query!(
+                r#"
+                  INSERT INTO issue_delivery_queue (
+                    newsletter_issue_id,
+                    subscriber_email
+                  )
+                  SELECT $1, email
+                  FROM subscriptions
+                  WHERE status = 'confirmed'
+                  AND user_id = $2
+                  AND NOT EXISTS (
+                    SELECT 1
+                    FROM email_suppressions
+                    WHERE email_suppressions.email = LOWER(subscriptions.email)
+                  )
+                "#,
+                newsletter_issue_id,
+                user_id
+            ))
+            .await?;
+        NewsletterIssue::register_delivery_author(transaction, user_id).await?;
+        NewsletterIssue::notify_delivery_workers(transaction, &newsletter_issue_id).await
+    }
+
+    /// Makes sure the author has a row in `delivery_author_quotas`, which the
+    /// workers rely on to share sending capacity fairly between authors.
+    pub async fn register_delivery_author(
+        transaction: &mut Transaction<'_, Postgres>,
+        user_id: &Uuid,
+    ) -> Result<(), sqlx::Error> {
+        transaction
+            .execute(sqlx::query!(
+                r#"
+                  INSERT INTO delivery_author_quotas (user_id)
+                  VALUES ($1)
+                  ON CONFLICT DO NOTHING
+                "#,
+                user_id
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Wakes idle delivery workers. Postgres only delivers the notification
+    /// once the transaction commits, so workers never look for tasks that are
+    /// not visible to them yet.
+    pub async fn notify_delivery_workers(
+        transaction: &mut Transaction<'_, Postgres>,
+        newsletter_issue_id: &Uuid,
+    ) -> Result<(), sqlx::Error> {
+        transaction
+            .execute(sqlx::query!(
+                "SELECT pg_notify($1, $2)",
+                DELIVERY_QUEUE_CHANNEL,
+                newsletter_issue_id.to_string()
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Stops workers from claiming the issue's queued deliveries until
+    /// [`NewsletterIssue::resume_delivery`] is called. Batches already being
+    /// sent are not interrupted.
+    pub async fn pause_delivery(
+        transaction: &mut Transaction<'_, Postgres>,
+        newsletter_issue_id: &Uuid,
+    ) -> Result<(), sqlx::Error> {
+        transaction
+            .execute(sqlx::query!(
+                r#"
+                  UPDATE newsletter_issues
+                  SET delivery_paused_at = COALESCE(delivery_paused_at, now())
+                  WHERE newsletter_issue_id = $1
+                "#,
+                newsletter_issue_id
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn resume_delivery(
+        transaction: &mut Transaction<'_, Postgres>,
+        newsletter_issue_id: &Uuid,
+    ) -> Result<(), sqlx::Error> {
+        transaction
+            .execute(sqlx::query!(
+                r#"
+                  UPDATE newsletter_issues
+                  SET delivery_paused_at = NULL
+                  WHERE newsletter_issue_id = $1
+                "#,
+                newsletter_issue_id
+            ))
+            .await?;
+        NewsletterIssue::notify_delivery_workers(transaction, newsletter_issue_id).await
+    }
+
+    /// Removes every delivery still queued for the issue, recording each one
+    /// as cancelled in the delivery log, and returns how many there were.
+    /// Deliveries a worker has claimed are left for it to acknowledge.
+    pub async fn cancel_delivery(
+        transaction: &mut Transaction<'_, Postgres>,
+        newsletter_issue_id: &Uuid,
+    ) -> Result<u64, sqlx::Error> {
+        let cancelled = transaction
+            .execute(sqlx::query!(
+                r#"
+                  WITH cancelled AS (
+                    DELETE FROM issue_delivery_queue
+                    WHERE
+                      newsletter_issue_id = $1 AND
+                      (locked_until IS NULL OR locked_until < now())
+                    RETURNING newsletter_issue_id, subscriber_email
+                  )
+                  INSERT INTO issue_delivery_log (
+                    newsletter_issue_id,
+                    subscriber_email,
+                    outcome
+                  )
+                  SELECT newsletter_issue_id, subscriber_email, $2
+                  FROM cancelled
+                "#,
+                newsletter_issue_id,
+                DeliveryOutcome::Cancelled.as_str()
+            ))
+            .await?
+            .rows_affected();
+        transaction
+            .execute(sqlx::query!(
+                r#"
+                  UPDATE newsletter_issues
+                  SET delivery_paused_at = NULL
+                  WHERE newsletter_issue_id = $1
+                "#,
+                newsletter_issue_id
+            ))
+            .await?;
+
+        Ok(cancelled)
+    }
+
     // Note - For serializing nested records sqlx allows returning
     // sequence of values, which are then mapped to the key names in
     // order. This means the order of the columns in the query must
@@ -355,6 +611,8 @@ impl NewsletterIssue {
         let content = Content::parse(self.content)?;
         let description = Description::parse(self.description)?;
         let title = Title::parse(self.title)?;
+        MergeTags::validate(title.as_ref())?;
+        MergeTags::validate(content.as_ref())?;
 
         Ok(Self {
             content: content.as_ref().to_string(),
@@ -363,6 +621,7 @@ impl NewsletterIssue {
             description: description.as_ref().to_string(),
             newsletter_issue_id: self.newsletter_issue_id,
             published_at: self.published_at,
+            scheduled_for: self.scheduled_for,
             slug: self.slug,
             title: title.as_ref().to_string(),
             user_id: self.user_id,
@@ -400,6 +659,7 @@ impl NewsletterIssue {
             description: self.description,
             newsletter_issue_id: self.newsletter_issue_id,
             published_at: self.published_at,
+            scheduled_for: self.scheduled_for,
             slug: self.slug,
             title: self.title,
             user_id: self.user_id,
@@ -454,6 +714,7 @@ pub struct NewsletterIssueAPI {
     pub html_content: String,
     pub newsletter_issue_id: Uuid,
     pub published_at: Option<DateTime<Utc>>,
+    pub scheduled_for: Option<DateTime<Utc>>,
     pub slug: String,
     pub title: String,
     pub user_id: Uuid,
@@ -470,6 +731,7 @@ impl From<NewsletterIssue> for NewsletterIssueAPI {
             html_content,
             newsletter_issue_id: newsletter_issue.newsletter_issue_id,
             published_at: newsletter_issue.published_at,
+            scheduled_for: newsletter_issue.scheduled_for,
             slug: newsletter_issue.slug,
             title: newsletter_issue.title,
             user_id: newsletter_issue.user_id,
@@ -481,6 +743,8 @@ impl From<NewsletterIssue> for NewsletterIssueAPI {
 pub struct NewsletterIssueEmail {
     pub description: String,
     pub html_content: String,
+    /// Merge tags of the title and content, left as placeholders in both.
+    pub merge_tags: MergeTags,
     pub newsletter_issue_id: Uuid,
     pub published_at: Option<DateTime<Utc>>,
     pub slug: String,
@@ -491,22 +755,85 @@ pub struct NewsletterIssueEmail {
 
 impl From<NewsletterIssue> for NewsletterIssueEmail {
     fn from(newsletter_issue: NewsletterIssue) -> NewsletterIssueEmail {
-        let html_content = markdown::to_html(&newsletter_issue.content);
-        let text_content = strip::strip_tags(&html_content);
+        let mut merge_tags = MergeTags::default();
+        let title = merge_tags.extract(&newsletter_issue.title);
+        let content = merge_tags.extract(&newsletter_issue.content);
+        let html_content = markdown::to_html(&content);
+        let text_content = markdown_to_plain_text(&content);
 
         NewsletterIssueEmail {
             description: newsletter_issue.description,
             html_content,
+            merge_tags,
             newsletter_issue_id: newsletter_issue.newsletter_issue_id,
             published_at: newsletter_issue.published_at,
             slug: newsletter_issue.slug,
             text_content,
-            title: newsletter_issue.title,
+            title,
             user_id: newsletter_issue.user_id,
         }
     }
 }
 
+/// Links of the rendered content, with their HTML-escaped destination.
+static LINK_HREF: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"href="([^"]*)""#).unwrap());
+
+impl NewsletterIssueEmail {
+    /// Routes the web links of the content through the click tracking endpoint
+    /// and adds an open tracking pixel. Both point at `base_url` and carry the
+    /// recipient's tracking token, filled in when the email is personalised.
+    pub fn with_tracking(mut self, base_url: &str, hmac_secret: &SecretString) -> Self {
+        let tracking_token = self.merge_tags.insert(MergeTagField::TrackingToken);
+        let html_content = LINK_HREF.replace_all(&self.html_content, |captures: &Captures| {
+            let url = escape::unescape_html(&captures[1]);
+            // Links that vary per recipient could not be signed up front.
+            if !(url.starts_with("https://") || url.starts_with("http://"))
+                || MergeTags::has_placeholders(&url)
+            {
+                return captures[0].to_string();
+            }
+            let tracked_url = format!(
+                "{}/tracking/click/{}?url={}&signature={}",
+                base_url,
+                tracking_token,
+                urlencoding::encode(&url),
+                LinkSignature::generate(&url, hmac_secret).as_ref()
+            );
+            format!(r#"href="{}""#, escape::escape_html(&tracked_url))
+        });
+        self.html_content = format!(
+            r#"{}<img src="{}/tracking/open/{}" width="1" height="1" alt="" />"#,
+            html_content, base_url, tracking_token
+        );
+
+        self
+    }
+}
+
+/// An issue's email exactly as a subscriber would receive it.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EmailPreviewAPI {
+    pub from_name: Option<String>,
+    pub html_body: String,
+    pub reply_to: Option<String>,
+    pub size: usize,
+    pub subject: String,
+    pub text_body: String,
+}
+
+impl From<OutgoingEmail> for EmailPreviewAPI {
+    fn from(email: OutgoingEmail) -> Self {
+        Self {
+            from_name: email.from_name().map(String::from),
+            html_body: email.html_body().to_string(),
+            reply_to: email.reply_to().map(String::from),
+            size: email.size(),
+            subject: email.subject().to_string(),
+            text_body: email.text_body().to_string(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct NewNewsletterIssue {
     pub content: String,
@@ -684,6 +1011,7 @@ mod tests {
     };
     use chrono::Utc;
     use claims::{assert_err, assert_ok};
+    use secrecy::SecretString;
     use uuid::Uuid;
 
     #[test]
@@ -730,6 +1058,7 @@ mod tests {
             description: new_newsletter_issue.description,
             newsletter_issue_id: new_newsletter_issue.newsletter_issue_id,
             published_at: Some(Utc::now()),
+            scheduled_for: None,
             slug: new_newsletter_issue.slug,
             title: new_newsletter_issue.title,
             user_id: Uuid::new_v4(),
@@ -760,6 +1089,7 @@ mod tests {
             description: new_newsletter_issue.description,
             newsletter_issue_id: new_newsletter_issue.newsletter_issue_id,
             published_at: Some(Utc::now()),
+            scheduled_for: None,
             slug: new_newsletter_issue.slug,
             title: new_newsletter_issue.title,
             user_id: Uuid::new_v4(),
@@ -770,6 +1100,40 @@ mod tests {
             newsletter_issue_email.html_content,
             "<h2>Newsletter content</h2>"
         );
-        assert_eq!(newsletter_issue_email.text_content, "Newsletter content");
+        assert_eq!(
+            newsletter_issue_email.text_content,
+            "Newsletter content\n------------------"
+        );
+    }
+
+    #[test]
+    fn tracking_routes_web_links_through_the_click_endpoint() {
+        let newsletter_issue_email = NewsletterIssueEmail::from(NewsletterIssue {
+            content: String::from(
+                "[Post](https://example.com/?a=1&b=2) [Mail](mailto:ursula@example.com)",
+            ),
+            cover_image_url: String::from(""),
+            created_at: Utc::now(),
+            description: String::from("Newsletter description"),
+            newsletter_issue_id: Uuid::new_v4(),
+            published_at: Some(Utc::now()),
+            scheduled_for: None,
+            slug: String::from("ursula-le-guin"),
+            title: String::from("Ursula Le Guin"),
+            user_id: Uuid::new_v4(),
+        })
+        .with_tracking(
+            "https://api.test",
+            &SecretString::from("super-long-and-secret-random-key"),
+        );
+        let html_content = newsletter_issue_email.html_content;
+
+        assert!(html_content.contains(
+            r#"href="https://api.test/tracking/click/MERGETAG0MERGETAG?url=https%3A%2F%2Fexample.com%2F%3Fa%3D1%26b%3D2&amp;signature="#
+        ));
+        assert!(html_content.contains(r#"href="mailto:ursula@example.com""#));
+        assert!(html_content.ends_with(
+            r#"<img src="https://api.test/tracking/open/MERGETAG0MERGETAG" width="1" height="1" alt="" />"#
+        ));
     }
 }
@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// What a subscriber did with a delivered issue.
+pub enum EngagementKind {
+    /// The tracking pixel was loaded.
+    Open,
+    /// A tracked link was followed.
+    Click,
+}
+
+impl EngagementKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EngagementKind::Open => "open",
+            EngagementKind::Click => "click",
+        }
+    }
+}
+
+pub struct NewsletterEngagementEvent;
+
+impl NewsletterEngagementEvent {
+    /// Events of subscribers or issues deleted since the email went out are
+    /// dropped.
+    pub async fn record(
+        newsletter_issue_id: &Uuid,
+        subscriber_id: &Uuid,
+        kind: EngagementKind,
+        url: Option<&str>,
+        pool: &PgPool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+              INSERT INTO newsletter_engagement_events (
+                newsletter_issue_id,
+                subscriber_id,
+                kind,
+                url
+              )
+              SELECT $1, $2, $3, $4
+              WHERE EXISTS (SELECT 1 FROM subscriptions WHERE id = $2)
+                AND EXISTS (
+                  SELECT 1 FROM newsletter_issues WHERE newsletter_issue_id = $1
+                )
+            "#,
+            newsletter_issue_id,
+            subscriber_id,
+            kind.as_str(),
+            url
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LinkClicksAPI {
+    pub clicks: i64,
+    pub unique_clicks: i64,
+    pub url: String,
+}
+
+/// Opens and clicks of a published issue. A subscriber who clicked a link
+/// counts as having opened the email, even when their client blocked the
+/// tracking pixel. Rates are relative to the number of emails sent.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IssueEngagementAPI {
+    pub click_rate: f64,
+    pub clicks: i64,
+    pub links: Vec<LinkClicksAPI>,
+    pub open_rate: f64,
+    pub opens: i64,
+    pub sent: i64,
+    pub unique_clicks: i64,
+    pub unique_opens: i64,
+}
+
+impl IssueEngagementAPI {
+    /// Callers are expected to have checked that the issue belongs to the
+    /// current user.
+    pub async fn find_by_newsletter_issue_id(
+        newsletter_issue_id: &Uuid,
+        pool: &PgPool,
+    ) -> Result<Self, sqlx::Error> {
+        let summary = sqlx::query!(
+            r#"
+              SELECT
+                COUNT(*) FILTER (WHERE kind = 'open') AS "opens!",
+                COUNT(DISTINCT subscriber_id) AS "unique_opens!",
+                COUNT(*) FILTER (WHERE kind = 'click') AS "clicks!",
+                COUNT(DISTINCT subscriber_id) FILTER (
+                  WHERE kind = 'click'
+                ) AS "unique_clicks!"
+              FROM newsletter_engagement_events
+              WHERE newsletter_issue_id = $1
+            "#,
+            newsletter_issue_id
+        )
+        .fetch_one(pool)
+        .await?;
+        let sent = sqlx::query_scalar!(
+            r#"
+              SELECT COUNT(*) AS "count!"
+              FROM issue_delivery_log
+              WHERE newsletter_issue_id = $1 AND outcome = 'sent'
+            "#,
+            newsletter_issue_id
+        )
+        .fetch_one(pool)
+        .await?;
+        let links = sqlx::query_as!(
+            LinkClicksAPI,
+            r#"
+              SELECT
+                url AS "url!",
+                COUNT(*) AS "clicks!",
+                COUNT(DISTINCT subscriber_id) AS "unique_clicks!"
+              FROM newsletter_engagement_events
+              WHERE newsletter_issue_id = $1 AND kind = 'click'
+              GROUP BY url
+              ORDER BY 2 DESC, url
+            "#,
+            newsletter_issue_id
+        )
+        .fetch_all(pool)
+        .await?;
+        let rate = |count: i64| {
+            if sent == 0 {
+                0.0
+            } else {
+                count as f64 / sent as f64
+            }
+        };
+
+        Ok(Self {
+            click_rate: rate(summary.unique_clicks),
+            clicks: summary.clicks,
+            links,
+            open_rate: rate(summary.unique_opens),
+            opens: summary.opens,
+            sent,
+            unique_clicks: summary.unique_clicks,
+            unique_opens: summary.unique_opens,
+        })
+    }
+}
@@ -0,0 +1,79 @@
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Test emails of an issue sent to its author, kept to rate limit them.
+pub struct NewsletterTestSend;
+
+impl NewsletterTestSend {
+    /// Counts the test sends of the last hour. The user's row stays locked
+    /// until the transaction ends, so concurrent requests are counted one
+    /// after the other.
+    pub async fn count_in_last_hour(
+        user_id: &Uuid,
+        transaction: &mut Transaction<'_, Postgres>,
+    ) -> Result<i64, sqlx::Error> {
+        transaction
+            .execute(sqlx::query!(
+                "SELECT user_id FROM users WHERE user_id = $1 FOR UPDATE",
+                user_id
+            ))
+            .await?;
+        sqlx::query_scalar!(
+            r#"
+              SELECT COUNT(*) AS "count!"
+              FROM newsletter_test_sends
+              WHERE user_id = $1 AND sent_at > now() - interval '1 hour'
+            "#,
+            user_id
+        )
+        .fetch_one(&mut **transaction)
+        .await
+    }
+
+    /// Records a test send ahead of sending it, so that the slot is taken as
+    /// soon as the transaction commits. Returns the id to release it by.
+    pub async fn record(
+        user_id: &Uuid,
+        newsletter_issue_id: &Uuid,
+        n_recipients: usize,
+        transaction: &mut Transaction<'_, Postgres>,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"
+              INSERT INTO newsletter_test_sends (
+                user_id,
+                newsletter_issue_id,
+                n_recipients
+              )
+              VALUES ($1, $2, $3)
+              RETURNING id
+            "#,
+            user_id,
+            newsletter_issue_id,
+            n_recipients as i32
+        )
+        .fetch_one(&mut **transaction)
+        .await
+    }
+
+    /// Settles a test send that failed after `n_sent` emails went out. The
+    /// slot is given back if none did, otherwise only the recipients actually
+    /// mailed are kept.
+    pub async fn settle_failed(id: i64, n_sent: usize, pool: &PgPool) -> Result<(), sqlx::Error> {
+        if n_sent == 0 {
+            sqlx::query!("DELETE FROM newsletter_test_sends WHERE id = $1", id)
+                .execute(pool)
+                .await?;
+        } else {
+            sqlx::query!(
+                "UPDATE newsletter_test_sends SET n_recipients = $2 WHERE id = $1",
+                id,
+                n_sent as i32
+            )
+            .execute(pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,14 +1,24 @@
+use crate::domain::Locale;
 use crate::domain::SubscriberEmail;
 use crate::domain::SubscriberName;
-use crate::email_client::EmailClient;
-use crate::utils::error_chain_fmt;
-use chrono::Utc;
+use crate::domain::UnsubscribeToken;
+use crate::email_client::{
+    EmailClient, OutgoingEmail, SendEmailError, SubscriptionConfirmation, UnsubscribeLinks,
+};
+use crate::models::{DELIVERY_QUEUE_CHANNEL, EmailAuthor};
+use crate::utils::{Pagination, error_chain_fmt};
+use chrono::{DateTime, TimeDelta, Utc};
 use rand::distributions::Alphanumeric;
 use rand::{Rng, thread_rng};
-use serde::Serialize;
-use sqlx::{Executor, Postgres, Transaction};
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
+const SUBSCRIPTION_TOKEN_TTL_HOURS: i64 = 48;
+/// Payload of the notification sent when confirmation emails are queued.
+const CONFIRMATION_EMAIL_NOTIFICATION: &str = "confirmation_email";
+
 #[derive(Serialize, Debug)]
 pub struct Subscription {
     pub id: Uuid,
@@ -18,6 +28,79 @@ pub struct Subscription {
 }
 
 impl Subscription {
+    pub async fn find_by_emails_and_user_id(
+        emails: &[String],
+        user_id: &Uuid,
+        pool: &PgPool,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            Subscription,
+            r#"
+              SELECT id, email, name, status
+              FROM subscriptions
+              WHERE email = ANY($1) AND user_id = $2
+            "#,
+            emails,
+            user_id
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    pub fn is_confirmed(&self) -> bool {
+        self.status == SubscriptionStatus::Confirmed.as_str()
+    }
+
+    pub fn is_pending_confirmation(&self) -> bool {
+        self.status == SubscriptionStatus::PendingConfirmation.as_str()
+    }
+
+    /// Moves a lapsed subscription back to `pending_confirmation` so that it
+    /// can be confirmed again.
+    pub async fn mark_pending_confirmation(
+        mut self,
+        transaction: &mut Transaction<'_, Postgres>,
+    ) -> Result<Self, sqlx::Error> {
+        let status = SubscriptionStatus::PendingConfirmation.as_str();
+        transaction
+            .execute(sqlx::query!(
+                r#"UPDATE subscriptions SET status = $1 WHERE id = $2"#,
+                status,
+                &self.id
+            ))
+            .await?;
+        self.status = status.to_string();
+
+        Ok(self)
+    }
+
+    pub fn unsubscribe_links(
+        &self,
+        base_url: &str,
+        client_base_url: &str,
+        hmac_secret: &SecretString,
+    ) -> UnsubscribeLinks {
+        let token = UnsubscribeToken::generate(&self.id, hmac_secret);
+
+        UnsubscribeLinks {
+            one_click_url: format!(
+                "{}/subscriptions/unsubscribe?unsubscribe_token={}",
+                base_url,
+                token.as_ref()
+            ),
+            page_url: format!(
+                "{}/subscriptions/unsubscribe?unsubscribe_token={}",
+                client_base_url,
+                token.as_ref()
+            ),
+        }
+    }
+
+    /// How long a confirmation link stays valid after it was issued.
+    pub fn token_ttl() -> TimeDelta {
+        TimeDelta::hours(SUBSCRIPTION_TOKEN_TTL_HOURS)
+    }
+
     pub fn generate_subscription_token() -> String {
         let mut rng = thread_rng();
         std::iter::repeat_with(|| rng.sample(Alphanumeric))
@@ -26,27 +109,80 @@ impl Subscription {
             .collect()
     }
 
+    /// Sent on behalf of `author`, in the subscriber's `locale`.
     pub async fn send_confirmation_email(
         &self,
         email_client: &EmailClient,
         base_url: &str,
         subscription_token: &str,
-    ) -> Result<(), reqwest::Error> {
+        author: &EmailAuthor,
+        locale: Locale,
+    ) -> Result<(), SendEmailError> {
+        let email = self.render_confirmation_email(
+            email_client,
+            base_url,
+            subscription_token,
+            author,
+            locale,
+        )?;
+        email_client.send_outgoing_email(&email).await
+    }
+
+    pub fn render_confirmation_email(
+        &self,
+        email_client: &EmailClient,
+        base_url: &str,
+        subscription_token: &str,
+        author: &EmailAuthor,
+        locale: Locale,
+    ) -> Result<OutgoingEmail, tera::Error> {
         let confirmation_link = format!(
             "{}/subscriptions/confirm?subscription_token={}",
             base_url, subscription_token
         );
-        let plain_body = format!(
-            "Welcome to our newsletter!\nVisit {} to confirm your subscription.",
-            confirmation_link
-        );
-        let html_body = format!(
-            "Welcome to our newsletter!<br />Click <a href=\"{}\">here</a> to confirm your subscription.",
-            confirmation_link
-        );
-        email_client
-            .send_email(&self.email, "Welcome!", &html_body, &plain_body)
-            .await
+        let email = SubscriptionConfirmation {
+            author_avatar_url: &author.avatar_url,
+            author_name: &author.name,
+            confirmation_link: &confirmation_link,
+            subscriber_name: &self.name,
+        };
+        email_client.render_transactional_email(&self.email, &email, locale)
+    }
+
+    /// Leaves the confirmation email to the delivery workers, who send it
+    /// within the provider quotas. Workers are only woken by
+    /// [`Subscription::notify_delivery_workers`].
+    pub async fn queue_confirmation_email(
+        &self,
+        transaction: &mut Transaction<'_, Postgres>,
+        subscription_token: &str,
+    ) -> Result<(), sqlx::Error> {
+        transaction
+            .execute(sqlx::query!(
+                r#"
+                  INSERT INTO confirmation_email_queue (subscription_token)
+                  VALUES ($1)
+                "#,
+                subscription_token
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Wakes idle delivery workers once the transaction commits.
+    pub async fn notify_delivery_workers(
+        transaction: &mut Transaction<'_, Postgres>,
+    ) -> Result<(), sqlx::Error> {
+        transaction
+            .execute(sqlx::query!(
+                "SELECT pg_notify($1, $2)",
+                DELIVERY_QUEUE_CHANNEL,
+                CONFIRMATION_EMAIL_NOTIFICATION
+            ))
+            .await?;
+
+        Ok(())
     }
 
     pub async fn store_token(
@@ -70,47 +206,372 @@ impl Subscription {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SubscriptionAPI {
+    pub email: String,
+    pub id: Uuid,
+    pub name: String,
+    pub status: String,
+    pub subscribed_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SubscriptionListAPI {
+    pub page: i64,
+    pub per_page: i64,
+    pub subscribers: Vec<SubscriptionAPI>,
+    pub total: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SubscriptionCountsAPI {
+    pub bounced: i64,
+    pub complained: i64,
+    pub confirmed: i64,
+    pub pending_confirmation: i64,
+    pub total: i64,
+    pub unsubscribed: i64,
+}
+
+/// Filters and pagination applied when an author lists their subscribers.
+pub struct SubscriptionFilter {
+    /// Case-insensitive substring matched against subscriber emails.
+    pub email: Option<String>,
+    pub pagination: Pagination,
+    pub status: Option<SubscriptionStatus>,
+}
+
+impl SubscriptionFilter {
+    fn email_pattern(&self) -> Option<String> {
+        self.email.as_ref().map(|email| {
+            let escaped = email
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            format!("%{escaped}%")
+        })
+    }
+}
+
+impl SubscriptionAPI {
+    pub async fn find_by_id_and_user_id(
+        id: &Uuid,
+        user_id: &Uuid,
+        pool: &PgPool,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as!(
+            SubscriptionAPI,
+            r#"
+              SELECT email, id, name, status, subscribed_at
+              FROM subscriptions
+              WHERE id = $1 AND user_id = $2
+            "#,
+            id,
+            user_id
+        )
+        .fetch_one(pool)
+        .await
+    }
+
+    pub async fn get_by_user_id(
+        user_id: &Uuid,
+        filter: &SubscriptionFilter,
+        pool: &PgPool,
+    ) -> Result<SubscriptionListAPI, sqlx::Error> {
+        let status = filter.status.as_ref().map(|status| status.as_str());
+        let email_pattern = filter.email_pattern();
+        let subscribers = sqlx::query_as!(
+            SubscriptionAPI,
+            r#"
+              SELECT email, id, name, status, subscribed_at
+              FROM subscriptions
+              WHERE user_id = $1
+                AND ($2::TEXT IS NULL OR status = $2)
+                AND ($3::TEXT IS NULL OR email ILIKE $3)
+              ORDER BY subscribed_at DESC, email
+              LIMIT $4
+              OFFSET $5
+            "#,
+            user_id,
+            status,
+            email_pattern,
+            filter.pagination.per_page,
+            filter.pagination.offset()
+        )
+        .fetch_all(pool)
+        .await?;
+        let total = sqlx::query_scalar!(
+            r#"
+              SELECT COUNT(*) AS "count!"
+              FROM subscriptions
+              WHERE user_id = $1
+                AND ($2::TEXT IS NULL OR status = $2)
+                AND ($3::TEXT IS NULL OR email ILIKE $3)
+            "#,
+            user_id,
+            status,
+            email_pattern
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(SubscriptionListAPI {
+            page: filter.pagination.page,
+            per_page: filter.pagination.per_page,
+            subscribers,
+            total,
+        })
+    }
+
+    pub async fn count_by_status(
+        user_id: &Uuid,
+        pool: &PgPool,
+    ) -> Result<SubscriptionCountsAPI, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+              SELECT status, COUNT(*) AS "count!"
+              FROM subscriptions
+              WHERE user_id = $1
+              GROUP BY status
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?;
+        let mut counts = SubscriptionCountsAPI::default();
+
+        for row in rows {
+            counts.total += row.count;
+            match SubscriptionStatus::try_from(row.status) {
+                Ok(SubscriptionStatus::Confirmed) => counts.confirmed = row.count,
+                Ok(SubscriptionStatus::PendingConfirmation) => {
+                    counts.pending_confirmation = row.count
+                }
+                Ok(SubscriptionStatus::Unsubscribed) => counts.unsubscribed = row.count,
+                Ok(SubscriptionStatus::Bounced) => counts.bounced = row.count,
+                Ok(SubscriptionStatus::Complained) => counts.complained = row.count,
+                Err(_) => {}
+            }
+        }
+
+        Ok(counts)
+    }
+
+    /// Fetches the next page of an author's subscribers ordered by email, for
+    /// walking the whole list without holding a connection open.
+    pub async fn get_page_after_email(
+        user_id: &Uuid,
+        after_email: Option<&str>,
+        limit: i64,
+        pool: &PgPool,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            SubscriptionAPI,
+            r#"
+              SELECT email, id, name, status, subscribed_at
+              FROM subscriptions
+              WHERE user_id = $1
+                AND ($2::TEXT IS NULL OR email > $2)
+              ORDER BY email
+              LIMIT $3
+            "#,
+            user_id,
+            after_email,
+            limit
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Removes the subscription along with its confirmation tokens and any
+    /// deliveries still queued for it. Returns `false` if nothing matched.
+    pub async fn delete(
+        id: &Uuid,
+        user_id: &Uuid,
+        transaction: &mut Transaction<'_, Postgres>,
+    ) -> Result<bool, sqlx::Error> {
+        transaction
+            .execute(sqlx::query!(
+                r#"
+                  DELETE FROM subscription_tokens
+                  USING subscriptions
+                  WHERE subscription_tokens.subscriber_id = subscriptions.id
+                    AND subscriptions.id = $1
+                    AND subscriptions.user_id = $2
+                "#,
+                id,
+                user_id
+            ))
+            .await?;
+        transaction
+            .execute(sqlx::query!(
+                r#"
+                  DELETE FROM issue_delivery_queue
+                  USING subscriptions, newsletter_issues
+                  WHERE issue_delivery_queue.subscriber_email = subscriptions.email
+                    AND issue_delivery_queue.newsletter_issue_id = newsletter_issues.newsletter_issue_id
+                    AND newsletter_issues.user_id = subscriptions.user_id
+                    AND subscriptions.id = $1
+                    AND subscriptions.user_id = $2
+                "#,
+                id,
+                user_id
+            ))
+            .await?;
+        let n_deleted_rows = transaction
+            .execute(sqlx::query!(
+                r#"
+                  DELETE FROM subscriptions
+                  WHERE id = $1 AND user_id = $2
+                "#,
+                id,
+                user_id
+            ))
+            .await?
+            .rows_affected();
+
+        Ok(n_deleted_rows > 0)
+    }
+}
+
+/// Outcome of importing a single CSV row. `line` is the 1-based line number in
+/// the uploaded file, header included.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SubscriptionImportRowAPI {
+    pub accepted: bool,
+    pub email: String,
+    pub error: Option<String>,
+    pub line: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SubscriptionImportReportAPI {
+    pub accepted: i64,
+    pub rejected: i64,
+    pub rows: Vec<SubscriptionImportRowAPI>,
+}
+
 pub struct NewSubscriber {
     pub email: SubscriberEmail,
+    /// Language the subscriber is written to in, outside of issues.
+    pub locale: Locale,
     pub name: SubscriberName,
     pub user_id: Uuid,
 }
 
 impl NewSubscriber {
+    /// Looks up a subscription with the same email for the same author, locking
+    /// the row until the transaction completes.
+    pub async fn find_existing(
+        &self,
+        transaction: &mut Transaction<'_, Postgres>,
+    ) -> Result<Option<Subscription>, sqlx::Error> {
+        sqlx::query_as!(
+            Subscription,
+            r#"
+              SELECT id, email, name, status
+              FROM subscriptions
+              WHERE email = $1 AND user_id = $2
+              FOR UPDATE
+            "#,
+            self.email.as_ref(),
+            &self.user_id
+        )
+        .fetch_optional(&mut **transaction)
+        .await
+    }
+
+    /// Inserts a subscriber pending confirmation. Returns `None` when the
+    /// email is already on the list, including when a concurrent request
+    /// added it first.
     pub async fn insert_subscriber(
         &self,
         transaction: &mut Transaction<'_, Postgres>,
-    ) -> Result<Subscription, sqlx::Error> {
+    ) -> Result<Option<Subscription>, sqlx::Error> {
         let subscriber_id = Uuid::new_v4();
         let status = SubscriptionStatus::PendingConfirmation.as_str();
-        transaction
+        let inserted = sqlx::query!(
+            r#"
+              INSERT INTO subscriptions (
+                id,
+                email,
+                locale,
+                name,
+                subscribed_at,
+                status,
+                user_id
+              )
+              VALUES ($1, $2, $3, $4, $5, $6, $7)
+              ON CONFLICT (email, user_id) DO NOTHING
+              RETURNING id
+            "#,
+            &subscriber_id,
+            self.email.as_ref(),
+            self.locale.as_str(),
+            self.name.as_ref(),
+            Utc::now(),
+            status,
+            &self.user_id
+        )
+        .fetch_optional(&mut **transaction)
+        .await?;
+
+        Ok(inserted.map(|row| Subscription {
+            email: self.email.to_string(),
+            id: row.id,
+            name: self.name.as_ref().to_string(),
+            status: status.to_string(),
+        }))
+    }
+}
+
+impl NewSubscriber {
+    /// Inserts an imported subscriber, leaving any existing subscription for the
+    /// same email untouched. Returns `None` when the email was already present.
+    pub async fn import_subscriber(
+        &self,
+        status: &SubscriptionStatus,
+        subscribed_at: DateTime<Utc>,
+        transaction: &mut Transaction<'_, Postgres>,
+    ) -> Result<Option<Subscription>, sqlx::Error> {
+        let subscriber_id = Uuid::new_v4();
+        let status = status.as_str();
+        let n_inserted_rows = transaction
             .execute(sqlx::query!(
                 r#"
                   INSERT INTO subscriptions (
                     id,
                     email,
+                    locale,
                     name,
                     subscribed_at,
                     status,
                     user_id
                   )
-                  VALUES ($1, $2, $3, $4, $5, $6)
+                  VALUES ($1, $2, $3, $4, $5, $6, $7)
+                  ON CONFLICT (email, user_id) DO NOTHING
                 "#,
                 &subscriber_id,
                 self.email.as_ref(),
+                self.locale.as_str(),
                 self.name.as_ref(),
-                Utc::now(),
+                subscribed_at,
                 status,
                 &self.user_id
             ))
-            .await?;
+            .await?
+            .rows_affected();
+
+        if n_inserted_rows == 0 {
+            return Ok(None);
+        }
 
-        Ok(Subscription {
+        Ok(Some(Subscription {
             email: self.email.to_string(),
             id: subscriber_id,
             name: self.name.as_ref().to_string(),
             status: status.to_string(),
-        })
+        }))
     }
 }
 
@@ -140,6 +601,9 @@ impl std::fmt::Display for StoreTokenError {
 pub enum SubscriptionStatus {
     PendingConfirmation,
     Confirmed,
+    Unsubscribed,
+    Bounced,
+    Complained,
 }
 
 impl SubscriptionStatus {
@@ -147,6 +611,29 @@ impl SubscriptionStatus {
         match self {
             SubscriptionStatus::Confirmed => "confirmed",
             SubscriptionStatus::PendingConfirmation => "pending_confirmation",
+            SubscriptionStatus::Unsubscribed => "unsubscribed",
+            SubscriptionStatus::Bounced => "bounced",
+            SubscriptionStatus::Complained => "complained",
+        }
+    }
+}
+
+impl TryFrom<String> for SubscriptionStatus {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.to_lowercase().as_str() {
+            "confirmed" => Ok(Self::Confirmed),
+            "pending_confirmation" => Ok(Self::PendingConfirmation),
+            "unsubscribed" => Ok(Self::Unsubscribed),
+            "bounced" => Ok(Self::Bounced),
+            "complained" => Ok(Self::Complained),
+            other => Err(format!(
+                "{} is not a supported subscription status. \
+                Use either `pending_confirmation`, `confirmed`, `unsubscribed`, \
+                `bounced` or `complained`.",
+                other
+            )),
         }
     }
 }
@@ -43,6 +43,15 @@ impl User {
             .await?
             .try_into()
     }
+
+    pub async fn find_email_by_user_id(
+        user_id: &Uuid,
+        transaction: &mut Transaction<'_, Postgres>,
+    ) -> Result<String, sqlx::Error> {
+        sqlx::query_scalar!("SELECT email FROM users WHERE user_id = $1", user_id)
+            .fetch_one(&mut **transaction)
+            .await
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -51,6 +51,7 @@ impl UserProfile {
                 bio AS "bio_html!: String",
                 description,
                 display_name,
+                email_tracking_enabled,
                 username,
                 (
                   SELECT COUNT(*)
@@ -210,6 +211,45 @@ impl UserProfile {
         Ok(())
     }
 
+    /// Tracking is opt-in, so authors without a profile are not tracked.
+    pub async fn email_tracking_enabled(
+        user_id: &Uuid,
+        db_pool: &PgPool,
+    ) -> Result<bool, sqlx::Error> {
+        let enabled = sqlx::query_scalar!(
+            r#"
+              SELECT email_tracking_enabled
+              FROM user_profiles
+              WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_optional(db_pool)
+        .await?;
+
+        Ok(enabled.unwrap_or(false))
+    }
+
+    pub async fn update_email_tracking(
+        user_id: &Uuid,
+        enabled: bool,
+        db_pool: &PgPool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+              UPDATE user_profiles
+              SET email_tracking_enabled = $1
+              WHERE user_id = $2
+            "#,
+            enabled,
+            user_id
+        )
+        .execute(db_pool)
+        .await?;
+
+        Ok(())
+    }
+
     async fn update_avatar(
         user_id: &Uuid,
         avatar_url: &String,
@@ -272,6 +312,8 @@ pub struct UserProfileAPI {
     pub bio_html: String,
     pub description: String,
     pub display_name: String,
+    /// Whether opens and clicks of the author's issues are tracked.
+    pub email_tracking_enabled: bool,
     pub username: String,
     pub total_issues: Option<i64>,
 }
@@ -311,6 +353,34 @@ pub struct PublicProfileListItem {
     pub total_issues: i64,
 }
 
+/// How an author is presented in the emails sent on their behalf.
+pub struct EmailAuthor {
+    /// Empty when the author has not uploaded an avatar.
+    pub avatar_url: String,
+    /// The display name, or the username when it is not set.
+    pub name: String,
+}
+
+impl EmailAuthor {
+    /// Falls back to the username for authors who have no profile yet.
+    pub async fn find_by_user_id(user_id: &Uuid, pool: &PgPool) -> Result<Self, sqlx::Error> {
+        sqlx::query_as!(
+            EmailAuthor,
+            r#"
+              SELECT
+                COALESCE(avatar_url, '') AS "avatar_url!",
+                COALESCE(NULLIF(display_name, ''), username) AS "name!"
+              FROM users
+              LEFT JOIN user_profiles ON users.user_id = user_profiles.user_id
+              WHERE users.user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_one(pool)
+        .await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::models::UserProfile;
@@ -0,0 +1,130 @@
+use chrono::{Days, Utc};
+use redis::aio::ConnectionManager;
+use redis::{Client, Script};
+use secrecy::{ExposeSecret, SecretString};
+use std::sync::LazyLock;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+/// Takes up to `ARGV[3]` tokens from the bucket in `KEYS[1]`, which holds at
+/// most `ARGV[1]` tokens and refills at that many tokens per second. When
+/// `ARGV[2]` is positive, `KEYS[2]` counts the tokens taken today and caps
+/// them at that daily limit; `ARGV[4]` is the time left until the day rolls
+/// over, in milliseconds.
+///
+/// Returns the number of tokens taken and, when that falls short of the
+/// request, how many milliseconds until the next token is available.
+static ACQUIRE_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r#"
+        local capacity = tonumber(ARGV[1])
+        local daily_limit = tonumber(ARGV[2])
+        local requested = tonumber(ARGV[3])
+        local ms_until_tomorrow = tonumber(ARGV[4])
+
+        local time = redis.call('TIME')
+        local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+        local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+        local tokens = tonumber(bucket[1]) or capacity
+        local updated_at = tonumber(bucket[2]) or now
+        tokens = math.min(capacity, tokens + math.max(0, now - updated_at) * capacity / 1000)
+
+        local granted = math.min(requested, math.floor(tokens))
+        local wait = 0
+        if granted < requested then
+            wait = math.ceil((1 - (tokens - granted)) * 1000 / capacity)
+        end
+        if daily_limit > 0 then
+            local left_today = daily_limit - (tonumber(redis.call('GET', KEYS[2])) or 0)
+            if left_today < requested then
+                granted = math.max(0, math.min(granted, left_today))
+                wait = ms_until_tomorrow
+            end
+            if granted > 0 then
+                redis.call('INCRBY', KEYS[2], granted)
+                redis.call('PEXPIRE', KEYS[2], ms_until_tomorrow + 60000)
+            end
+        end
+        tokens = tokens - granted
+
+        redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', now)
+        redis.call('PEXPIRE', KEYS[1], 60000)
+        return {granted, wait}
+        "#,
+    )
+});
+
+/// How many sends a caller may go ahead with right now.
+#[derive(Debug, PartialEq)]
+pub struct Reservation {
+    pub granted: usize,
+    /// When fewer sends were granted than requested, how long until it is
+    /// worth asking for more.
+    pub retry_after: Duration,
+}
+
+/// Token bucket shared, through Redis, by every worker loop of every replica,
+/// so that together they stay within the email provider's quotas.
+pub struct RateLimiter {
+    client: Client,
+    connection: OnceCell<ConnectionManager>,
+    key_prefix: String,
+    sends_per_day: Option<u32>,
+    sends_per_second: u32,
+}
+
+impl RateLimiter {
+    pub fn new(
+        redis_uri: &SecretString,
+        key_prefix: String,
+        sends_per_second: u32,
+        sends_per_day: Option<u32>,
+    ) -> Result<Self, redis::RedisError> {
+        Ok(Self {
+            client: Client::open(redis_uri.expose_secret())?,
+            connection: OnceCell::new(),
+            key_prefix,
+            sends_per_day,
+            sends_per_second: sends_per_second.max(1),
+        })
+    }
+
+    /// Most sends that can be granted at once, when the bucket is full.
+    pub fn burst_size(&self) -> usize {
+        self.sends_per_second as usize
+    }
+
+    /// Takes up to `requested` tokens, never blocking: the caller decides what
+    /// to do with whatever could not be granted.
+    pub async fn acquire(&self, requested: usize) -> Result<Reservation, redis::RedisError> {
+        let mut connection = self
+            .connection
+            .get_or_try_init(|| self.client.get_connection_manager())
+            .await?
+            .clone();
+        let now = Utc::now();
+        let tomorrow = now
+            .date_naive()
+            .checked_add_days(Days::new(1))
+            .expect("Date out of range.")
+            .and_hms_opt(0, 0, 0)
+            .expect("Midnight is a valid time.")
+            .and_utc();
+        let ms_until_tomorrow = (tomorrow - now).num_milliseconds();
+
+        let (granted, wait_ms): (usize, u64) = ACQUIRE_SCRIPT
+            .key(format!("{}:bucket", self.key_prefix))
+            .key(format!("{}:day:{}", self.key_prefix, now.date_naive()))
+            .arg(self.sends_per_second)
+            .arg(self.sends_per_day.unwrap_or(0))
+            .arg(requested)
+            .arg(ms_until_tomorrow)
+            .invoke_async(&mut connection)
+            .await?;
+
+        Ok(Reservation {
+            granted,
+            retry_after: Duration::from_millis(wait_ms),
+        })
+    }
+}
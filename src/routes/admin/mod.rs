@@ -3,5 +3,7 @@ mod dashboard;
 pub mod authenticate;
 pub mod logout;
 pub mod newsletters;
+pub mod outbox;
 pub mod password;
+pub mod subscribers;
 pub mod user;
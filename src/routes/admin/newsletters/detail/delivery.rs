@@ -0,0 +1,186 @@
+use crate::authentication::UserId;
+use crate::models::{IssueDeliveryReportAPI, NewsletterIssue};
+use crate::utils::{Pagination, e400, e404, e500};
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, get, post, web};
+use anyhow::Context;
+use serde::Deserialize;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct DeliveryQuery {
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+#[get("/newsletters/{newsletter_issue_id}/delivery")]
+#[tracing::instrument(
+    name = "Retrieving delivery progress of a newsletter issue",
+    skip_all,
+    fields(user_id=%&*user_id)
+)]
+pub async fn get(
+    path: web::Path<(Uuid,)>,
+    pool: web::Data<PgPool>,
+    query: web::Query<DeliveryQuery>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let newsletter_issue_id = path.into_inner().0;
+    let pagination = Pagination::parse(query.page, query.per_page).map_err(e400)?;
+    NewsletterIssue::find_by_user_id_and_newsletter_issue_id(*user_id, &newsletter_issue_id, &pool)
+        .await
+        .context("Failed to find newsletter issue.")
+        .map_err(e404)?;
+    let report = IssueDeliveryReportAPI::find_by_newsletter_issue_id(
+        &newsletter_issue_id,
+        &pagination,
+        &pool,
+    )
+    .await
+    .context("Failed to query delivery progress.")
+    .map_err(e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(report))
+}
+
+impl NewsletterIssue {
+    fn validate_published(self) -> Result<Self, String> {
+        if self.published_at.is_none() {
+            return Err(String::from(
+                "The newsletter issue has not been published yet.",
+            ));
+        }
+
+        Ok(self)
+    }
+}
+
+#[post("/newsletters/{newsletter_issue_id}/delivery/pause")]
+#[tracing::instrument(
+    name = "Pausing delivery of a newsletter issue",
+    skip_all,
+    fields(user_id=%&*user_id)
+)]
+pub async fn pause(
+    path: web::Path<(Uuid,)>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let newsletter_issue_id = path.into_inner().0;
+    let mut transaction = begin_for_published_issue(&user_id, &newsletter_issue_id, &pool).await?;
+    NewsletterIssue::pause_delivery(&mut transaction, &newsletter_issue_id)
+        .await
+        .context("Failed to pause delivery.")
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(e500)?;
+
+    delivery_report(&newsletter_issue_id, &pool).await
+}
+
+#[post("/newsletters/{newsletter_issue_id}/delivery/resume")]
+#[tracing::instrument(
+    name = "Resuming delivery of a newsletter issue",
+    skip_all,
+    fields(user_id=%&*user_id)
+)]
+pub async fn resume(
+    path: web::Path<(Uuid,)>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let newsletter_issue_id = path.into_inner().0;
+    let mut transaction = begin_for_published_issue(&user_id, &newsletter_issue_id, &pool).await?;
+    NewsletterIssue::resume_delivery(&mut transaction, &newsletter_issue_id)
+        .await
+        .context("Failed to resume delivery.")
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(e500)?;
+
+    delivery_report(&newsletter_issue_id, &pool).await
+}
+
+/// Emails in a batch a worker has already claimed may still go out; every
+/// other queued email is dropped and logged as cancelled.
+#[post("/newsletters/{newsletter_issue_id}/delivery/cancel")]
+#[tracing::instrument(
+    name = "Cancelling delivery of a newsletter issue",
+    skip_all,
+    fields(user_id=%&*user_id)
+)]
+pub async fn cancel(
+    path: web::Path<(Uuid,)>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let newsletter_issue_id = path.into_inner().0;
+    let mut transaction = begin_for_published_issue(&user_id, &newsletter_issue_id, &pool).await?;
+    let cancelled = NewsletterIssue::cancel_delivery(&mut transaction, &newsletter_issue_id)
+        .await
+        .context("Failed to cancel delivery.")
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(e500)?;
+    tracing::info!(cancelled, "Cancelled the remaining deliveries of an issue.");
+
+    delivery_report(&newsletter_issue_id, &pool).await
+}
+
+/// Opens a transaction once the issue is known to be the user's and to have
+/// been published.
+async fn begin_for_published_issue(
+    user_id: &Uuid,
+    newsletter_issue_id: &Uuid,
+    pool: &PgPool,
+) -> Result<Transaction<'static, Postgres>, actix_web::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to begin database transaction.")
+        .map_err(e500)?;
+    NewsletterIssue::find_by_user_id_and_newsletter_issue_id_txn(
+        *user_id,
+        newsletter_issue_id,
+        &mut transaction,
+    )
+    .await
+    .context("Failed to find newsletter issue.")
+    .map_err(e404)?
+    .validate_published()
+    .map_err(e400)?;
+
+    Ok(transaction)
+}
+
+async fn delivery_report(
+    newsletter_issue_id: &Uuid,
+    pool: &PgPool,
+) -> Result<HttpResponse, actix_web::Error> {
+    let pagination = Pagination::parse(None, None).map_err(e500)?;
+    let report =
+        IssueDeliveryReportAPI::find_by_newsletter_issue_id(newsletter_issue_id, &pagination, pool)
+            .await
+            .context("Failed to query delivery progress.")
+            .map_err(e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(report))
+}
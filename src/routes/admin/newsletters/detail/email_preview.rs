@@ -0,0 +1,104 @@
+use crate::authentication::UserId;
+use crate::email_client::{EmailClient, UnsubscribeLinks};
+use crate::models::{
+    EmailBrandingSettings, EmailPreviewAPI, NewsletterIssue, NewsletterIssueEmail, User,
+    UserProfile,
+};
+use crate::startup::{ApplicationBaseUrl, ApplicationClientBaseUrl, HmacSecret};
+use crate::utils::{e404, e500};
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, get, web};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Stands in for the per-subscriber tracking token. It does not carry a valid
+/// signature, so opening the preview or following its links records nothing.
+const PREVIEW_TRACKING_TOKEN: &str = "preview";
+
+/// Renders the issue the way the delivery worker would, addressed to the
+/// author. The unsubscribe links lack the per-subscriber token, and tracked
+/// links and the open pixel carry a placeholder token.
+#[get("/newsletters/{newsletter_issue_id}/email_preview")]
+#[tracing::instrument(
+    name = "Previewing the email of a newsletter issue",
+    skip_all,
+    fields(user_id=%&*user_id)
+)]
+pub async fn get(
+    path: web::Path<(Uuid,)>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    client_base_url: web::Data<ApplicationClientBaseUrl>,
+    hmac_secret: web::Data<HmacSecret>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let newsletter_issue_id = path.into_inner().0;
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to begin database transaction.")
+        .map_err(e500)?;
+    let mut issue: NewsletterIssueEmail =
+        NewsletterIssue::find_by_user_id_and_newsletter_issue_id_txn(
+            *user_id,
+            &newsletter_issue_id,
+            &mut transaction,
+        )
+        .await
+        .context("Failed to query for newsletter issue.")
+        .map_err(e404)?
+        .into();
+    let author_email = User::find_email_by_user_id(&user_id, &mut transaction)
+        .await
+        .context("Failed to query for the author's email address.")
+        .map_err(e500)?;
+    let tracking_enabled = UserProfile::email_tracking_enabled(&user_id, &pool)
+        .await
+        .context("Failed to query whether email tracking is enabled.")
+        .map_err(e500)?;
+    let branding = EmailBrandingSettings::find_email_branding_by_user_id(&user_id, &pool)
+        .await
+        .context("Failed to query for the email branding.")
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(e500)?;
+
+    let tracking_token = if tracking_enabled {
+        issue = issue.with_tracking(&base_url.0, &hmac_secret.0);
+        Some(PREVIEW_TRACKING_TOKEN)
+    } else {
+        None
+    };
+    let unsubscribe_links = UnsubscribeLinks {
+        one_click_url: format!("{}/subscriptions/unsubscribe", base_url.0),
+        page_url: format!("{}/subscriptions/unsubscribe", client_base_url.0),
+    };
+    let preview: EmailPreviewAPI = email_client
+        .render_newsletter(
+            &issue.title,
+            &issue.html_content,
+            &issue.text_content,
+            true,
+            &branding,
+        )
+        .context("Failed to render the newsletter.")
+        .map_err(e500)?
+        .with_merge_tags(issue.merge_tags)
+        .personalise(
+            &author_email,
+            None,
+            tracking_token,
+            Some(&unsubscribe_links),
+        )
+        .into();
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(preview))
+}
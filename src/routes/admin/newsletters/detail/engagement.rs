@@ -0,0 +1,35 @@
+use crate::authentication::UserId;
+use crate::models::{IssueEngagementAPI, NewsletterIssue};
+use crate::utils::{e404, e500};
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, get, web};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[get("/newsletters/{newsletter_issue_id}/engagement")]
+#[tracing::instrument(
+    name = "Retrieving opens and clicks of a newsletter issue",
+    skip_all,
+    fields(user_id=%&*user_id)
+)]
+pub async fn get(
+    path: web::Path<(Uuid,)>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let newsletter_issue_id = path.into_inner().0;
+    NewsletterIssue::find_by_user_id_and_newsletter_issue_id(*user_id, &newsletter_issue_id, &pool)
+        .await
+        .context("Failed to find newsletter issue.")
+        .map_err(e404)?;
+    let engagement = IssueEngagementAPI::find_by_newsletter_issue_id(&newsletter_issue_id, &pool)
+        .await
+        .context("Failed to query opens and clicks.")
+        .map_err(e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(engagement))
+}
@@ -0,0 +1,78 @@
+use crate::authentication::UserId;
+use crate::models::{IssueDeliveryFailureAPI, IssueDeliveryRequeueAPI, NewsletterIssue};
+use crate::utils::{e404, e500};
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, get, post, web};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[get("/newsletters/{newsletter_issue_id}/failures")]
+#[tracing::instrument(
+    name = "Retrieving failed deliveries of a newsletter issue",
+    skip_all,
+    fields(user_id=%&*user_id)
+)]
+pub async fn get(
+    path: web::Path<(Uuid,)>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let newsletter_issue_id = path.into_inner().0;
+    NewsletterIssue::find_by_user_id_and_newsletter_issue_id(*user_id, &newsletter_issue_id, &pool)
+        .await
+        .context("Failed to find newsletter issue.")
+        .map_err(e404)?;
+    let failures =
+        IssueDeliveryFailureAPI::get_by_newsletter_issue_id(&newsletter_issue_id, &user_id, &pool)
+            .await
+            .context("Failed to query failed deliveries.")
+            .map_err(e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(failures))
+}
+
+#[post("/newsletters/{newsletter_issue_id}/failures/requeue")]
+#[tracing::instrument(
+    name = "Requeueing failed deliveries of a newsletter issue",
+    skip_all,
+    fields(user_id=%&*user_id)
+)]
+pub async fn requeue(
+    path: web::Path<(Uuid,)>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let newsletter_issue_id = path.into_inner().0;
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to begin database transaction.")
+        .map_err(e500)?;
+    NewsletterIssue::find_by_user_id_and_newsletter_issue_id_txn(
+        *user_id,
+        &newsletter_issue_id,
+        &mut transaction,
+    )
+    .await
+    .context("Failed to find newsletter issue.")
+    .map_err(e404)?;
+    let requeued =
+        IssueDeliveryFailureAPI::requeue(&newsletter_issue_id, &user_id, &mut transaction)
+            .await
+            .context("Failed to requeue failed deliveries.")
+            .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(IssueDeliveryRequeueAPI { requeued }))
+}
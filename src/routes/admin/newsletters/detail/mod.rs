@@ -1,6 +1,12 @@
 mod index;
 
 pub mod cover_image;
+pub mod delivery;
+pub mod email_preview;
+pub mod engagement;
+pub mod failures;
 pub mod publish;
+pub mod schedule;
+pub mod test_send;
 
 pub use index::*;
@@ -6,7 +6,7 @@ use actix_web::http::header::ContentType;
 use actix_web::{HttpResponse, put, web};
 use anyhow::Context;
 use serde::Deserialize;
-use sqlx::{Executor, PgPool, Postgres, Transaction};
+use sqlx::PgPool;
 use uuid::Uuid;
 
 const SUCCESS_MESSAGE: &str =
@@ -56,7 +56,7 @@ pub async fn put(
     .await
     .context("Failed to publish newsletter issue details.")
     .map_err(e500)?;
-    enqueue_delivery_tasks(&mut transaction, newsletter_issue_id, &user_id)
+    NewsletterIssue::enqueue_delivery_tasks(&mut transaction, newsletter_issue_id, &user_id)
         .await
         .context("Failed to enqueue delivery tasks.")
         .map_err(e500)?;
@@ -68,29 +68,3 @@ pub async fn put(
         .map_err(e500)?;
     Ok(response)
 }
-
-#[tracing::instrument(skip_all)]
-async fn enqueue_delivery_tasks(
-    transaction: &mut Transaction<'_, Postgres>,
-    newsletter_issue_id: Uuid,
-    user_id: &Uuid,
-) -> Result<(), sqlx::Error> {
-    transaction
-        .execute(sqlx::query!(
-            r#"
-              INSERT INTO issue_delivery_queue (
-                newsletter_issue_id,
-                subscriber_email
-              )
-              SELECT $1, email
-              FROM subscriptions
-              WHERE status = 'confirmed'
-              AND user_id = $2
-            "#,
-            newsletter_issue_id,
-            user_id
-        ))
-        .await?;
-
-    Ok(())
-}
@@ -0,0 +1,125 @@
+use crate::authentication::UserId;
+use crate::models::{NewsletterIssue, NewsletterIssueAPI};
+use crate::utils::{e400, e404, e500};
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, delete, put, web};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct ScheduleParams {
+    scheduled_for: DateTime<Utc>,
+}
+
+impl NewsletterIssue {
+    fn validate_unpublished(self) -> Result<Self, String> {
+        if self.published_at.is_some() {
+            return Err(String::from(
+                "The newsletter issue has already been published.",
+            ));
+        }
+
+        Ok(self)
+    }
+}
+
+#[put("/newsletters/{newsletter_issue_id}/schedule")]
+#[tracing::instrument(
+  name = "Schedule a newsletter issue",
+  skip_all,
+  fields(user_id=%*user_id)
+)]
+pub async fn put(
+    params: web::Json<ScheduleParams>,
+    path: web::Path<(Uuid,)>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let newsletter_issue_id = path.into_inner().0;
+
+    if params.scheduled_for <= Utc::now() {
+        return Err(e400("The scheduled time must be in the future."));
+    }
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to begin database transaction.")
+        .map_err(e500)?;
+    let newsletter_issue_api: NewsletterIssueAPI =
+        NewsletterIssue::find_by_user_id_and_newsletter_issue_id_txn(
+            *user_id,
+            &newsletter_issue_id,
+            &mut transaction,
+        )
+        .await
+        .context("Failed to query for newsletter issue.")
+        .map_err(e404)?
+        .validate_unpublished()
+        .map_err(e400)?
+        .validate_for_publish()
+        .map_err(e400)?
+        .schedule(Some(params.scheduled_for), &mut transaction)
+        .await
+        .context("Failed to schedule newsletter issue.")
+        .map_err(e500)?
+        .into();
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(newsletter_issue_api))
+}
+
+#[delete("/newsletters/{newsletter_issue_id}/schedule")]
+#[tracing::instrument(
+  name = "Cancel a scheduled newsletter issue",
+  skip_all,
+  fields(user_id=%*user_id)
+)]
+pub async fn delete(
+    path: web::Path<(Uuid,)>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let newsletter_issue_id = path.into_inner().0;
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to begin database transaction.")
+        .map_err(e500)?;
+    let newsletter_issue_api: NewsletterIssueAPI =
+        NewsletterIssue::find_by_user_id_and_newsletter_issue_id_txn(
+            *user_id,
+            &newsletter_issue_id,
+            &mut transaction,
+        )
+        .await
+        .context("Failed to query for newsletter issue.")
+        .map_err(e404)?
+        .validate_unpublished()
+        .map_err(e400)?
+        .schedule(None, &mut transaction)
+        .await
+        .context("Failed to cancel newsletter issue schedule.")
+        .map_err(e500)?
+        .into();
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(newsletter_issue_api))
+}
@@ -0,0 +1,187 @@
+use crate::authentication::UserId;
+use crate::domain::SubscriberEmail;
+use crate::email_client::{EmailClient, RenderedNewsletter};
+use crate::models::{
+    EmailBrandingSettings, EmailSuppression, NewsletterIssue, NewsletterIssueEmail,
+    NewsletterTestSend, User,
+};
+use crate::utils::{ResponseMessage, e400, e404, e429, e500};
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, post, web};
+use anyhow::Context;
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Addresses a test email may be sent to on top of the author's own.
+const MAX_EXTRA_RECIPIENTS: usize = 3;
+const MAX_TEST_SENDS_PER_HOUR: i64 = 10;
+
+#[derive(Deserialize)]
+pub struct TestSendParams {
+    #[serde(default)]
+    recipients: Vec<String>,
+}
+
+impl TestSendParams {
+    fn parse_recipients(self, author_email: String) -> Result<Vec<String>, String> {
+        if self.recipients.len() > MAX_EXTRA_RECIPIENTS {
+            return Err(format!(
+                "A test email can be sent to at most {} extra addresses.",
+                MAX_EXTRA_RECIPIENTS
+            ));
+        }
+        let mut recipients = vec![author_email];
+        for recipient in self.recipients {
+            let recipient = SubscriberEmail::parse(recipient)?.as_ref().to_string();
+            if !recipients.contains(&recipient) {
+                recipients.push(recipient);
+            }
+        }
+
+        Ok(recipients)
+    }
+}
+
+#[post("/newsletters/{newsletter_issue_id}/test_send")]
+#[tracing::instrument(
+  name = "Send a test email of a newsletter issue",
+  skip_all,
+  fields(user_id=%*user_id)
+)]
+pub async fn post(
+    params: web::Json<TestSendParams>,
+    path: web::Path<(Uuid,)>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let newsletter_issue_id = path.into_inner().0;
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to begin database transaction.")
+        .map_err(e500)?;
+    let issue: NewsletterIssueEmail = NewsletterIssue::find_by_user_id_and_newsletter_issue_id_txn(
+        *user_id,
+        &newsletter_issue_id,
+        &mut transaction,
+    )
+    .await
+    .context("Failed to query for newsletter issue.")
+    .map_err(e404)?
+    .into();
+    let author_email = User::find_email_by_user_id(&user_id, &mut transaction)
+        .await
+        .context("Failed to query for the author's email address.")
+        .map_err(e500)?;
+    let recipients = params.0.parse_recipients(author_email).map_err(e400)?;
+    // Leave out extra addresses the provider reported as bouncing or
+    // complaining.
+    let suppressed: HashSet<String> = EmailSuppression::find_suppressed(&recipients[1..], &pool)
+        .await
+        .context("Failed to check the email suppression list.")
+        .map_err(e500)?
+        .into_iter()
+        .collect();
+    let recipients: Vec<String> = recipients
+        .into_iter()
+        .filter(|recipient| !suppressed.contains(recipient))
+        .collect();
+
+    let sent_in_last_hour = NewsletterTestSend::count_in_last_hour(&user_id, &mut transaction)
+        .await
+        .context("Failed to count recent test emails.")
+        .map_err(e500)?;
+    if sent_in_last_hour >= MAX_TEST_SENDS_PER_HOUR {
+        return Err(e429(format!(
+            "At most {} test emails can be sent per hour.",
+            MAX_TEST_SENDS_PER_HOUR
+        )));
+    }
+    let test_send_id = NewsletterTestSend::record(
+        &user_id,
+        &newsletter_issue_id,
+        recipients.len(),
+        &mut transaction,
+    )
+    .await
+    .context("Failed to record the test email.")
+    .map_err(e500)?;
+    // Release the lock on the user before talking to the email provider.
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction.")
+        .map_err(e500)?;
+
+    let (n_sent, result) =
+        send_test_email(issue, &recipients, &user_id, &pool, &email_client).await;
+    if let Err(e) = result {
+        // Only count the emails that went out.
+        NewsletterTestSend::settle_failed(test_send_id, n_sent, &pool)
+            .await
+            .context("Failed to release the test email.")
+            .map_err(e500)?;
+        return Err(e500(e));
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(ResponseMessage::from(format!(
+            "A test email has been sent to {}.",
+            recipients.join(", ")
+        ))))
+}
+
+/// Sends the test email to each recipient in turn, stopping at the first
+/// failure. Returns how many emails went out along with the outcome.
+async fn send_test_email(
+    issue: NewsletterIssueEmail,
+    recipients: &[String],
+    user_id: &UserId,
+    pool: &PgPool,
+    email_client: &EmailClient,
+) -> (usize, Result<(), anyhow::Error>) {
+    let newsletter = match render_test_email(issue, user_id, pool, email_client).await {
+        Ok(newsletter) => newsletter,
+        Err(e) => return (0, Err(e)),
+    };
+    for (n_sent, recipient) in recipients.iter().enumerate() {
+        if let Err(e) = email_client
+            .send_outgoing_email(&newsletter.personalise(recipient, None, None, None))
+            .await
+            .context("Failed to send the test email.")
+        {
+            return (n_sent, Err(e));
+        }
+    }
+
+    (recipients.len(), Ok(()))
+}
+
+async fn render_test_email(
+    issue: NewsletterIssueEmail,
+    user_id: &UserId,
+    pool: &PgPool,
+    email_client: &EmailClient,
+) -> Result<RenderedNewsletter, anyhow::Error> {
+    let branding = EmailBrandingSettings::find_email_branding_by_user_id(user_id, pool)
+        .await
+        .context("Failed to query for the email branding.")?;
+    let subject = format!("[Test] {}", issue.title);
+    let newsletter = email_client
+        .render_newsletter(
+            &subject,
+            &issue.html_content,
+            &issue.text_content,
+            false,
+            &branding,
+        )
+        .context("Failed to render the newsletter.")?
+        .with_merge_tags(issue.merge_tags);
+
+    Ok(newsletter)
+}
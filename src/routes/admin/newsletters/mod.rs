@@ -2,5 +2,6 @@ mod index;
 
 pub mod detail;
 pub mod drafts;
+pub mod scheduled;
 
 pub use index::*;
@@ -0,0 +1,33 @@
+use crate::email_client::EmailClient;
+use crate::utils::{e404, e500};
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{HttpResponse, get, web};
+use anyhow::Context;
+
+/// Downloads a message as written to the outbox, ready to be opened in a
+/// mail client.
+#[get("/outbox/{message_id}")]
+#[tracing::instrument(name = "Downloading an outbox message", skip_all)]
+pub async fn get(
+    path: web::Path<(String,)>,
+    email_client: web::Data<EmailClient>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let message_id = path.into_inner().0;
+    let outbox = email_client
+        .outbox()
+        .ok_or_else(|| e404("Emails are not written to an outbox."))?;
+    let message = outbox
+        .read(&message_id)
+        .await
+        .context("Failed to read outbox message.")
+        .map_err(e500)?
+        .ok_or_else(|| e404("Message not found."))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("message/rfc822")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!("{message_id}.eml"))],
+        })
+        .body(message))
+}
@@ -0,0 +1,27 @@
+use crate::email_client::EmailClient;
+use crate::utils::{e404, e500};
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, get, web};
+use anyhow::Context;
+
+/// Most messages listed, newest first.
+const OUTBOX_LISTING_SIZE: usize = 50;
+
+/// Only available when emails are written to the outbox, which is meant for
+/// local development.
+#[get("/outbox")]
+#[tracing::instrument(name = "Listing outbox messages", skip_all)]
+pub async fn get(email_client: web::Data<EmailClient>) -> Result<HttpResponse, actix_web::Error> {
+    let outbox = email_client
+        .outbox()
+        .ok_or_else(|| e404("Emails are not written to an outbox."))?;
+    let messages = outbox
+        .list(OUTBOX_LISTING_SIZE)
+        .await
+        .context("Failed to list outbox messages.")
+        .map_err(e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(messages))
+}
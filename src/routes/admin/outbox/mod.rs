@@ -0,0 +1,5 @@
+mod index;
+
+pub mod detail;
+
+pub use index::*;
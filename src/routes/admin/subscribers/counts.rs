@@ -0,0 +1,28 @@
+use crate::authentication::UserId;
+use crate::models::SubscriptionAPI;
+use crate::utils::e500;
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, get, web};
+use anyhow::Context;
+use sqlx::PgPool;
+
+#[get("/subscribers/counts")]
+#[tracing::instrument(
+    name = "Counting user's subscribers by status",
+    skip_all,
+    fields(user_id=%&*user_id)
+)]
+pub async fn get(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let counts = SubscriptionAPI::count_by_status(&user_id, &pool)
+        .await
+        .context("Failed to count subscribers.")
+        .map_err(e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(counts))
+}
@@ -0,0 +1,100 @@
+use crate::authentication::UserId;
+use crate::models::SubscriptionAPI;
+use crate::utils::e500;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{HttpResponse, get, web};
+use anyhow::Context;
+use futures_util::stream;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const EXPORT_BATCH_SIZE: i64 = 500;
+
+enum ExportState {
+    Header,
+    After(Option<String>),
+    Done,
+}
+
+fn write_csv<I, R>(records: I) -> Result<web::Bytes, anyhow::Error>
+where
+    I: IntoIterator<Item = R>,
+    R: IntoIterator,
+    R::Item: AsRef<[u8]>,
+{
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    for record in records {
+        writer.write_record(record)?;
+    }
+
+    Ok(writer.into_inner()?.into())
+}
+
+async fn next_chunk(
+    pool: &PgPool,
+    user_id: &Uuid,
+    state: ExportState,
+) -> Option<(Result<web::Bytes, actix_web::Error>, ExportState)> {
+    let after_email = match state {
+        ExportState::Header => {
+            let header = write_csv([["email", "name", "status", "subscribed_at"]]);
+            return Some((header.map_err(e500), ExportState::After(None)));
+        }
+        ExportState::After(after_email) => after_email,
+        ExportState::Done => return None,
+    };
+    let subscribers = match SubscriptionAPI::get_page_after_email(
+        user_id,
+        after_email.as_deref(),
+        EXPORT_BATCH_SIZE,
+        pool,
+    )
+    .await
+    .context("Failed to query subscribers for export.")
+    {
+        Ok(subscribers) => subscribers,
+        Err(e) => return Some((Err(e500(e)), ExportState::Done)),
+    };
+    let last = subscribers.last()?;
+    let next_state = if (subscribers.len() as i64) < EXPORT_BATCH_SIZE {
+        ExportState::Done
+    } else {
+        ExportState::After(Some(last.email.clone()))
+    };
+    let chunk = write_csv(subscribers.iter().map(|subscriber| {
+        [
+            subscriber.email.clone(),
+            subscriber.name.clone(),
+            subscriber.status.clone(),
+            subscriber.subscribed_at.to_rfc3339(),
+        ]
+    }));
+
+    Some((chunk.map_err(e500), next_state))
+}
+
+#[get("/subscribers/export")]
+#[tracing::instrument(
+    name = "Exporting user's subscribers",
+    skip_all,
+    fields(user_id=%&*user_id)
+)]
+pub async fn get(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = *user_id.into_inner();
+    let body = stream::unfold(ExportState::Header, move |state| {
+        let pool = pool.clone();
+        async move { next_chunk(&pool, &user_id, state).await }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(String::from("subscribers.csv"))],
+        })
+        .streaming(body))
+}
@@ -0,0 +1,234 @@
+use crate::authentication::UserId;
+use crate::domain::{Locale, SubscriberEmail, SubscriberName};
+use crate::models::{
+    EmailSuppression, NewSubscriber, Subscription, SubscriptionImportReportAPI,
+    SubscriptionImportRowAPI, SubscriptionStatus,
+};
+use crate::utils::{e400, e500};
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, post, web};
+use anyhow::Context;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::collections::HashSet;
+
+#[derive(Deserialize)]
+pub struct ImportQuery {
+    /// Treat imported subscribers as already confirmed instead of sending them
+    /// a confirmation email.
+    #[serde(default)]
+    confirmed: bool,
+}
+
+#[derive(Deserialize)]
+struct ImportRecord {
+    email: String,
+    name: String,
+    #[serde(default)]
+    subscribed_at: Option<String>,
+}
+
+struct ImportRow {
+    line: u64,
+    new_subscriber: NewSubscriber,
+    subscribed_at: DateTime<Utc>,
+}
+
+impl ImportRecord {
+    fn parse(self, user_id: uuid::Uuid) -> Result<(NewSubscriber, DateTime<Utc>), String> {
+        let email = SubscriberEmail::parse(self.email)?;
+        let name = SubscriberName::parse(self.name)?;
+        let subscribed_at = match self.subscribed_at.filter(|s| !s.is_empty()) {
+            Some(subscribed_at) => parse_subscribed_at(&subscribed_at)?,
+            None => Utc::now(),
+        };
+
+        Ok((
+            NewSubscriber {
+                email,
+                // The CSV says nothing of the language subscribers read.
+                locale: Locale::default(),
+                name,
+                user_id,
+            },
+            subscribed_at,
+        ))
+    }
+}
+
+/// Accepts either an RFC 3339 timestamp or a plain `YYYY-MM-DD` date.
+fn parse_subscribed_at(s: &str) -> Result<DateTime<Utc>, String> {
+    let subscribed_at = DateTime::parse_from_rfc3339(s)
+        .map(|datetime| datetime.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_utc())
+        })
+        .map_err(|_| format!("{} is not a valid subscription date.", s))?;
+
+    if subscribed_at > Utc::now() {
+        return Err(format!("{} is in the future.", s));
+    }
+
+    Ok(subscribed_at)
+}
+
+#[post("/subscribers/import")]
+#[tracing::instrument(
+    name = "Importing user's subscribers",
+    skip_all,
+    fields(user_id=%&*user_id, confirmed=%query.confirmed)
+)]
+pub async fn post(
+    body: web::Bytes,
+    pool: web::Data<PgPool>,
+    query: web::Query<ImportQuery>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(&body[..]);
+    let headers = reader
+        .headers()
+        .context("Failed to read the CSV header.")
+        .map_err(e400)?
+        .clone();
+    let Some(email_column) = headers.iter().position(|h| h == "email") else {
+        return Err(e400("The CSV header must include an `email` column."));
+    };
+
+    if !headers.iter().any(|h| h == "name") {
+        return Err(e400("The CSV header must include a `name` column."));
+    }
+
+    let mut report = SubscriptionImportReportAPI::default();
+    let mut rows: Vec<ImportRow> = Vec::new();
+
+    for result in reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map_or(0, |position| position.line());
+                report.reject(line, String::new(), e.to_string());
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, |position| position.line());
+        let email = record.get(email_column).unwrap_or_default().to_string();
+        let parsed = record
+            .deserialize::<ImportRecord>(Some(&headers))
+            .map_err(|e| e.to_string())
+            .and_then(|record| record.parse(*user_id));
+
+        match parsed {
+            Ok((new_subscriber, subscribed_at)) => rows.push(ImportRow {
+                line,
+                new_subscriber,
+                subscribed_at,
+            }),
+            Err(error) => report.reject(line, email, error),
+        }
+    }
+
+    let status = if query.confirmed {
+        SubscriptionStatus::Confirmed
+    } else {
+        SubscriptionStatus::PendingConfirmation
+    };
+    let emails: Vec<String> = rows
+        .iter()
+        .map(|row| row.new_subscriber.email.to_string())
+        .collect();
+    let suppressed: HashSet<String> = EmailSuppression::find_suppressed(&emails, &pool)
+        .await
+        .context("Failed to check the email suppression list.")
+        .map_err(e500)?
+        .into_iter()
+        .collect();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to begin database transaction.")
+        .map_err(e500)?;
+    let mut n_queued_confirmations = 0;
+
+    for row in rows {
+        let email = row.new_subscriber.email.to_string();
+
+        if suppressed.contains(&email) {
+            report.reject(
+                row.line,
+                email,
+                "Address is on the suppression list.".into(),
+            );
+            continue;
+        }
+
+        let subscription = row
+            .new_subscriber
+            .import_subscriber(&status, row.subscribed_at, &mut transaction)
+            .await
+            .context("Failed to import subscriber.")
+            .map_err(e500)?;
+        let Some(subscription) = subscription else {
+            report.reject(row.line, email, "Already subscribed.".into());
+            continue;
+        };
+
+        report.accept(row.line, email);
+
+        if !query.confirmed {
+            let subscription_token = Subscription::generate_subscription_token();
+            subscription
+                .store_token(&mut transaction, &subscription_token)
+                .await
+                .context("Failed to store the confirmation token for an imported subscriber.")
+                .map_err(e500)?
+                .queue_confirmation_email(&mut transaction, &subscription_token)
+                .await
+                .context("Failed to queue the confirmation email of an imported subscriber.")
+                .map_err(e500)?;
+            n_queued_confirmations += 1;
+        }
+    }
+
+    if n_queued_confirmations > 0 {
+        Subscription::notify_delivery_workers(&mut transaction)
+            .await
+            .context("Failed to notify the delivery workers.")
+            .map_err(e500)?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to import subscribers.")
+        .map_err(e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(report))
+}
+
+impl SubscriptionImportReportAPI {
+    fn accept(&mut self, line: u64, email: String) {
+        self.accepted += 1;
+        self.rows.push(SubscriptionImportRowAPI {
+            accepted: true,
+            email,
+            error: None,
+            line,
+        });
+    }
+
+    fn reject(&mut self, line: u64, email: String, error: String) {
+        self.rejected += 1;
+        self.rows.push(SubscriptionImportRowAPI {
+            accepted: false,
+            email,
+            error: Some(error),
+            line,
+        });
+    }
+}
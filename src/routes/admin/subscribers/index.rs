@@ -0,0 +1,55 @@
+use crate::authentication::UserId;
+use crate::models::{SubscriptionAPI, SubscriptionFilter, SubscriptionStatus};
+use crate::utils::{Pagination, e400, e500, is_empty_or_whitespace};
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, get, web};
+use anyhow::Context;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+#[derive(Deserialize)]
+pub struct SubscribersQuery {
+    email: Option<String>,
+    page: Option<i64>,
+    per_page: Option<i64>,
+    status: Option<String>,
+}
+
+impl TryFrom<SubscribersQuery> for SubscriptionFilter {
+    type Error = String;
+
+    fn try_from(query: SubscribersQuery) -> Result<Self, Self::Error> {
+        let pagination = Pagination::parse(query.page, query.per_page)?;
+        let status = query.status.map(SubscriptionStatus::try_from).transpose()?;
+        let email = query.email.filter(|email| !is_empty_or_whitespace(email));
+
+        Ok(Self {
+            email,
+            pagination,
+            status,
+        })
+    }
+}
+
+#[get("/subscribers")]
+#[tracing::instrument(
+    name = "Retrieving user's subscribers",
+    skip_all,
+    fields(user_id=%&*user_id)
+)]
+pub async fn get(
+    pool: web::Data<PgPool>,
+    query: web::Query<SubscribersQuery>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let filter = SubscriptionFilter::try_from(query.into_inner()).map_err(e400)?;
+    let subscribers = SubscriptionAPI::get_by_user_id(&user_id, &filter, &pool)
+        .await
+        .context("Failed to query subscribers.")
+        .map_err(e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(subscribers))
+}
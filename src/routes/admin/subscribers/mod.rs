@@ -0,0 +1,8 @@
+mod index;
+
+pub mod counts;
+pub mod detail;
+pub mod export;
+pub mod import;
+
+pub use index::*;
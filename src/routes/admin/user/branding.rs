@@ -0,0 +1,64 @@
+use crate::authentication::UserId;
+use crate::models::{EmailBrandingAPI, EmailBrandingSettings};
+use crate::utils::{e400, e404, e500};
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, get, put, web};
+use anyhow::Context;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+#[get("/user/branding")]
+#[tracing::instrument(name = "Get email branding", skip_all)]
+pub async fn get(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let branding: EmailBrandingAPI =
+        EmailBrandingSettings::find_email_branding_api_by_user_id(&user_id.into_inner(), &pool)
+            .await
+            .context("Failed to find user.")
+            .map_err(e404)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .json(branding))
+}
+
+#[derive(Deserialize)]
+struct EmailBrandingParams {
+    pub accent_color: String,
+    pub footer_text: String,
+    pub postal_address: String,
+    pub publication_name: String,
+    pub reply_to_email: String,
+}
+
+/// Sets how the issues delivered from now on are branded.
+#[put("/user/branding")]
+#[tracing::instrument(
+  name = "Updating email branding",
+  skip_all,
+  fields(user_id=%*user_id)
+)]
+pub async fn put(
+    params: web::Json<EmailBrandingParams>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    EmailBrandingSettings {
+        accent_color: params.0.accent_color,
+        footer_text: params.0.footer_text,
+        postal_address: params.0.postal_address,
+        publication_name: params.0.publication_name,
+        reply_to_email: params.0.reply_to_email,
+        user_id: *user_id.into_inner(),
+    }
+    .validate()
+    .map_err(e400)?
+    .update(&pool)
+    .await
+    .context("Failed to update email branding.")
+    .map_err(e500)?;
+
+    Ok(HttpResponse::Ok().finish())
+}
@@ -2,5 +2,8 @@ mod index;
 
 pub mod avatar;
 pub mod banner;
+pub mod branding;
+pub mod logo;
+pub mod tracking;
 
 pub use index::*;
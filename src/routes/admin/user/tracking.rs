@@ -0,0 +1,33 @@
+use crate::authentication::UserId;
+use crate::models::UserProfile;
+use crate::utils::e500;
+use actix_web::{HttpResponse, put, web};
+use anyhow::Context;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+#[derive(Deserialize)]
+struct UpdateTrackingParams {
+    pub enabled: bool,
+}
+
+/// Turns open and click tracking on or off for the issues delivered from now
+/// on. Emails already sent keep their tracking links.
+#[put("/user/tracking")]
+#[tracing::instrument(
+  name = "Updating email tracking",
+  skip_all,
+  fields(user_id=%*user_id)
+)]
+pub async fn put(
+    params: web::Json<UpdateTrackingParams>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    UserProfile::update_email_tracking(&user_id.into_inner(), params.0.enabled, &pool)
+        .await
+        .context("Failed to update email tracking.")
+        .map_err(e500)?;
+
+    Ok(HttpResponse::Ok().finish())
+}
@@ -5,4 +5,6 @@ pub mod index;
 pub mod login;
 pub mod newsletters;
 pub mod subscriptions;
+pub mod tracking;
 pub mod users;
+pub mod webhooks;
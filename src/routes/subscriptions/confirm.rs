@@ -1,7 +1,9 @@
+use crate::models::Subscription;
 use crate::utils::error_chain_fmt;
 use actix_web::http::StatusCode;
 use actix_web::{HttpResponse, ResponseError, put, web};
 use anyhow::Context;
+use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 use uuid::Uuid;
 
@@ -16,6 +18,8 @@ pub enum ConfirmationError {
     UnexpectedError(#[from] anyhow::Error),
     #[error("There is no subscriber associated with the provided token.")]
     UnknownToken,
+    #[error("The confirmation link has expired. Subscribe again to receive a new one.")]
+    ExpiredToken,
 }
 
 impl std::fmt::Debug for ConfirmationError {
@@ -28,6 +32,7 @@ impl ResponseError for ConfirmationError {
     fn status_code(&self) -> StatusCode {
         match self {
             Self::UnknownToken => StatusCode::UNAUTHORIZED,
+            Self::ExpiredToken => StatusCode::GONE,
             Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -39,20 +44,31 @@ pub async fn put(
     parameters: web::Query<Parameters>,
     pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, ConfirmationError> {
-    let subscriber_id = get_subscriber_id_from_token(&pool, &parameters.subscription_token)
+    let token = get_token(&pool, &parameters.subscription_token)
         .await
         .context("Failed to retrieve the subscriber id associated with the provided token.")?
         .ok_or(ConfirmationError::UnknownToken)?;
-    confirm_subscriber(&pool, subscriber_id)
+
+    if token.created_at + Subscription::token_ttl() < Utc::now() {
+        return Err(ConfirmationError::ExpiredToken);
+    }
+
+    confirm_subscriber(&pool, token.subscriber_id)
         .await
         .context("Failed to update the subscriber status to `confirmed`.")?;
     Ok(HttpResponse::Ok().finish())
 }
 
+/// Only pending subscriptions are confirmed, so a stale link cannot resurrect a
+/// subscription after an unsubscribe.
 #[tracing::instrument(name = "Mark subscriber as confirmed", skip(subscriber_id, pool))]
 pub async fn confirm_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Result<(), sqlx::Error> {
     sqlx::query!(
-        r#"UPDATE subscriptions SET status = 'confirmed' WHERE id = $1"#,
+        r#"
+          UPDATE subscriptions
+          SET status = 'confirmed'
+          WHERE id = $1 AND status = 'pending_confirmation'
+        "#,
         subscriber_id,
     )
     .execute(pool)
@@ -60,16 +76,25 @@ pub async fn confirm_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Result<()
     Ok(())
 }
 
-#[tracing::instrument(name = "Get subscriber_id from token", skip(subscription_token, pool))]
-pub async fn get_subscriber_id_from_token(
+pub struct StoredToken {
+    created_at: DateTime<Utc>,
+    subscriber_id: Uuid,
+}
+
+#[tracing::instrument(name = "Get subscription token", skip(subscription_token, pool))]
+pub async fn get_token(
     pool: &PgPool,
     subscription_token: &str,
-) -> Result<Option<Uuid>, sqlx::Error> {
-    let result = sqlx::query!(
-        r#"SELECT subscriber_id FROM subscription_tokens WHERE subscription_token = $1"#,
+) -> Result<Option<StoredToken>, sqlx::Error> {
+    sqlx::query_as!(
+        StoredToken,
+        r#"
+          SELECT created_at, subscriber_id
+          FROM subscription_tokens
+          WHERE subscription_token = $1
+        "#,
         subscription_token,
     )
     .fetch_optional(pool)
-    .await?;
-    Ok(result.map(|r| r.subscriber_id))
+    .await
 }
@@ -1,10 +1,11 @@
 use crate::challenge::Base64Challenger;
-use crate::domain::{SubscriberEmail, SubscriberName};
+use crate::domain::{Locale, SubscriberEmail, SubscriberName};
 use crate::email_client::EmailClient;
-use crate::models::{NewSubscriber, Subscription, User};
+use crate::models::{EmailAuthor, EmailSuppression, NewSubscriber, Subscription, User};
 use crate::startup::{ApplicationClientBaseUrl, CaptchaSecret};
 use crate::utils::{e400, e404, e500};
-use actix_web::{HttpResponse, post, web};
+use actix_web::http::header::ACCEPT_LANGUAGE;
+use actix_web::{HttpRequest, HttpResponse, post, web};
 use anyhow::Context;
 use secrecy::SecretString;
 use serde::{Deserialize, Serialize};
@@ -22,6 +23,7 @@ pub struct SubscribeParams {
 impl NewSubscriber {
     async fn try_from(
         params: SubscribeParams,
+        locale: Locale,
         captcha_secret: SecretString,
         transaction: &mut Transaction<'_, Postgres>,
     ) -> Result<Self, actix_web::Error> {
@@ -41,6 +43,7 @@ impl NewSubscriber {
 
         Ok(Self {
             email,
+            locale,
             name,
             user_id: user.user_id,
         })
@@ -50,7 +53,7 @@ impl NewSubscriber {
 #[post("/subscriptions")]
 #[tracing::instrument(
     name = "Adding a new subscriber",
-    skip(params, pool, email_client, base_url, captcha_secret),
+    skip(params, pool, email_client, base_url, captcha_secret, request),
     fields(
         subscriber_email = %params.email,
         subscriber_name = %params.name
@@ -62,20 +65,67 @@ pub async fn post(
     email_client: web::Data<EmailClient>,
     params: web::Json<SubscribeParams>,
     pool: web::Data<PgPool>,
+    request: HttpRequest,
 ) -> Result<HttpResponse, actix_web::Error> {
+    let locale = request
+        .headers()
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map(Locale::from_accept_language)
+        .unwrap_or_default();
     let mut transaction = pool
         .begin()
         .await
         .context("Failed to acquire a Postgres connection from the pool")
         .map_err(e500)?;
     let new_subscriber =
-        NewSubscriber::try_from(params.0, captcha_secret.0.clone(), &mut transaction).await?;
-    let subscription_token = Subscription::generate_subscription_token();
-    let subscription = new_subscriber
+        NewSubscriber::try_from(params.0, locale, captcha_secret.0.clone(), &mut transaction)
+            .await?;
+    // Never mail an address the provider reported as bouncing or complaining,
+    // but do not reveal that to the caller either.
+    if EmailSuppression::is_suppressed(new_subscriber.email.as_ref(), &pool)
+        .await
+        .context("Failed to check the email suppression list.")
+        .map_err(e500)?
+    {
+        return Ok(HttpResponse::Ok().finish());
+    }
+
+    let inserted = new_subscriber
         .insert_subscriber(&mut transaction)
         .await
         .context("Failed to insert new subscriber in the database.")
-        .map_err(e500)?
+        .map_err(e500)?;
+    let subscription = match inserted {
+        Some(subscription) => subscription,
+        // The address is already on the list, possibly added by a concurrent
+        // request, so carry on with the stored subscription.
+        None => {
+            let existing = new_subscriber
+                .find_existing(&mut transaction)
+                .await
+                .context("Failed to look up an existing subscriber.")
+                .map_err(e500)?
+                .context("The existing subscriber could not be found.")
+                .map_err(e500)?;
+            // Respond exactly as for a new subscriber so the endpoint does not
+            // reveal who is already on the list.
+            if existing.is_confirmed() {
+                return Ok(HttpResponse::Ok().finish());
+            }
+            if existing.is_pending_confirmation() {
+                existing
+            } else {
+                existing
+                    .mark_pending_confirmation(&mut transaction)
+                    .await
+                    .context("Failed to reset the subscriber status to `pending_confirmation`.")
+                    .map_err(e500)?
+            }
+        }
+    };
+    let subscription_token = Subscription::generate_subscription_token();
+    let subscription = subscription
         .store_token(&mut transaction, &subscription_token)
         .await
         .context("Failed to store the confirmation token for a new subscriber.")
@@ -85,8 +135,18 @@ pub async fn post(
         .await
         .context("Failed to commit SQL transaction to store a new subscriber.")
         .map_err(e500)?;
+    let author = EmailAuthor::find_by_user_id(&new_subscriber.user_id, &pool)
+        .await
+        .context("Failed to find how to present the author in emails.")
+        .map_err(e500)?;
     subscription
-        .send_confirmation_email(&email_client, &base_url.0, &subscription_token)
+        .send_confirmation_email(
+            &email_client,
+            &base_url.0,
+            &subscription_token,
+            &author,
+            new_subscriber.locale,
+        )
         .await
         .context("Failed to send a confirmation email.")
         .map_err(e500)?;
@@ -1,5 +1,6 @@
 mod index;
 
 pub mod confirm;
+pub mod unsubscribe;
 
 pub use index::*;
@@ -0,0 +1,73 @@
+use crate::domain::UnsubscribeToken;
+use crate::models::SubscriptionStatus;
+use crate::startup::HmacSecret;
+use crate::utils::error_chain_fmt;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError, post, web};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    unsubscribe_token: String,
+}
+
+#[derive(thiserror::Error)]
+pub enum UnsubscribeError {
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+    #[error("{0}")]
+    InvalidToken(String),
+}
+
+impl std::fmt::Debug for UnsubscribeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for UnsubscribeError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::InvalidToken(_) => StatusCode::UNAUTHORIZED,
+            Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+// Serves both the client's unsubscribe page and RFC 8058 one-click requests,
+// whose `List-Unsubscribe=One-Click` form body carries nothing we need.
+#[post("/subscriptions/unsubscribe")]
+#[tracing::instrument(name = "Unsubscribe a subscriber", skip(parameters, pool, hmac_secret))]
+pub async fn post(
+    parameters: web::Query<Parameters>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, UnsubscribeError> {
+    let subscriber_id = UnsubscribeToken::parse(&parameters.unsubscribe_token, &hmac_secret.0)
+        .map_err(UnsubscribeError::InvalidToken)?;
+    unsubscribe_subscriber(&pool, subscriber_id)
+        .await
+        .context("Failed to update the subscriber status to `unsubscribed`.")?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Bounced and complaining subscribers keep their status, so that the
+/// suppression stays visible to their author.
+#[tracing::instrument(name = "Mark subscriber as unsubscribed", skip(subscriber_id, pool))]
+pub async fn unsubscribe_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+          UPDATE subscriptions
+          SET status = $1
+          WHERE id = $2
+            AND status IN ('confirmed', 'pending_confirmation')
+        "#,
+        SubscriptionStatus::Unsubscribed.as_str(),
+        subscriber_id,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use crate::domain::{LinkSignature, TrackingToken};
+use crate::models::{EngagementKind, NewsletterEngagementEvent};
+use crate::startup::HmacSecret;
+use crate::utils::e400;
+use actix_web::http::header::{CacheControl, CacheDirective, LOCATION};
+use actix_web::{HttpResponse, get, web};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+#[derive(Deserialize)]
+pub struct ClickParams {
+    signature: String,
+    url: String,
+}
+
+/// Only redirects to links signed when the issue was delivered, so that it
+/// cannot be used as an open redirect. A click is still followed through
+/// when its token is invalid or cannot be recorded.
+#[get("/tracking/click/{tracking_token}")]
+#[tracing::instrument(name = "Record a link click", skip_all)]
+pub async fn get(
+    path: web::Path<(String,)>,
+    params: web::Query<ClickParams>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = params.into_inner();
+    LinkSignature::verify(&params.url, &params.signature, &hmac_secret.0).map_err(e400)?;
+
+    match TrackingToken::parse(&path.into_inner().0, &hmac_secret.0) {
+        Ok((newsletter_issue_id, subscriber_id)) => {
+            if let Err(e) = NewsletterEngagementEvent::record(
+                &newsletter_issue_id,
+                &subscriber_id,
+                EngagementKind::Click,
+                Some(&params.url),
+                &pool,
+            )
+            .await
+            {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to record a link click.",
+                );
+            }
+        }
+        Err(e) => tracing::warn!(error.message = %e, "Ignoring an invalid click tracking token."),
+    }
+
+    Ok(HttpResponse::Found()
+        .insert_header((LOCATION, params.url))
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .finish())
+}
@@ -0,0 +1,2 @@
+pub mod click;
+pub mod open;
@@ -0,0 +1,49 @@
+use crate::domain::TrackingToken;
+use crate::models::{EngagementKind, NewsletterEngagementEvent};
+use crate::startup::HmacSecret;
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::{HttpResponse, get, web};
+use sqlx::PgPool;
+
+/// A transparent 1x1 GIF.
+const PIXEL: [u8; 43] = [
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+// Always answers with the pixel: a broken image in the reader's email client
+// would not help anyone, even when the token is invalid.
+#[get("/tracking/open/{tracking_token}")]
+#[tracing::instrument(name = "Record an email open", skip_all)]
+pub async fn get(
+    path: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> HttpResponse {
+    match TrackingToken::parse(&path.into_inner().0, &hmac_secret.0) {
+        Ok((newsletter_issue_id, subscriber_id)) => {
+            if let Err(e) = NewsletterEngagementEvent::record(
+                &newsletter_issue_id,
+                &subscriber_id,
+                EngagementKind::Open,
+                None,
+                &pool,
+            )
+            .await
+            {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to record an email open.",
+                );
+            }
+        }
+        Err(e) => tracing::warn!(error.message = %e, "Ignoring an invalid open tracking token."),
+    }
+
+    HttpResponse::Ok()
+        .content_type("image/gif")
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .body(PIXEL.as_slice())
+}
@@ -0,0 +1,154 @@
+use crate::domain::SubscriberEmail;
+use crate::models::{EmailSuppression, SuppressionReason};
+use crate::startup::EmailWebhookToken;
+use crate::utils::error_chain_fmt;
+use actix_web::http::StatusCode;
+use actix_web::http::header::{self, HeaderMap, HeaderValue};
+use actix_web::{HttpRequest, HttpResponse, ResponseError, post, web};
+use anyhow::Context;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use tracing::Span;
+
+/// The subset of Postmark's bounce and spam complaint webhook payloads we act on.
+/// Other record types (deliveries, opens, clicks...) are acknowledged and ignored.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PostmarkWebhook {
+    #[serde(default)]
+    description: Option<String>,
+    email: String,
+    #[serde(default)]
+    inactive: bool,
+    record_type: String,
+    #[serde(default, rename = "Type")]
+    kind: Option<String>,
+}
+
+impl PostmarkWebhook {
+    /// Soft bounces (full mailbox, transient DNS failures...) are not worth
+    /// suppressing: only permanent failures, or addresses Postmark itself has
+    /// deactivated, are. Neither is an address that is not valid.
+    fn suppression(self) -> Option<EmailSuppression> {
+        let reason = match (self.record_type.as_str(), self.kind.as_deref()) {
+            ("SpamComplaint", _) => SuppressionReason::Complained,
+            ("Bounce", Some("HardBounce" | "BadEmailAddress")) => SuppressionReason::Bounced,
+            ("Bounce", _) if self.inactive => SuppressionReason::Bounced,
+            _ => return None,
+        };
+
+        Some(EmailSuppression {
+            details: self.description,
+            email: SubscriberEmail::parse(self.email).ok()?,
+            reason,
+        })
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum WebhookError {
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+    #[error("Invalid webhook credentials.")]
+    Unauthorized,
+    #[error("Invalid webhook payload.")]
+    InvalidPayload(#[from] serde_json::Error),
+}
+
+impl std::fmt::Debug for WebhookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for WebhookError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::InvalidPayload(_) => StatusCode::BAD_REQUEST,
+            Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::new(self.status_code());
+
+        if let Self::Unauthorized = self {
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                HeaderValue::from_static(r#"Basic realm="webhooks""#),
+            );
+        }
+
+        response
+    }
+}
+
+/// Postmark authenticates webhooks with the basic auth credentials embedded in
+/// the configured URL; only the password is checked against our shared secret.
+fn basic_authentication_password(headers: &HeaderMap) -> Option<String> {
+    let encoded = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded).ok()?).ok()?;
+    let (_username, password) = decoded.split_once(':')?;
+
+    Some(password.to_string())
+}
+
+/// The body is only parsed once the caller is authenticated, so nobody else
+/// gets to have it deserialized or its content logged.
+#[post("/webhooks/postmark")]
+#[tracing::instrument(
+    name = "Handling a Postmark webhook",
+    skip_all,
+    fields(record_type = tracing::field::Empty)
+)]
+pub async fn post(
+    body: web::Bytes,
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+    webhook_token: web::Data<EmailWebhookToken>,
+) -> Result<HttpResponse, WebhookError> {
+    let password =
+        basic_authentication_password(request.headers()).ok_or(WebhookError::Unauthorized)?;
+
+    // Compare digests so the check does not short-circuit on the first
+    // mismatching byte of the secret.
+    if Sha256::digest(password.as_bytes())
+        != Sha256::digest(webhook_token.0.expose_secret().as_bytes())
+    {
+        return Err(WebhookError::Unauthorized);
+    }
+
+    let payload: PostmarkWebhook = serde_json::from_slice(&body)?;
+    Span::current().record("record_type", payload.record_type.as_str());
+    let Some(suppression) = payload.suppression() else {
+        return Ok(HttpResponse::Ok().finish());
+    };
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    suppression
+        .store(&mut transaction)
+        .await
+        .context("Failed to store the email suppression.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to store an email suppression.")?;
+    tracing::info!(
+        subscriber_email = %suppression.email,
+        reason = suppression.reason.as_str(),
+        "Suppressed an email address reported by the email provider.",
+    );
+
+    Ok(HttpResponse::Ok().finish())
+}
@@ -4,7 +4,8 @@ use crate::clients::s3_client::S3Client;
 use crate::configuration::{DatabaseSettings, Settings};
 use crate::email_client::EmailClient;
 use crate::routes::{
-    admin, captcha, health_check, index, login, newsletters, subscriptions, users,
+    admin, captcha, health_check, index, login, newsletters, subscriptions, tracking, users,
+    webhooks,
 };
 use actix_cors::Cors;
 use actix_session::SessionMiddleware;
@@ -20,6 +21,7 @@ use secrecy::{ExposeSecret, SecretString};
 use sqlx::PgPool;
 use sqlx::postgres::PgPoolOptions;
 use std::net::TcpListener;
+use tokio_util::sync::CancellationToken;
 use tracing_actix_web::TracingLogger;
 
 pub struct Application {
@@ -32,7 +34,8 @@ impl Application {
         let connection_pool = get_connection_pool(&configuration.database);
         let cloudinary_client = configuration.cloudinary_client.client();
         let s3_client = configuration.s3_client.client().await?;
-        let email_client = configuration.email_client.client();
+        let email_webhook_token = configuration.email_client.webhook_token.clone();
+        let email_client = configuration.email_client.client()?;
         let address = format!(
             "{}:{}",
             configuration.application.host, configuration.application.port
@@ -45,6 +48,7 @@ impl Application {
             connection_pool,
             cloudinary_client,
             email_client,
+            email_webhook_token,
             s3_client,
             configuration.application.base_url,
             configuration.application.hmac_secret,
@@ -63,7 +67,17 @@ impl Application {
         self.port
     }
 
-    pub async fn run_until_stopped(self) -> Result<(), std::io::Error> {
+    /// Serves requests until `shutdown` is cancelled, then stops accepting
+    /// connections and lets in-flight requests complete.
+    pub async fn run_until_stopped(
+        self,
+        shutdown: CancellationToken,
+    ) -> Result<(), std::io::Error> {
+        let handle = self.server.handle();
+        tokio::spawn(async move {
+            shutdown.cancelled().await;
+            handle.stop(true).await;
+        });
         self.server.await
     }
 }
@@ -78,6 +92,7 @@ async fn run(
     db_pool: PgPool,
     cloudinary_client: CloudinaryClient,
     email_client: EmailClient,
+    email_webhook_token: SecretString,
     s3_client: S3Client,
     base_url: String,
     hmac_secret: SecretString,
@@ -138,14 +153,38 @@ async fn run(
                     .service(admin::newsletters::get)
                     .service(admin::newsletters::post)
                     .service(admin::newsletters::drafts::get)
+                    .service(admin::newsletters::scheduled::get)
                     .service(admin::newsletters::detail::get)
                     .service(admin::newsletters::detail::put)
                     .service(admin::newsletters::detail::cover_image::put)
                     .service(admin::newsletters::detail::publish::put)
+                    .service(admin::newsletters::detail::schedule::put)
+                    .service(admin::newsletters::detail::schedule::delete)
+                    .service(admin::newsletters::detail::test_send::post)
+                    .service(admin::newsletters::detail::delivery::get)
+                    .service(admin::newsletters::detail::delivery::pause)
+                    .service(admin::newsletters::detail::delivery::resume)
+                    .service(admin::newsletters::detail::delivery::cancel)
+                    .service(admin::newsletters::detail::email_preview::get)
+                    .service(admin::newsletters::detail::engagement::get)
+                    .service(admin::newsletters::detail::failures::get)
+                    .service(admin::newsletters::detail::failures::requeue)
+                    .service(admin::outbox::get)
+                    .service(admin::outbox::detail::get)
+                    .service(admin::subscribers::get)
+                    .service(admin::subscribers::counts::get)
+                    .service(admin::subscribers::export::get)
+                    .service(admin::subscribers::import::post)
+                    .service(admin::subscribers::detail::get)
+                    .service(admin::subscribers::detail::delete)
                     .service(admin::user::get)
                     .service(admin::user::put)
                     .service(admin::user::banner::put)
                     .service(admin::user::avatar::put)
+                    .service(admin::user::branding::get)
+                    .service(admin::user::branding::put)
+                    .service(admin::user::logo::put)
+                    .service(admin::user::tracking::put)
                     .service(admin::password::put),
             )
             .service(captcha::get)
@@ -156,8 +195,12 @@ async fn run(
             .service(newsletters::by_user::get)
             .service(subscriptions::confirm::put)
             .service(subscriptions::post)
+            .service(subscriptions::unsubscribe::post)
+            .service(tracking::click::get)
+            .service(tracking::open::get)
             .service(users::detail::get)
             .service(users::get)
+            .service(webhooks::postmark::post)
             .app_data(client_base_url.clone())
             .app_data(base_url.clone())
             .app_data(cloudinary_client.clone())
@@ -166,9 +209,12 @@ async fn run(
             .app_data(s3_client.clone())
             .app_data(Data::new(HmacSecret(hmac_secret.clone())))
             .app_data(Data::new(CaptchaSecret(captcha_secret.clone())))
+            .app_data(Data::new(EmailWebhookToken(email_webhook_token.clone())))
             .app_data(web::JsonConfig::default().limit(1024 * 1024 * 50))
+            .app_data(web::PayloadConfig::new(1024 * 1024 * 10))
     })
     .listen(listener)?
+    .disable_signals()
     .run();
     Ok(server)
 }
@@ -182,3 +228,6 @@ pub struct HmacSecret(pub SecretString);
 
 #[derive(Clone)]
 pub struct CaptchaSecret(pub SecretString);
+
+#[derive(Clone)]
+pub struct EmailWebhookToken(pub SecretString);
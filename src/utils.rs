@@ -30,6 +30,15 @@ where
     ServerError::NotFoundError(e).into()
 }
 
+// Return a 429 with the user-representation of the error as body.
+// The error root cause is preserved for logging purposes.
+pub fn e429<T>(e: T) -> actix_web::Error
+where
+    T: std::fmt::Debug + std::fmt::Display + 'static,
+{
+    ServerError::TooManyRequestsError(e).into()
+}
+
 pub fn see_other(location: &str) -> HttpResponse {
     HttpResponse::SeeOther()
         .insert_header((LOCATION, location))
@@ -44,6 +53,8 @@ pub enum ServerError<T: std::fmt::Debug + std::fmt::Display + 'static> {
     BadRequestError(T),
     #[error("{0}")]
     NotFoundError(T),
+    #[error("{0}")]
+    TooManyRequestsError(T),
 }
 
 impl<T: std::fmt::Debug + std::fmt::Display + 'static> std::fmt::Debug for ServerError<T> {
@@ -58,6 +69,7 @@ impl<T: std::fmt::Debug + std::fmt::Display + 'static> ResponseError for ServerE
             ServerError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             ServerError::BadRequestError(_) => StatusCode::BAD_REQUEST,
             ServerError::NotFoundError(_) => StatusCode::NOT_FOUND,
+            ServerError::TooManyRequestsError(_) => StatusCode::TOO_MANY_REQUESTS,
         }
     }
 
@@ -108,6 +120,41 @@ impl From<&str> for ResponseMessage {
     }
 }
 
+const DEFAULT_PER_PAGE: i64 = 25;
+const MAX_PER_PAGE: i64 = 100;
+
+/// Validated `page`/`per_page` query parameters of a paginated listing.
+pub struct Pagination {
+    pub page: i64,
+    pub per_page: i64,
+}
+
+impl Pagination {
+    pub fn parse(page: Option<i64>, per_page: Option<i64>) -> Result<Self, String> {
+        let page = page.unwrap_or(1);
+        let per_page = per_page.unwrap_or(DEFAULT_PER_PAGE);
+
+        if page < 1 {
+            return Err(String::from("Page must be greater than zero."));
+        }
+
+        if !(1..=MAX_PER_PAGE).contains(&per_page) {
+            return Err(format!("Per page must be between 1 and {MAX_PER_PAGE}."));
+        }
+
+        if (page - 1).checked_mul(per_page).is_none() {
+            return Err(String::from("Page is too large."));
+        }
+
+        Ok(Self { page, per_page })
+    }
+
+    /// Rows to skip before the page. `parse` makes sure it fits in an `i64`.
+    pub fn offset(&self) -> i64 {
+        (self.page - 1) * self.per_page
+    }
+}
+
 pub fn error_chain_fmt(
     e: &impl std::error::Error,
     f: &mut std::fmt::Formatter<'_>,
@@ -1,2 +1,4 @@
 mod newsletters;
+mod outbox;
+mod subscribers;
 mod user;
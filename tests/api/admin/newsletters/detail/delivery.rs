@@ -0,0 +1,387 @@
+use crate::helpers::spawn_app;
+use newsletter_api::issue_delivery_worker::try_execute_task;
+use newsletter_api::models::IssueDeliveryReportAPI;
+use std::time::Duration;
+use uuid::Uuid;
+use wiremock::matchers::{any, body_string_contains, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn an_issue_is_delivered_to_all_its_subscribers_in_a_single_batch() {
+    // Arrange
+    let app = spawn_app().await;
+    for _ in 0..3 {
+        app.create_confirmed_subscriber(None, None).await;
+    }
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    try_execute_task(&app.db_pool, &app.email_client, &app.delivery_context)
+        .await
+        .unwrap();
+
+    // Assert
+    let response = app
+        .get_admin_newsletter_delivery(&newsletter_issue_id, &[])
+        .await;
+    let report: IssueDeliveryReportAPI = response.json().await.unwrap();
+    assert_eq!(0, report.queued);
+    assert_eq!(3, report.sent);
+}
+
+#[tokio::test]
+async fn delivery_progress_reports_queued_and_sent_emails() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, None).await;
+    app.create_confirmed_subscriber(None, None).await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Before the worker runs
+    let response = app
+        .get_admin_newsletter_delivery(&newsletter_issue_id, &[])
+        .await;
+    assert_eq!(200, response.status().as_u16());
+    let report: IssueDeliveryReportAPI = response.json().await.unwrap();
+    assert_eq!(2, report.queued);
+    assert_eq!(0, report.sent);
+    assert!(report.first_sent_at.is_none());
+
+    // Act - Part 2 - After the worker runs
+    app.dispatch_all_pending_emails().await;
+    let response = app
+        .get_admin_newsletter_delivery(&newsletter_issue_id, &[])
+        .await;
+    let report: IssueDeliveryReportAPI = response.json().await.unwrap();
+    assert_eq!(0, report.queued);
+    assert_eq!(2, report.sent);
+    assert_eq!(0, report.failed);
+    assert!(report.first_sent_at.unwrap() <= report.last_sent_at.unwrap());
+}
+
+#[tokio::test]
+async fn delivery_progress_lists_failed_and_suppressed_recipients() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, Some("first@example.com".into()))
+        .await;
+    app.create_confirmed_subscriber(None, Some("second@example.com".into()))
+        .await;
+    app.create_confirmed_subscriber(None, Some("delivered@example.com".into()))
+        .await;
+    app.create_confirmed_subscriber(None, Some("bounced@example.com".into()))
+        .await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+    sqlx::query!(
+        "INSERT INTO email_suppressions (email, reason) VALUES ('bounced@example.com', 'bounced')"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    for email in ["first@example.com", "second@example.com"] {
+        Mock::given(path("/api/v1/send"))
+            .and(method("POST"))
+            .and(body_string_contains(email))
+            .respond_with(ResponseTemplate::new(422))
+            .mount(&app.email_server)
+            .await;
+    }
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+    let response = app
+        .get_admin_newsletter_delivery(&newsletter_issue_id, &[("per_page", "1")])
+        .await;
+
+    // Assert
+    let report: IssueDeliveryReportAPI = response.json().await.unwrap();
+    assert_eq!(1, report.sent);
+    assert_eq!(2, report.failed);
+    assert_eq!(1, report.suppressed);
+    assert_eq!(1, report.failures.len());
+    assert!(report.failures[0].error.as_ref().unwrap().contains("422"));
+
+    let response = app
+        .get_admin_newsletter_delivery(&newsletter_issue_id, &[("page", "2"), ("per_page", "1")])
+        .await;
+    let second_page: IssueDeliveryReportAPI = response.json().await.unwrap();
+    assert_eq!(1, second_page.failures.len());
+    assert_ne!(
+        report.failures[0].subscriber_email,
+        second_page.failures[0].subscriber_email
+    );
+}
+
+#[tokio::test]
+async fn delivery_progress_of_an_unknown_issue_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .get_admin_newsletter_delivery(&Uuid::new_v4(), &[])
+        .await;
+
+    // Assert
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn delivery_progress_rejects_invalid_pagination() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    let too_large_page = i64::MAX.to_string();
+
+    for page in ["0", too_large_page.as_str()] {
+        // Act
+        let response = app
+            .get_admin_newsletter_delivery(&newsletter_issue_id, &[("page", page)])
+            .await;
+
+        // Assert
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request for page {}.",
+            page
+        );
+    }
+}
+
+#[tokio::test]
+async fn unauthenticated_users_cannot_see_delivery_progress() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .get_admin_newsletter_delivery(&Uuid::new_v4(), &[])
+        .await;
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn paused_deliveries_are_only_sent_once_resumed() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, None).await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Pause
+    let response = app
+        .post_admin_pause_newsletter_delivery(&newsletter_issue_id)
+        .await;
+    assert_eq!(200, response.status().as_u16());
+    let report: IssueDeliveryReportAPI = response.json().await.unwrap();
+    assert!(report.paused_at.is_some());
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 1
+    let response = app
+        .get_admin_newsletter_delivery(&newsletter_issue_id, &[])
+        .await;
+    let report: IssueDeliveryReportAPI = response.json().await.unwrap();
+    assert_eq!(1, report.queued);
+    assert_eq!(0, report.sent);
+
+    // Act - Part 2 - Resume
+    let response = app
+        .post_admin_resume_newsletter_delivery(&newsletter_issue_id)
+        .await;
+    assert_eq!(200, response.status().as_u16());
+    let report: IssueDeliveryReportAPI = response.json().await.unwrap();
+    assert!(report.paused_at.is_none());
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 2
+    let response = app
+        .get_admin_newsletter_delivery(&newsletter_issue_id, &[])
+        .await;
+    let report: IssueDeliveryReportAPI = response.json().await.unwrap();
+    assert_eq!(0, report.queued);
+    assert_eq!(1, report.sent);
+}
+
+#[tokio::test]
+async fn cancelling_drops_the_remaining_deliveries_and_records_them() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, None).await;
+    app.create_confirmed_subscriber(None, None).await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+    app.post_admin_pause_newsletter_delivery(&newsletter_issue_id)
+        .await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_admin_cancel_newsletter_delivery(&newsletter_issue_id)
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let report: IssueDeliveryReportAPI = response.json().await.unwrap();
+    assert_eq!(0, report.queued);
+    assert_eq!(2, report.cancelled);
+    assert_eq!(0, report.sent);
+    assert!(report.paused_at.is_none());
+    let cancelled =
+        sqlx::query!("SELECT subscriber_email FROM issue_delivery_log WHERE outcome = 'cancelled'")
+            .fetch_all(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(2, cancelled.len());
+}
+
+#[tokio::test]
+async fn cancelling_leaves_claimed_deliveries_to_the_worker() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, None).await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(1_000)))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let db_pool = app.db_pool.clone();
+    let email_client = app.email_client.clone();
+    let delivery_context = app.delivery_context.clone();
+    let in_flight = tokio::spawn(async move {
+        try_execute_task(&db_pool, &email_client, &delivery_context)
+            .await
+            .unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    // Act
+    let response = app
+        .post_admin_cancel_newsletter_delivery(&newsletter_issue_id)
+        .await;
+    in_flight.await.unwrap();
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let response = app
+        .get_admin_newsletter_delivery(&newsletter_issue_id, &[])
+        .await;
+    let report: IssueDeliveryReportAPI = response.json().await.unwrap();
+    assert_eq!(0, report.queued);
+    assert_eq!(0, report.cancelled);
+    assert_eq!(1, report.sent);
+}
+
+#[tokio::test]
+async fn delivery_of_unpublished_issues_cannot_be_paused_or_cancelled() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+
+    // Act
+    let paused = app
+        .post_admin_pause_newsletter_delivery(&newsletter_issue_id)
+        .await;
+    let resumed = app
+        .post_admin_resume_newsletter_delivery(&newsletter_issue_id)
+        .await;
+    let cancelled = app
+        .post_admin_cancel_newsletter_delivery(&newsletter_issue_id)
+        .await;
+
+    // Assert
+    assert_eq!(400, paused.status().as_u16());
+    assert_eq!(400, resumed.status().as_u16());
+    assert_eq!(400, cancelled.status().as_u16());
+}
+
+#[tokio::test]
+async fn delivery_of_another_users_issue_cannot_be_paused_or_cancelled() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = Uuid::new_v4();
+
+    // Act
+    let paused = app
+        .post_admin_pause_newsletter_delivery(&newsletter_issue_id)
+        .await;
+    let cancelled = app
+        .post_admin_cancel_newsletter_delivery(&newsletter_issue_id)
+        .await;
+
+    // Assert
+    assert_eq!(404, paused.status().as_u16());
+    assert_eq!(404, cancelled.status().as_u16());
+}
+
+#[tokio::test]
+async fn unauthenticated_users_cannot_pause_resume_or_cancel_deliveries() {
+    // Arrange
+    let app = spawn_app().await;
+    let newsletter_issue_id = Uuid::new_v4();
+
+    // Act
+    let paused = app
+        .post_admin_pause_newsletter_delivery(&newsletter_issue_id)
+        .await;
+    let resumed = app
+        .post_admin_resume_newsletter_delivery(&newsletter_issue_id)
+        .await;
+    let cancelled = app
+        .post_admin_cancel_newsletter_delivery(&newsletter_issue_id)
+        .await;
+
+    // Assert
+    assert_eq!(401, paused.status().as_u16());
+    assert_eq!(401, resumed.status().as_u16());
+    assert_eq!(401, cancelled.status().as_u16());
+}
@@ -0,0 +1,91 @@
+use crate::helpers::spawn_app;
+use newsletter_api::models::{EmailPreviewAPI, NewsletterIssueAPI};
+use uuid::Uuid;
+use wiremock::matchers::any;
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn the_preview_shows_the_rendered_email_bodies() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    let response = app.get_admin_newsletter_issue(&newsletter_issue_id).await;
+    let newsletter_issue: NewsletterIssueAPI = response.json().await.unwrap();
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .get_admin_newsletter_email_preview(&newsletter_issue_id)
+        .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let preview: EmailPreviewAPI = response.json().await.unwrap();
+    assert_eq!(newsletter_issue.title, preview.subject);
+    assert!(
+        preview
+            .html_body
+            .contains("<h2>Newsletter body as markdown</h2>")
+    );
+    assert!(preview.html_body.contains("Unsubscribe"));
+    assert!(preview.text_body.contains("Newsletter body as markdown"));
+    assert!(!preview.text_body.contains("<h2>"));
+    assert!(preview.text_body.contains("Unsubscribe: "));
+    assert!(
+        preview.size > preview.subject.len() + preview.html_body.len() + preview.text_body.len()
+    );
+}
+
+#[tokio::test]
+async fn the_preview_is_tracked_like_a_delivery_when_tracking_is_enabled() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.put_admin_update_user_tracking(&serde_json::json!({ "enabled": true }))
+        .await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+
+    // Act
+    let response = app
+        .get_admin_newsletter_email_preview(&newsletter_issue_id)
+        .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let preview: EmailPreviewAPI = response.json().await.unwrap();
+    assert!(preview.html_body.contains("/tracking/open/preview"));
+}
+
+#[tokio::test]
+async fn previewing_another_users_issue_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .get_admin_newsletter_email_preview(&Uuid::new_v4())
+        .await;
+
+    // Assert
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn unauthenticated_users_cannot_preview_emails() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .get_admin_newsletter_email_preview(&Uuid::new_v4())
+        .await;
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+}
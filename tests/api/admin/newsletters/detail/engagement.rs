@@ -0,0 +1,44 @@
+use crate::helpers::spawn_app;
+use newsletter_api::models::IssueEngagementAPI;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn engagement_of_an_issue_without_events_is_empty() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+
+    // Act
+    let response = app
+        .get_admin_newsletter_engagement(&newsletter_issue_id)
+        .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let engagement: IssueEngagementAPI = response.json().await.unwrap();
+    assert_eq!(0, engagement.sent);
+    assert_eq!(0, engagement.unique_opens);
+    assert_eq!(0.0, engagement.open_rate);
+    assert_eq!(0.0, engagement.click_rate);
+    assert!(engagement.links.is_empty());
+}
+
+#[tokio::test]
+async fn engagement_of_an_unknown_issue_returns_404() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app.get_admin_newsletter_engagement(&Uuid::new_v4()).await;
+
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn unauthenticated_users_cannot_see_engagement() {
+    let app = spawn_app().await;
+
+    let response = app.get_admin_newsletter_engagement(&Uuid::new_v4()).await;
+
+    assert_eq!(401, response.status().as_u16());
+}
@@ -0,0 +1,296 @@
+use crate::helpers::{TestApp, spawn_app};
+use newsletter_api::models::{
+    IssueDeliveryFailureAPI, IssueDeliveryReportAPI, IssueDeliveryRequeueAPI,
+};
+use uuid::Uuid;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+/// Publish an issue to a single confirmed subscriber, leaving it queued.
+async fn publish_to_one_subscriber(app: &TestApp) -> Uuid {
+    app.create_confirmed_subscriber(None, None).await;
+    app.test_user.login(app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+    newsletter_issue_id
+}
+
+async fn mount_email_response(app: &TestApp, status: u16) {
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(status))
+        .mount(&app.email_server)
+        .await;
+}
+
+#[tokio::test]
+async fn transient_failures_are_rescheduled_with_backoff() {
+    // Arrange
+    let app = spawn_app().await;
+    publish_to_one_subscriber(&app).await;
+    mount_email_response(&app, 503).await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let task = sqlx::query!(
+        "SELECT n_retries, execute_after > now() AS \"is_deferred!\" FROM issue_delivery_queue"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("The task should still be queued.");
+    assert_eq!(1, task.n_retries);
+    assert!(task.is_deferred);
+    let failures = sqlx::query!("SELECT subscriber_email FROM issue_delivery_failures")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(failures.is_empty());
+}
+
+#[tokio::test]
+async fn permanent_failures_are_moved_to_the_failures_table() {
+    // Arrange
+    let app = spawn_app().await;
+    let newsletter_issue_id = publish_to_one_subscriber(&app).await;
+    mount_email_response(&app, 422).await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let response = app
+        .get_admin_newsletter_failures(&newsletter_issue_id)
+        .await;
+    assert_eq!(200, response.status().as_u16());
+    let failures: Vec<IssueDeliveryFailureAPI> = response.json().await.unwrap();
+    assert_eq!(1, failures.len());
+    assert_eq!(0, failures[0].n_retries);
+    assert!(failures[0].error.contains("422"));
+    let queued = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(0, queued);
+}
+
+#[tokio::test]
+async fn tasks_that_exhaust_their_retries_are_moved_to_the_failures_table() {
+    // Arrange
+    let app = spawn_app().await;
+    let newsletter_issue_id = publish_to_one_subscriber(&app).await;
+    mount_email_response(&app, 500).await;
+    sqlx::query!("UPDATE issue_delivery_queue SET n_retries = 5")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let response = app
+        .get_admin_newsletter_failures(&newsletter_issue_id)
+        .await;
+    let failures: Vec<IssueDeliveryFailureAPI> = response.json().await.unwrap();
+    assert_eq!(1, failures.len());
+    assert_eq!(5, failures[0].n_retries);
+}
+
+#[tokio::test]
+async fn rate_limited_deliveries_are_deferred_without_using_up_their_retries() {
+    // Arrange
+    let app = spawn_app().await;
+    publish_to_one_subscriber(&app).await;
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "120"))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    sqlx::query!("UPDATE issue_delivery_queue SET n_retries = 5")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let task = sqlx::query!(
+        r#"
+        SELECT
+            n_retries,
+            execute_after > now() + interval '100 seconds' AS "honours_retry_after!",
+            locked_by
+        FROM issue_delivery_queue
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("The task should still be queued.");
+    assert_eq!(5, task.n_retries);
+    assert!(task.honours_retry_after);
+    assert!(task.locked_by.is_none());
+    let failures = sqlx::query!("SELECT subscriber_email FROM issue_delivery_failures")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(failures.is_empty());
+}
+
+#[tokio::test]
+async fn failures_within_a_batch_only_affect_their_own_recipient() {
+    // Arrange
+    let app = spawn_app().await;
+    let failing_email = String::from("failing@example.com");
+    app.create_confirmed_subscriber(None, Some(failing_email.clone()))
+        .await;
+    let newsletter_issue_id = publish_to_one_subscriber(&app).await;
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .and(body_partial_json(serde_json::json!({
+          "To": [{ "Email": failing_email }]
+        })))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let queued = sqlx::query!("SELECT subscriber_email, n_retries FROM issue_delivery_queue")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(1, queued.len());
+    assert_eq!(failing_email, queued[0].subscriber_email);
+    assert_eq!(1, queued[0].n_retries);
+    let response = app
+        .get_admin_newsletter_failures(&newsletter_issue_id)
+        .await;
+    let failures: Vec<IssueDeliveryFailureAPI> = response.json().await.unwrap();
+    assert!(failures.is_empty());
+}
+
+#[tokio::test]
+async fn failed_deliveries_can_be_requeued_and_delivered() {
+    // Arrange
+    let app = spawn_app().await;
+    let newsletter_issue_id = publish_to_one_subscriber(&app).await;
+    let guard = Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(400))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+    drop(guard);
+
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_admin_requeue_newsletter_failures(&newsletter_issue_id)
+        .await;
+    assert_eq!(200, response.status().as_u16());
+    let response_body: IssueDeliveryRequeueAPI = response.json().await.unwrap();
+    assert_eq!(1, response_body.requeued);
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let response = app
+        .get_admin_newsletter_failures(&newsletter_issue_id)
+        .await;
+    let failures: Vec<IssueDeliveryFailureAPI> = response.json().await.unwrap();
+    assert!(failures.is_empty());
+    // Mock verifies on Drop that the requeued email was sent
+}
+
+#[tokio::test]
+async fn requeued_deliveries_are_reported_by_their_latest_outcome() {
+    // Arrange
+    let app = spawn_app().await;
+    let newsletter_issue_id = publish_to_one_subscriber(&app).await;
+    let guard = Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(400))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+    drop(guard);
+    mount_email_response(&app, 200).await;
+
+    // Act - Part 1 - Requeue the failed delivery
+    app.post_admin_requeue_newsletter_failures(&newsletter_issue_id)
+        .await;
+    let response = app
+        .get_admin_newsletter_delivery(&newsletter_issue_id, &[])
+        .await;
+    let report: IssueDeliveryReportAPI = response.json().await.unwrap();
+    assert_eq!(1, report.queued);
+    assert_eq!(0, report.failed);
+    assert!(report.failures.is_empty());
+
+    // Act - Part 2 - Deliver it
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let response = app
+        .get_admin_newsletter_delivery(&newsletter_issue_id, &[])
+        .await;
+    let report: IssueDeliveryReportAPI = response.json().await.unwrap();
+    assert_eq!(0, report.queued);
+    assert_eq!(1, report.sent);
+    assert_eq!(0, report.failed);
+    assert!(report.failures.is_empty());
+}
+
+#[tokio::test]
+async fn failures_of_unknown_issues_return_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let list = app.get_admin_newsletter_failures(&Uuid::new_v4()).await;
+    let requeue = app
+        .post_admin_requeue_newsletter_failures(&Uuid::new_v4())
+        .await;
+
+    // Assert
+    assert_eq!(404, list.status().as_u16());
+    assert_eq!(404, requeue.status().as_u16());
+}
+
+#[tokio::test]
+async fn unauthenticated_users_cannot_see_or_requeue_failures() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let list = app.get_admin_newsletter_failures(&Uuid::new_v4()).await;
+    let requeue = app
+        .post_admin_requeue_newsletter_failures(&Uuid::new_v4())
+        .await;
+
+    // Assert
+    assert_eq!(401, list.status().as_u16());
+    assert_eq!(401, requeue.status().as_u16());
+}
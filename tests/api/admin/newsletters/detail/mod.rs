@@ -1,3 +1,9 @@
 mod cover_image;
+mod delivery;
+mod email_preview;
+mod engagement;
+mod failures;
 mod index;
 mod publish;
+mod schedule;
+mod test_send;
@@ -379,3 +379,124 @@ async fn publish_newsletters_returns_422_for_empty_content() {
     let response_body: ResponseErrorMessage = response.json().await.unwrap();
     assert_eq!("Content body is required.".to_string(), response_body.error);
 }
+
+#[tokio::test]
+async fn publish_newsletters_returns_400_for_unknown_merge_tags() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.post_admin_create_newsletter(&serde_json::json!({
+      "title": "Newsletter title",
+      "description": "Newsletter description",
+      "content": "Hi {{ subscriber.age }}",
+      "cover_image": "",
+    }))
+    .await;
+
+    let response = app.get_admin_unpublished_newsletter_issues().await;
+    let response_body: Vec<NewsletterIssueAPI> = response.json().await.unwrap();
+    let newsletter_issue_id = response_body[0].newsletter_issue_id;
+
+    let response = app
+        .put_admin_publish_newsletter(
+            &newsletter_issue_id,
+            &serde_json::json!({
+              "idempotency_key": uuid::Uuid::new_v4().to_string()
+            }),
+        )
+        .await;
+    assert_eq!(400, response.status().as_u16());
+
+    let response_body: ResponseErrorMessage = response.json().await.unwrap();
+    assert_eq!(
+        "{{ subscriber.age }} is not a known merge tag.".to_string(),
+        response_body.error
+    );
+}
+
+#[tokio::test]
+async fn merge_tags_are_resolved_for_each_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let email: String = SafeEmail().fake();
+    app.create_confirmed_subscriber(None, Some(email.clone()))
+        .await;
+    let name = sqlx::query_scalar!("SELECT name FROM subscriptions WHERE email = $1", email)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    app.test_user.login(&app).await;
+    app.post_admin_create_newsletter(&serde_json::json!({
+      "title": "Hello {{ subscriber.name }}",
+      "description": "Newsletter description",
+      "content": "Sent to {{subscriber.email}}. [Unsubscribe]({{ unsubscribe_url }})",
+      "cover_image": "",
+    }))
+    .await;
+    let response = app.get_admin_unpublished_newsletter_issues().await;
+    let response_body: Vec<NewsletterIssueAPI> = response.json().await.unwrap();
+    let newsletter_issue_id = response_body[0].newsletter_issue_id;
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value =
+        serde_json::from_slice(&email_requests.last().unwrap().body).unwrap();
+    assert_eq!(body["Subject"], format!("Hello {name}"));
+    let html = body["Html"].as_str().unwrap();
+    assert!(html.contains(&format!("Sent to {email}.")));
+    assert!(html.contains(&format!(
+        r#"<a href="{}/subscriptions/unsubscribe?unsubscribe_token="#,
+        app.delivery_context.client_base_url
+    )));
+    assert!(!html.contains("{{"));
+    assert!(body["Text"].as_str().unwrap().contains(&email));
+}
+
+#[tokio::test]
+async fn merge_tags_fall_back_to_their_default() {
+    // Arrange
+    let app = spawn_app().await;
+    let email: String = SafeEmail().fake();
+    app.create_confirmed_subscriber(None, Some(email.clone()))
+        .await;
+    sqlx::query!("UPDATE subscriptions SET name = '' WHERE email = $1", email)
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.test_user.login(&app).await;
+    app.post_admin_create_newsletter(&serde_json::json!({
+      "title": "Newsletter title",
+      "description": "Newsletter description",
+      "content": r#"Hi {{ subscriber.name | default: "reader" }}!"#,
+      "cover_image": "",
+    }))
+    .await;
+    let response = app.get_admin_unpublished_newsletter_issues().await;
+    let response_body: Vec<NewsletterIssueAPI> = response.json().await.unwrap();
+    let newsletter_issue_id = response_body[0].newsletter_issue_id;
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value =
+        serde_json::from_slice(&email_requests.last().unwrap().body).unwrap();
+    assert!(body["Html"].as_str().unwrap().contains("Hi reader!"));
+}
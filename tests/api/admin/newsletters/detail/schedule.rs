@@ -0,0 +1,282 @@
+use crate::helpers::{TestApp, spawn_app};
+use chrono::{Duration, Utc};
+use newsletter_api::issue_scheduler::try_publish_scheduled_issue;
+use newsletter_api::models::NewsletterIssueAPI;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn schedule_in_an_hour(app: &TestApp, newsletter_issue_id: &Uuid) -> NewsletterIssueAPI {
+    let response = app
+        .put_admin_schedule_newsletter(
+            newsletter_issue_id,
+            &serde_json::json!({
+              "scheduled_for": Utc::now() + Duration::hours(1),
+            }),
+        )
+        .await;
+    assert_eq!(200, response.status().as_u16());
+    response.json().await.unwrap()
+}
+
+/// Move the schedule into the past so that the issue is due.
+async fn make_due(app: &TestApp, newsletter_issue_id: &Uuid) {
+    sqlx::query!(
+        "UPDATE newsletter_issues SET scheduled_for = now() - interval '1 minute'
+        WHERE newsletter_issue_id = $1",
+        newsletter_issue_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn unauthenticated_users_cannot_schedule_newsletter_issues() {
+    let app = spawn_app().await;
+
+    let response = app
+        .put_admin_schedule_newsletter(
+            &Uuid::new_v4(),
+            &serde_json::json!({
+              "scheduled_for": Utc::now() + Duration::hours(1),
+            }),
+        )
+        .await;
+    assert_eq!(401, response.status().as_u16());
+
+    let response = app.delete_admin_schedule_newsletter(&Uuid::new_v4()).await;
+    assert_eq!(401, response.status().as_u16());
+
+    let response = app.get_admin_scheduled_newsletter_issues().await;
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn scheduled_issues_are_listed_as_scheduled_and_not_as_drafts() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+
+    let newsletter_issue = schedule_in_an_hour(&app, &newsletter_issue_id).await;
+    assert!(newsletter_issue.scheduled_for.is_some());
+    assert!(newsletter_issue.published_at.is_none());
+
+    let response = app.get_admin_scheduled_newsletter_issues().await;
+    assert_eq!(200, response.status().as_u16());
+    let scheduled: Vec<NewsletterIssueAPI> = response.json().await.unwrap();
+    assert_eq!(1, scheduled.len());
+    assert_eq!(newsletter_issue_id, scheduled[0].newsletter_issue_id);
+
+    let response = app.get_admin_unpublished_newsletter_issues().await;
+    let drafts: Vec<NewsletterIssueAPI> = response.json().await.unwrap();
+    assert!(drafts.is_empty());
+}
+
+#[tokio::test]
+async fn scheduling_in_the_past_is_rejected() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+
+    let response = app
+        .put_admin_schedule_newsletter(
+            &newsletter_issue_id,
+            &serde_json::json!({
+              "scheduled_for": Utc::now() - Duration::minutes(1),
+            }),
+        )
+        .await;
+
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn scheduling_an_invalid_issue_is_rejected() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.post_admin_create_newsletter(&serde_json::json!({
+      "title": "Newsletter title",
+      "description": "",
+      "content": "",
+      "cover_image": "",
+    }))
+    .await;
+    let response = app.get_admin_unpublished_newsletter_issues().await;
+    let drafts: Vec<NewsletterIssueAPI> = response.json().await.unwrap();
+
+    let response = app
+        .put_admin_schedule_newsletter(
+            &drafts[0].newsletter_issue_id,
+            &serde_json::json!({
+              "scheduled_for": Utc::now() + Duration::hours(1),
+            }),
+        )
+        .await;
+
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn scheduling_a_published_issue_is_rejected() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+
+    let response = app
+        .put_admin_schedule_newsletter(
+            &newsletter_issue_id,
+            &serde_json::json!({
+              "scheduled_for": Utc::now() + Duration::hours(1),
+            }),
+        )
+        .await;
+
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn scheduling_another_users_issue_returns_404() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app
+        .put_admin_schedule_newsletter(
+            &Uuid::new_v4(),
+            &serde_json::json!({
+              "scheduled_for": Utc::now() + Duration::hours(1),
+            }),
+        )
+        .await;
+
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn cancelling_a_schedule_moves_the_issue_back_to_drafts() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    schedule_in_an_hour(&app, &newsletter_issue_id).await;
+
+    let response = app
+        .delete_admin_schedule_newsletter(&newsletter_issue_id)
+        .await;
+    assert_eq!(200, response.status().as_u16());
+    let newsletter_issue: NewsletterIssueAPI = response.json().await.unwrap();
+    assert!(newsletter_issue.scheduled_for.is_none());
+
+    let response = app.get_admin_scheduled_newsletter_issues().await;
+    let scheduled: Vec<NewsletterIssueAPI> = response.json().await.unwrap();
+    assert!(scheduled.is_empty());
+    let response = app.get_admin_unpublished_newsletter_issues().await;
+    let drafts: Vec<NewsletterIssueAPI> = response.json().await.unwrap();
+    assert_eq!(newsletter_issue_id, drafts[0].newsletter_issue_id);
+}
+
+#[tokio::test]
+async fn issues_are_not_published_before_they_are_due() {
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, None).await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    schedule_in_an_hour(&app, &newsletter_issue_id).await;
+
+    app.publish_due_scheduled_issues().await;
+
+    let response = app.get_admin_newsletter_issue(&newsletter_issue_id).await;
+    let newsletter_issue: NewsletterIssueAPI = response.json().await.unwrap();
+    assert!(newsletter_issue.published_at.is_none());
+    let queued = sqlx::query!("SELECT subscriber_email FROM issue_delivery_queue")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(queued.is_empty());
+}
+
+#[tokio::test]
+async fn due_issues_are_published_and_delivered() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, None).await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    schedule_in_an_hour(&app, &newsletter_issue_id).await;
+    make_due(&app, &newsletter_issue_id).await;
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.publish_due_scheduled_issues().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let response = app.get_admin_newsletter_issue(&newsletter_issue_id).await;
+    let newsletter_issue: NewsletterIssueAPI = response.json().await.unwrap();
+    assert!(newsletter_issue.published_at.is_some());
+    assert!(newsletter_issue.scheduled_for.is_none());
+}
+
+#[tokio::test]
+async fn concurrent_schedulers_enqueue_a_due_issue_once() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, None).await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    schedule_in_an_hour(&app, &newsletter_issue_id).await;
+    make_due(&app, &newsletter_issue_id).await;
+
+    // Act
+    let (first, second) = tokio::join!(
+        try_publish_scheduled_issue(&app.db_pool),
+        try_publish_scheduled_issue(&app.db_pool)
+    );
+    first.unwrap();
+    second.unwrap();
+    app.publish_due_scheduled_issues().await;
+
+    // Assert
+    let queued = sqlx::query!("SELECT subscriber_email FROM issue_delivery_queue")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(1, queued.len());
+}
+
+#[tokio::test]
+async fn due_issues_that_became_invalid_are_unscheduled() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, None).await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    schedule_in_an_hour(&app, &newsletter_issue_id).await;
+    make_due(&app, &newsletter_issue_id).await;
+    sqlx::query!(
+        "UPDATE newsletter_issues SET content = '' WHERE newsletter_issue_id = $1",
+        newsletter_issue_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    app.publish_due_scheduled_issues().await;
+
+    // Assert
+    let response = app.get_admin_newsletter_issue(&newsletter_issue_id).await;
+    let newsletter_issue: NewsletterIssueAPI = response.json().await.unwrap();
+    assert!(newsletter_issue.published_at.is_none());
+    assert!(newsletter_issue.scheduled_for.is_none());
+    let queued = sqlx::query!("SELECT subscriber_email FROM issue_delivery_queue")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(queued.is_empty());
+}
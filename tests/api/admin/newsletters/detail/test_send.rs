@@ -0,0 +1,314 @@
+use crate::helpers::{TestApp, spawn_app};
+use futures_util::future::join_all;
+use newsletter_api::models::NewsletterIssueAPI;
+use uuid::Uuid;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn author_email(app: &TestApp) -> String {
+    sqlx::query_scalar!(
+        "SELECT email FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn a_test_email_of_a_draft_is_sent_to_its_author() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, None).await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .and(body_partial_json(serde_json::json!({
+            "To": [{ "Email": author_email(&app).await }],
+        })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_admin_test_send_newsletter(&newsletter_issue_id, &serde_json::json!({}))
+        .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let email_request = email_requests.last().unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert!(body["Subject"].as_str().unwrap().starts_with("[Test] "));
+    let response = app.get_admin_newsletter_issue(&newsletter_issue_id).await;
+    let newsletter_issue: NewsletterIssueAPI = response.json().await.unwrap();
+    assert!(newsletter_issue.published_at.is_none());
+    let queued = sqlx::query!("SELECT subscriber_email FROM issue_delivery_queue")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(queued.is_empty());
+}
+
+#[tokio::test]
+async fn a_test_email_can_also_be_sent_to_a_few_extra_addresses() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_admin_test_send_newsletter(
+            &newsletter_issue_id,
+            &serde_json::json!({
+              "recipients": ["editor@example.com", "proofreader@example.com"],
+            }),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn suppressed_extra_addresses_are_left_out_of_a_test_send() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    sqlx::query!(
+        "INSERT INTO email_suppressions (email, reason) VALUES ('bounced@example.com', 'bounced')"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    Mock::given(path("/api/v1/send"))
+        .and(body_partial_json(serde_json::json!({
+            "To": [{ "Email": "bounced@example.com" }],
+        })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/api/v1/send"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_admin_test_send_newsletter(
+            &newsletter_issue_id,
+            &serde_json::json!({
+              "recipients": ["editor@example.com", "Bounced@example.com"],
+            }),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn test_send_rejects_invalid_or_too_many_extra_addresses() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    Mock::given(path("/api/v1/send"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    let test_cases = vec![
+        (serde_json::json!(["not-an-email"]), "an invalid address"),
+        (
+            serde_json::json!([
+                "one@example.com",
+                "two@example.com",
+                "three@example.com",
+                "four@example.com",
+            ]),
+            "too many addresses",
+        ),
+    ];
+
+    for (recipients, description) in test_cases {
+        // Act
+        let response = app
+            .post_admin_test_send_newsletter(
+                &newsletter_issue_id,
+                &serde_json::json!({ "recipients": recipients }),
+            )
+            .await;
+
+        // Assert
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request when the payload had {}.",
+            description
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_sends_are_rate_limited_per_user() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_test_sends (user_id, newsletter_issue_id, n_recipients)
+        SELECT $1, $2, 1 FROM generate_series(1, 10)
+        "#,
+        app.test_user.user_id,
+        newsletter_issue_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    Mock::given(path("/api/v1/send"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_admin_test_send_newsletter(&newsletter_issue_id, &serde_json::json!({}))
+        .await;
+
+    // Assert
+    assert_eq!(429, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn concurrent_test_sends_cannot_exceed_the_rate_limit() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    Mock::given(path("/api/v1/send"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(10)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let body = serde_json::json!({});
+    let responses =
+        join_all((0..15).map(|_| app.post_admin_test_send_newsletter(&newsletter_issue_id, &body)))
+            .await;
+
+    // Assert
+    let statuses: Vec<u16> = responses.iter().map(|r| r.status().as_u16()).collect();
+    assert_eq!(10, statuses.iter().filter(|&&s| s == 200).count());
+    assert_eq!(5, statuses.iter().filter(|&&s| s == 429).count());
+}
+
+#[tokio::test]
+async fn failed_test_sends_do_not_count_towards_the_rate_limit() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    Mock::given(path("/api/v1/send"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_admin_test_send_newsletter(&newsletter_issue_id, &serde_json::json!({}))
+        .await;
+
+    // Assert
+    assert_eq!(500, response.status().as_u16());
+    let test_sends = sqlx::query!("SELECT id FROM newsletter_test_sends")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(test_sends.is_empty());
+}
+
+#[tokio::test]
+async fn partially_failed_test_sends_only_count_the_emails_that_went_out() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    Mock::given(path("/api/v1/send"))
+        .and(body_partial_json(serde_json::json!({
+            "To": [{ "Email": "editor@example.com" }],
+        })))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/api/v1/send"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_admin_test_send_newsletter(
+            &newsletter_issue_id,
+            &serde_json::json!({
+              "recipients": ["editor@example.com", "proofreader@example.com"],
+            }),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(500, response.status().as_u16());
+    let test_send = sqlx::query!("SELECT n_recipients FROM newsletter_test_sends")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(1, test_send.n_recipients);
+}
+
+#[tokio::test]
+async fn test_sends_of_another_users_issue_return_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_admin_test_send_newsletter(&Uuid::new_v4(), &serde_json::json!({}))
+        .await;
+
+    // Assert
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn unauthenticated_users_cannot_send_test_emails() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_admin_test_send_newsletter(&Uuid::new_v4(), &serde_json::json!({}))
+        .await;
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+}
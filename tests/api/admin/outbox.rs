@@ -0,0 +1,98 @@
+use crate::helpers::{TestApp, spawn_app, spawn_app_with_outbox};
+use fake::Fake;
+use fake::faker::internet::en::SafeEmail;
+use newsletter_api::email_client::OutboxMessage;
+
+/// Undoes the quoted-printable soft line breaks and escapes the MIME encoder
+/// may have applied to the bodies.
+fn decode_bodies(raw: &str) -> String {
+    raw.replace("=\r\n", "").replace("=3D", "=")
+}
+
+async fn list_outbox(app: &TestApp) -> Vec<OutboxMessage> {
+    let response = app.get_admin_outbox().await;
+    assert_eq!(200, response.status().as_u16());
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn unauthenticated_users_cannot_list_the_outbox() {
+    let app = spawn_app_with_outbox().await;
+
+    let response = app.get_admin_outbox().await;
+
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn the_outbox_is_not_found_when_emails_are_sent_through_an_api() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app.get_admin_outbox().await;
+
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn unknown_outbox_messages_are_not_found() {
+    let app = spawn_app_with_outbox().await;
+    app.test_user.login(&app).await;
+
+    for message_id in ["20261017T101500000000Z-unknown", "..%2Fsecrets"] {
+        let response = app.get_admin_outbox_message(message_id).await;
+
+        assert_eq!(404, response.status().as_u16());
+    }
+}
+
+#[tokio::test]
+async fn subscribing_confirming_and_publishing_runs_through_the_outbox() {
+    // Arrange
+    let app = spawn_app_with_outbox().await;
+    app.test_user.login(&app).await;
+    let (answer, challenge) = app.get_solved_captcha_challenge();
+    let email: String = SafeEmail().fake();
+
+    // Act - Part 1 - Subscribe
+    let response = app
+        .post_subscriptions(&serde_json::json!({
+            "name": "Le Guin",
+            "email": email,
+            "username": app.test_user.username,
+            "signed_answer": challenge,
+            "answer_attempt": answer,
+        }))
+        .await;
+    assert_eq!(200, response.status().as_u16());
+
+    // Assert - Part 1 - The confirmation email is in the outbox
+    let messages = list_outbox(&app).await;
+    assert_eq!(1, messages.len());
+    assert_eq!(email, messages[0].to);
+    let response = app.get_admin_outbox_message(&messages[0].id).await;
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("message/rfc822", response.headers()["Content-Type"]);
+    let raw = decode_bodies(&response.text().await.unwrap());
+    let link = linkify::LinkFinder::new()
+        .links(&raw)
+        .map(|link| link.as_str().to_owned())
+        .find(|link| link.contains("subscription_token="))
+        .expect("The confirmation email has no confirmation link.");
+    let mut confirmation_link = reqwest::Url::parse(&link).unwrap();
+    confirmation_link.set_port(Some(app.port)).unwrap();
+
+    // Act - Part 2 - Confirm, then publish an issue
+    let response = app.api_client.put(confirmation_link).send().await.unwrap();
+    assert_eq!(200, response.status().as_u16());
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 2 - The issue is the newest message
+    let messages = list_outbox(&app).await;
+    assert_eq!(2, messages.len());
+    assert_eq!(email, messages[0].to);
+    assert!(messages[0].subject.starts_with("Newsletter title"));
+    assert!(messages[0].created_at >= messages[1].created_at);
+}
@@ -0,0 +1,29 @@
+use crate::helpers::spawn_app;
+use newsletter_api::models::SubscriptionCountsAPI;
+
+#[tokio::test]
+async fn unauthenticated_users_cannot_count_subscribers() {
+    let app = spawn_app().await;
+
+    let response = app.get_admin_subscriber_counts().await;
+
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn subscribers_are_counted_by_status() {
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, None).await;
+    app.create_confirmed_subscriber(None, None).await;
+    app.create_unconfirmed_subscriber(None, None).await;
+    app.test_user.login(&app).await;
+
+    let response = app.get_admin_subscriber_counts().await;
+    assert_eq!(200, response.status().as_u16());
+
+    let response_body: SubscriptionCountsAPI = response.json().await.unwrap();
+    assert_eq!(2, response_body.confirmed);
+    assert_eq!(1, response_body.pending_confirmation);
+    assert_eq!(0, response_body.unsubscribed);
+    assert_eq!(3, response_body.total);
+}
@@ -0,0 +1,116 @@
+use crate::helpers::spawn_app;
+use fake::Fake;
+use fake::faker::internet::en::SafeEmail;
+use newsletter_api::models::{NewUser, NewUserData, SubscriptionAPI, SubscriptionListAPI};
+use secrecy::SecretString;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn authenticated_users_can_view_a_subscriber() {
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, Some("ursula@example.com".into()))
+        .await;
+    app.test_user.login(&app).await;
+    let response = app.get_admin_subscribers(&[]).await;
+    let subscriber_id = response
+        .json::<SubscriptionListAPI>()
+        .await
+        .unwrap()
+        .subscribers[0]
+        .id;
+
+    let response = app.get_admin_subscriber(&subscriber_id).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let response_body: SubscriptionAPI = response.json().await.unwrap();
+    assert_eq!("ursula@example.com", response_body.email);
+    assert_eq!("confirmed", response_body.status);
+}
+
+#[tokio::test]
+async fn viewing_an_unknown_subscriber_returns_404() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app.get_admin_subscriber(&Uuid::new_v4()).await;
+
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn authenticated_users_can_remove_a_subscriber() {
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, None).await;
+    app.create_unconfirmed_subscriber(None, None).await;
+    app.test_user.login(&app).await;
+    let response = app.get_admin_subscribers(&[]).await;
+    let subscribers = response
+        .json::<SubscriptionListAPI>()
+        .await
+        .unwrap()
+        .subscribers;
+
+    for subscriber in subscribers {
+        let response = app.delete_admin_subscriber(&subscriber.id).await;
+        assert_eq!(200, response.status().as_u16());
+    }
+
+    let response = app.get_admin_subscribers(&[]).await;
+    let response_body: SubscriptionListAPI = response.json().await.unwrap();
+    assert_eq!(0, response_body.total);
+}
+
+#[tokio::test]
+async fn removing_a_subscriber_drops_their_queued_deliveries() {
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, None).await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+    let response = app.get_admin_subscribers(&[]).await;
+    let subscriber_id = response
+        .json::<SubscriptionListAPI>()
+        .await
+        .unwrap()
+        .subscribers[0]
+        .id;
+
+    let response = app.delete_admin_subscriber(&subscriber_id).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let queued = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(0, queued);
+}
+
+#[tokio::test]
+async fn users_cannot_view_or_remove_anothers_subscriber() {
+    let app = spawn_app().await;
+    let second_user: NewUser = NewUserData {
+        username: Uuid::new_v4().to_string(),
+        email: SafeEmail().fake(),
+        password: SecretString::from(Uuid::new_v4().to_string()),
+    }
+    .try_into()
+    .unwrap();
+    let mut transaction = app.db_pool.begin().await.unwrap();
+    let second_user = second_user.store(&mut transaction).await.unwrap();
+    transaction.commit().await.unwrap();
+    app.create_confirmed_subscriber(Some(second_user.username.clone()), None)
+        .await;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+    app.test_user.login(&app).await;
+
+    let response = app.get_admin_subscriber(&subscriber_id).await;
+    assert_eq!(404, response.status().as_u16());
+
+    let response = app.delete_admin_subscriber(&subscriber_id).await;
+    assert_eq!(404, response.status().as_u16());
+}
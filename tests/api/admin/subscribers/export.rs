@@ -0,0 +1,72 @@
+use crate::helpers::spawn_app;
+
+#[tokio::test]
+async fn unauthenticated_users_cannot_export_subscribers() {
+    let app = spawn_app().await;
+
+    let response = app.get_admin_subscribers_export().await;
+
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn export_streams_the_users_subscribers_as_csv() {
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, Some("ursula@example.com".into()))
+        .await;
+    app.create_unconfirmed_subscriber(None, Some("octavia@example.com".into()))
+        .await;
+    app.test_user.login(&app).await;
+
+    let response = app.get_admin_subscribers_export().await;
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(
+        "text/csv; charset=utf-8",
+        response.headers()["content-type"].to_str().unwrap()
+    );
+
+    let body = response.text().await.unwrap();
+    let lines: Vec<&str> = body.lines().collect();
+    assert_eq!(3, lines.len());
+    assert_eq!("email,name,status,subscribed_at", lines[0]);
+    assert!(lines[1].starts_with("octavia@example.com,"));
+    assert!(lines[1].contains(",pending_confirmation,"));
+    assert!(lines[2].starts_with("ursula@example.com,"));
+    assert!(lines[2].contains(",confirmed,"));
+}
+
+#[tokio::test]
+async fn exported_csv_can_be_imported_again() {
+    let app = spawn_app().await;
+    for _ in 0..3 {
+        app.create_confirmed_subscriber(None, None).await;
+    }
+    app.test_user.login(&app).await;
+    let body = app
+        .get_admin_subscribers_export()
+        .await
+        .text()
+        .await
+        .unwrap();
+    sqlx::query!("DELETE FROM subscription_tokens")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    sqlx::query!("DELETE FROM subscriptions")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = app
+        .post_admin_subscribers_import(&body, &[("confirmed", "true")])
+        .await;
+    assert_eq!(200, response.status().as_u16());
+
+    let reexported = app
+        .get_admin_subscribers_export()
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(body, reexported);
+}
@@ -0,0 +1,189 @@
+use crate::helpers::spawn_app;
+use newsletter_api::models::SubscriptionImportReportAPI;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const VALID_CSV: &str = "email,name,subscribed_at
+ursula@example.com,Ursula,2020-01-01
+octavia@example.com,Octavia,2021-06-15T10:30:00Z
+";
+
+#[tokio::test]
+async fn unauthenticated_users_cannot_import_subscribers() {
+    let app = spawn_app().await;
+
+    let response = app.post_admin_subscribers_import(VALID_CSV, &[]).await;
+
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn importing_confirmed_subscribers_does_not_send_emails() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_admin_subscribers_import(VALID_CSV, &[("confirmed", "true")])
+        .await;
+    assert_eq!(200, response.status().as_u16());
+
+    let report: SubscriptionImportReportAPI = response.json().await.unwrap();
+    assert_eq!(2, report.accepted);
+    assert_eq!(0, report.rejected);
+
+    let saved =
+        sqlx::query!("SELECT email, status, subscribed_at FROM subscriptions ORDER BY email")
+            .fetch_all(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(2, saved.len());
+    assert_eq!("octavia@example.com", saved[0].email);
+    assert_eq!("confirmed", saved[0].status);
+    assert_eq!(
+        "2021-06-15T10:30:00+00:00",
+        saved[0].subscribed_at.to_rfc3339()
+    );
+    assert_eq!(
+        "2020-01-01T00:00:00+00:00",
+        saved[1].subscribed_at.to_rfc3339()
+    );
+}
+
+#[tokio::test]
+async fn importing_unconfirmed_subscribers_queues_confirmation_emails() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_admin_subscribers_import(VALID_CSV, &[]).await;
+    assert_eq!(200, response.status().as_u16());
+    // The delivery workers send them, not the request.
+    assert!(
+        app.email_server
+            .received_requests()
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    let response = reqwest::Client::new()
+        .put(confirmation_links.html)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+
+    let statuses = sqlx::query!("SELECT status FROM subscriptions ORDER BY status")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!("confirmed", statuses[0].status);
+    assert_eq!("pending_confirmation", statuses[1].status);
+}
+
+#[tokio::test]
+async fn import_reports_rejected_rows_and_keeps_valid_ones() {
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, Some("existing@example.com".into()))
+        .await;
+    app.test_user.login(&app).await;
+    let csv = "email,name
+valid@example.com,Valid
+not-an-email,Invalid
+existing@example.com,Existing
+valid@example.com,Duplicate
+blank@example.com,
+";
+
+    let response = app
+        .post_admin_subscribers_import(csv, &[("confirmed", "true")])
+        .await;
+    assert_eq!(200, response.status().as_u16());
+
+    let report: SubscriptionImportReportAPI = response.json().await.unwrap();
+    assert_eq!(1, report.accepted);
+    assert_eq!(4, report.rejected);
+
+    let lines: Vec<(u64, bool)> = report
+        .rows
+        .iter()
+        .map(|row| (row.line, row.accepted))
+        .collect();
+    for expected in [(2, true), (3, false), (4, false), (5, false), (6, false)] {
+        assert!(
+            lines.contains(&expected),
+            "Missing row report {:?}",
+            expected
+        );
+    }
+    assert!(
+        report
+            .rows
+            .iter()
+            .filter(|row| !row.accepted)
+            .all(|row| row.error.is_some())
+    );
+
+    let count = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(2, count);
+}
+
+#[tokio::test]
+async fn import_rejects_invalid_subscription_dates() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let csv = "email,name,subscribed_at
+first@example.com,First,yesterday
+second@example.com,Second,2999-01-01
+";
+
+    let response = app
+        .post_admin_subscribers_import(csv, &[("confirmed", "true")])
+        .await;
+
+    let report: SubscriptionImportReportAPI = response.json().await.unwrap();
+    assert_eq!(0, report.accepted);
+    assert_eq!(2, report.rejected);
+}
+
+#[tokio::test]
+async fn import_returns_400_when_required_columns_are_missing() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let test_cases = vec![
+        ("name\nUrsula\n", "missing email column"),
+        ("email\nursula@example.com\n", "missing name column"),
+        ("", "empty body"),
+    ];
+
+    for (csv, error_message) in test_cases {
+        let response = app.post_admin_subscribers_import(csv, &[]).await;
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request when the payload had a {}.",
+            error_message
+        );
+    }
+}
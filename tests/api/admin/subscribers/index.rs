@@ -0,0 +1,130 @@
+use crate::helpers::spawn_app;
+use fake::Fake;
+use fake::faker::internet::en::SafeEmail;
+use newsletter_api::models::{NewUser, NewUserData, SubscriptionListAPI};
+use secrecy::SecretString;
+
+#[tokio::test]
+async fn unauthenticated_users_cannot_list_subscribers() {
+    let app = spawn_app().await;
+
+    let response = app.get_admin_subscribers(&[]).await;
+
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn subscribers_are_scoped_to_the_logged_in_user() {
+    let app = spawn_app().await;
+    let second_user: NewUser = NewUserData {
+        username: uuid::Uuid::new_v4().to_string(),
+        email: SafeEmail().fake(),
+        password: SecretString::from(uuid::Uuid::new_v4().to_string()),
+    }
+    .try_into()
+    .unwrap();
+    let mut transaction = app.db_pool.begin().await.unwrap();
+    let second_user = second_user.store(&mut transaction).await.unwrap();
+    transaction.commit().await.unwrap();
+
+    app.create_confirmed_subscriber(None, Some("mine@example.com".into()))
+        .await;
+    app.create_confirmed_subscriber(
+        Some(second_user.username.clone()),
+        Some("theirs@example.com".into()),
+    )
+    .await;
+    app.test_user.login(&app).await;
+
+    let response = app.get_admin_subscribers(&[]).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let response_body: SubscriptionListAPI = response.json().await.unwrap();
+    assert_eq!(1, response_body.total);
+    assert_eq!(1, response_body.subscribers.len());
+    assert_eq!("mine@example.com", response_body.subscribers[0].email);
+}
+
+#[tokio::test]
+async fn subscribers_can_be_filtered_by_status_and_email() {
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, Some("ursula@example.com".into()))
+        .await;
+    app.create_confirmed_subscriber(None, Some("octavia@example.com".into()))
+        .await;
+    app.create_unconfirmed_subscriber(None, Some("ursula@example.org".into()))
+        .await;
+    app.test_user.login(&app).await;
+
+    let response = app.get_admin_subscribers(&[("status", "confirmed")]).await;
+    let response_body: SubscriptionListAPI = response.json().await.unwrap();
+    assert_eq!(2, response_body.total);
+
+    let response = app.get_admin_subscribers(&[("email", "URSULA")]).await;
+    let response_body: SubscriptionListAPI = response.json().await.unwrap();
+    assert_eq!(2, response_body.total);
+
+    let response = app
+        .get_admin_subscribers(&[("email", "ursula"), ("status", "pending_confirmation")])
+        .await;
+    let response_body: SubscriptionListAPI = response.json().await.unwrap();
+    assert_eq!(1, response_body.total);
+    assert_eq!("ursula@example.org", response_body.subscribers[0].email);
+
+    // Wildcards are matched literally
+    let response = app.get_admin_subscribers(&[("email", "%")]).await;
+    let response_body: SubscriptionListAPI = response.json().await.unwrap();
+    assert_eq!(0, response_body.total);
+}
+
+#[tokio::test]
+async fn subscribers_are_paginated() {
+    let app = spawn_app().await;
+    for _ in 0..3 {
+        app.create_confirmed_subscriber(None, None).await;
+    }
+    app.test_user.login(&app).await;
+
+    let response = app
+        .get_admin_subscribers(&[("page", "1"), ("per_page", "2")])
+        .await;
+    let first_page: SubscriptionListAPI = response.json().await.unwrap();
+    let response = app
+        .get_admin_subscribers(&[("page", "2"), ("per_page", "2")])
+        .await;
+    let second_page: SubscriptionListAPI = response.json().await.unwrap();
+
+    assert_eq!(3, first_page.total);
+    assert_eq!(2, first_page.subscribers.len());
+    assert_eq!(1, second_page.subscribers.len());
+    assert!(
+        first_page
+            .subscribers
+            .iter()
+            .all(|s| s.id != second_page.subscribers[0].id)
+    );
+}
+
+#[tokio::test]
+async fn listing_subscribers_returns_400_for_invalid_query_parameters() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let test_cases = vec![
+        (vec![("status", "banana")], "unknown status"),
+        (vec![("page", "0")], "page is zero"),
+        (vec![("page", "9223372036854775807")], "page is too large"),
+        (vec![("per_page", "0")], "per page is zero"),
+        (vec![("per_page", "101")], "per page is too large"),
+    ];
+
+    for (query, error_message) in test_cases {
+        let response = app.get_admin_subscribers(&query).await;
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request when the {}.",
+            error_message
+        );
+    }
+}
@@ -0,0 +1,5 @@
+mod counts;
+mod detail;
+mod export;
+mod import;
+mod index;
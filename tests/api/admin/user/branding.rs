@@ -0,0 +1,183 @@
+use crate::helpers::{TestApp, spawn_app};
+use newsletter_api::clients::cloudinary_client::fixtures::mock_cloudinary_upload_response;
+use newsletter_api::models::{EmailBrandingAPI, NewsletterIssueAPI};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+fn branding() -> serde_json::Value {
+    serde_json::json!({
+        "accent_color": "#1D4ED8",
+        "footer_text": "Thanks for reading Earthsea Dispatches.",
+        "postal_address": "1 Library Lane, Portland, OR 97201",
+        "publication_name": "Earthsea Dispatches",
+        "reply_to_email": "ursula@example.com",
+    })
+}
+
+/// Publishes an issue, as the logged in test user, to a single confirmed
+/// subscriber and returns the request sent to Mailpit.
+async fn deliver_an_issue(app: &TestApp) -> serde_json::Value {
+    app.create_confirmed_subscriber(None, None).await;
+    app.post_admin_create_newsletter(&serde_json::json!({
+      "title": "Newsletter title",
+      "description": "Newsletter description",
+      "content": "Newsletter body",
+      "cover_image": "",
+    }))
+    .await;
+    let response = app.get_admin_unpublished_newsletter_issues().await;
+    let response_body: Vec<NewsletterIssueAPI> = response.json().await.unwrap();
+    let newsletter_issue_id = response_body[0].newsletter_issue_id;
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    serde_json::from_slice(&email_requests.last().unwrap().body).unwrap()
+}
+
+#[tokio::test]
+async fn unauthenticated_user_cannot_update_branding() {
+    let app = spawn_app().await;
+
+    let response = app.put_admin_update_user_branding(&branding()).await;
+
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn branding_is_empty_by_default_and_can_be_updated() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app.get_admin_user_branding().await;
+    let response_body: EmailBrandingAPI = response.json().await.unwrap();
+    assert_eq!("", response_body.publication_name);
+    assert_eq!("", response_body.reply_to_email);
+
+    let response = app.put_admin_update_user_branding(&branding()).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let response = app.get_admin_user_branding().await;
+    let response_body: EmailBrandingAPI = response.json().await.unwrap();
+    assert_eq!("#1d4ed8", response_body.accent_color);
+    assert_eq!(
+        "Thanks for reading Earthsea Dispatches.",
+        response_body.footer_text
+    );
+    assert_eq!(
+        "1 Library Lane, Portland, OR 97201",
+        response_body.postal_address
+    );
+    assert_eq!("Earthsea Dispatches", response_body.publication_name);
+    assert_eq!("ursula@example.com", response_body.reply_to_email);
+}
+
+#[tokio::test]
+async fn invalid_branding_is_rejected() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let test_cases = vec![
+        ("accent_color", "red", "not a hex color"),
+        (
+            "publication_name",
+            "Earthsea <Dispatches>",
+            "illegal characters",
+        ),
+        (
+            "reply_to_email",
+            "ursula.example.com",
+            "invalid reply-to address",
+        ),
+    ];
+
+    for (field, value, error_message) in test_cases {
+        let mut body = branding();
+        body[field] = serde_json::json!(value);
+
+        let response = app.put_admin_update_user_branding(&body).await;
+
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request when the payload had an {}.",
+            error_message
+        );
+    }
+}
+
+#[tokio::test]
+async fn authenticated_user_can_update_the_email_logo() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let mock_response = mock_cloudinary_upload_response(&app.cloudinary_server.uri());
+
+    Mock::given(path(format!(
+        "/v1_1/{}/image/upload",
+        &app.cloudinary_client.bucket
+    )))
+    .and(method("POST"))
+    .respond_with(ResponseTemplate::new(200).set_body_json(mock_response))
+    .expect(1)
+    .mount(&app.cloudinary_server)
+    .await;
+
+    let response = app
+        .put_admin_update_user_branding_logo(&serde_json::json!({
+          "image": "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAUAAAAFCAYAAACNbyblAAAAHElEQVQI12P4//8/w38GIAXDIBKE0DHxgljNBAAO9TXL0Y4OHwAAAABJRU5ErkJggg==",
+        }))
+        .await;
+
+    assert_eq!(200, response.status().as_u16());
+
+    let response = app.get_admin_user_branding().await;
+    let response_body: EmailBrandingAPI = response.json().await.unwrap();
+
+    assert!(response_body.logo_url.contains(&format!(
+        "/images/user/email_logo/{}.png",
+        app.test_user.user_id
+    )));
+}
+
+#[tokio::test]
+async fn issues_are_sent_in_the_author_branding() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.put_admin_update_user_branding(&branding()).await;
+
+    // Act
+    let body = deliver_an_issue(&app).await;
+
+    // Assert
+    assert_eq!("Earthsea Dispatches", body["From"]["Name"]);
+    assert_eq!("ursula@example.com", body["ReplyTo"][0]["Email"]);
+    let html = body["Html"].as_str().unwrap();
+    assert!(html.contains("Earthsea Dispatches"));
+    assert!(html.contains("#1d4ed8"));
+    let text = body["Text"].as_str().unwrap();
+    for content in [html, text] {
+        assert!(content.contains("Thanks for reading Earthsea Dispatches."));
+        assert!(content.contains("1 Library Lane, Portland, OR 97201"));
+    }
+}
+
+#[tokio::test]
+async fn issues_are_sent_under_the_author_name_until_branding_is_set() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let body = deliver_an_issue(&app).await;
+
+    // Assert
+    assert_eq!(app.test_user.username.as_str(), body["From"]["Name"]);
+    assert!(body.get("ReplyTo").is_none());
+}
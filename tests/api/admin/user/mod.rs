@@ -1,3 +1,5 @@
 mod avatar;
 mod banner;
+mod branding;
 mod index;
+mod tracking;
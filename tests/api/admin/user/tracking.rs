@@ -0,0 +1,83 @@
+use crate::helpers::spawn_app;
+use newsletter_api::models::{NewsletterIssueAPI, UserProfileAPI};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn unauthenticated_user_cannot_update_tracking() {
+    let app = spawn_app().await;
+
+    let response = app
+        .put_admin_update_user_tracking(&serde_json::json!({ "enabled": false }))
+        .await;
+
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn tracking_is_disabled_by_default_and_can_be_turned_on_and_off() {
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app.get_admin_user().await;
+    let response_body: UserProfileAPI = response.json().await.unwrap();
+    assert!(!response_body.email_tracking_enabled);
+
+    let response = app
+        .put_admin_update_user_tracking(&serde_json::json!({ "enabled": true }))
+        .await;
+    assert_eq!(200, response.status().as_u16());
+
+    let response = app.get_admin_user().await;
+    let response_body: UserProfileAPI = response.json().await.unwrap();
+    assert!(response_body.email_tracking_enabled);
+
+    let response = app
+        .put_admin_update_user_tracking(&serde_json::json!({ "enabled": false }))
+        .await;
+    assert_eq!(200, response.status().as_u16());
+
+    let response = app.get_admin_user().await;
+    let response_body: UserProfileAPI = response.json().await.unwrap();
+    assert!(!response_body.email_tracking_enabled);
+}
+
+#[tokio::test]
+async fn emails_are_not_tracked_once_tracking_is_turned_off() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, None).await;
+    app.test_user.login(&app).await;
+    app.put_admin_update_user_tracking(&serde_json::json!({ "enabled": true }))
+        .await;
+    app.put_admin_update_user_tracking(&serde_json::json!({ "enabled": false }))
+        .await;
+    app.post_admin_create_newsletter(&serde_json::json!({
+      "title": "Newsletter title",
+      "description": "Newsletter description",
+      "content": "Read [the post](https://example.com/post).",
+      "cover_image": "",
+    }))
+    .await;
+    let response = app.get_admin_unpublished_newsletter_issues().await;
+    let response_body: Vec<NewsletterIssueAPI> = response.json().await.unwrap();
+    let newsletter_issue_id = response_body[0].newsletter_issue_id;
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value =
+        serde_json::from_slice(&email_requests.last().unwrap().body).unwrap();
+    let html = body["Html"].as_str().unwrap();
+    assert!(html.contains(r#"href="https://example.com/post""#));
+    assert!(!html.contains("/tracking/"));
+}
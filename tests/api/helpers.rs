@@ -3,15 +3,17 @@ use fake::faker::internet::en::SafeEmail;
 use fake::faker::name::en::Name;
 use newsletter_api::challenge::Base64Challenger;
 use newsletter_api::clients::cloudinary_client::CloudinaryClient;
-use newsletter_api::configuration::{DatabaseSettings, get_configuration};
+use newsletter_api::configuration::{DatabaseSettings, Settings, get_configuration};
 use newsletter_api::email_client::{EmailClient, EmailServer};
-use newsletter_api::issue_delivery_worker::{ExecutionOutcome, try_execute_task};
-use newsletter_api::models::{NewUser, NewUserData, UserProfile};
+use newsletter_api::issue_delivery_worker::{DeliveryContext, ExecutionOutcome, try_execute_task};
+use newsletter_api::issue_scheduler::{SchedulerOutcome, try_publish_scheduled_issue};
+use newsletter_api::models::{NewUser, NewUserData, NewsletterIssueAPI, UserProfile};
 use newsletter_api::startup::{Application, get_connection_pool};
 use newsletter_api::telemetry::{get_subscriber, init_subscriber};
 use secrecy::SecretString;
 use sqlx::{Connection, Executor, PgConnection, PgPool};
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 use wiremock::matchers::{method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
@@ -38,8 +40,10 @@ pub struct TestApp {
     pub api_client: reqwest::Client,
     pub cloudinary_client: CloudinaryClient,
     pub cloudinary_server: MockServer,
-    pub email_client: EmailClient,
+    pub email_client: Arc<EmailClient>,
     pub captcha_secret: SecretString,
+    pub delivery_context: DeliveryContext,
+    pub email_webhook_token: SecretString,
 }
 
 /// Confirmation links embedded in the request to the email API.
@@ -52,7 +56,7 @@ impl TestApp {
     pub async fn dispatch_all_pending_emails(&self) {
         loop {
             if let ExecutionOutcome::EmptyQueue =
-                try_execute_task(&self.db_pool, &self.email_client)
+                try_execute_task(&self.db_pool, &self.email_client, &self.delivery_context)
                     .await
                     .unwrap()
             {
@@ -61,6 +65,16 @@ impl TestApp {
         }
     }
 
+    pub async fn publish_due_scheduled_issues(&self) {
+        loop {
+            if let SchedulerOutcome::EmptyQueue =
+                try_publish_scheduled_issue(&self.db_pool).await.unwrap()
+            {
+                break;
+            }
+        }
+    }
+
     pub async fn post_subscriptions<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -73,6 +87,32 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_subscriptions_with_language<Body>(
+        &self,
+        body: &Body,
+        accept_language: &str,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/subscriptions", &self.address))
+            .header("Accept-Language", accept_language)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_unsubscribe(&self, unsubscribe_token: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/subscriptions/unsubscribe", &self.address))
+            .query(&[("unsubscribe_token", unsubscribe_token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_login<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -121,6 +161,94 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_admin_scheduled_newsletter_issues(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/newsletters/scheduled", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_newsletter_email_preview(
+        &self,
+        newsletter_issue_id: &Uuid,
+    ) -> reqwest::Response {
+        self.api_client
+            .get(format!(
+                "{}/admin/newsletters/{}/email_preview",
+                &self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_newsletter_engagement(
+        &self,
+        newsletter_issue_id: &Uuid,
+    ) -> reqwest::Response {
+        self.api_client
+            .get(format!(
+                "{}/admin/newsletters/{}/engagement",
+                &self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_test_send_newsletter<Body>(
+        &self,
+        newsletter_issue_id: &Uuid,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/{}/test_send",
+                &self.address, newsletter_issue_id
+            ))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn put_admin_schedule_newsletter<Body>(
+        &self,
+        newsletter_issue_id: &Uuid,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .put(format!(
+                "{}/admin/newsletters/{}/schedule",
+                &self.address, newsletter_issue_id
+            ))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_admin_schedule_newsletter(
+        &self,
+        newsletter_issue_id: &Uuid,
+    ) -> reqwest::Response {
+        self.api_client
+            .delete(format!(
+                "{}/admin/newsletters/{}/schedule",
+                &self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn get_admin_newsletter_issue(
         &self,
         newsletter_issue_id: &Uuid,
@@ -204,6 +332,225 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_postmark_webhook<Body>(
+        &self,
+        body: &Body,
+        password: Option<&str>,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let mut request = self
+            .api_client
+            .post(format!("{}/webhooks/postmark", &self.address))
+            .json(body);
+
+        if let Some(password) = password {
+            request = request.basic_auth("postmark", Some(password));
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_outbox(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/outbox", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_outbox_message(&self, message_id: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/outbox/{}", &self.address, message_id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_subscribers(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_subscriber_counts(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers/counts", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_subscribers_import(
+        &self,
+        csv: &str,
+        query: &[(&str, &str)],
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/subscribers/import", &self.address))
+            .query(query)
+            .header("Content-Type", "text/csv")
+            .body(csv.to_string())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_subscribers_export(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers/export", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_subscriber(&self, subscriber_id: &Uuid) -> reqwest::Response {
+        self.api_client
+            .get(format!(
+                "{}/admin/subscribers/{}",
+                &self.address, subscriber_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_admin_subscriber(&self, subscriber_id: &Uuid) -> reqwest::Response {
+        self.api_client
+            .delete(format!(
+                "{}/admin/subscribers/{}",
+                &self.address, subscriber_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_newsletter_delivery(
+        &self,
+        newsletter_issue_id: &Uuid,
+        query: &[(&str, &str)],
+    ) -> reqwest::Response {
+        self.api_client
+            .get(format!(
+                "{}/admin/newsletters/{}/delivery",
+                &self.address, newsletter_issue_id
+            ))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_pause_newsletter_delivery(
+        &self,
+        newsletter_issue_id: &Uuid,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/{}/delivery/pause",
+                &self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_resume_newsletter_delivery(
+        &self,
+        newsletter_issue_id: &Uuid,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/{}/delivery/resume",
+                &self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_cancel_newsletter_delivery(
+        &self,
+        newsletter_issue_id: &Uuid,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/{}/delivery/cancel",
+                &self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_newsletter_failures(
+        &self,
+        newsletter_issue_id: &Uuid,
+    ) -> reqwest::Response {
+        self.api_client
+            .get(format!(
+                "{}/admin/newsletters/{}/failures",
+                &self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_requeue_newsletter_failures(
+        &self,
+        newsletter_issue_id: &Uuid,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/{}/failures/requeue",
+                &self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Create a draft with valid content and return its id.
+    pub async fn create_draft_newsletter_issue(&self) -> Uuid {
+        // Titles are slugged, so they must be unique per author.
+        let title = format!("Newsletter title {}", Uuid::new_v4());
+        let response = self
+            .post_admin_create_newsletter(&serde_json::json!({
+              "title": title,
+              "description": "Newsletter description",
+              "content": "## Newsletter body as markdown",
+              "cover_image": "",
+            }))
+            .await;
+        assert_eq!(201, response.status().as_u16());
+
+        let response = self.get_admin_unpublished_newsletter_issues().await;
+        let response_body: Vec<NewsletterIssueAPI> = response.json().await.unwrap();
+        response_body
+            .into_iter()
+            .find(|issue| issue.title == title)
+            .unwrap()
+            .newsletter_issue_id
+    }
+
+    pub async fn publish_newsletter_issue(&self, newsletter_issue_id: &Uuid) {
+        let response = self
+            .put_admin_publish_newsletter(
+                newsletter_issue_id,
+                &serde_json::json!({
+                  "idempotency_key": Uuid::new_v4().to_string()
+                }),
+            )
+            .await;
+        assert_eq!(200, response.status().as_u16());
+    }
+
     pub async fn get_admin_user(&self) -> reqwest::Response {
         self.api_client
             .get(&format!("{}/admin/user", &self.address))
@@ -224,6 +571,18 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn put_admin_update_user_tracking<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .put(format!("{}/admin/user/tracking", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn put_admin_update_user_profile_avatar<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -236,6 +595,38 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_admin_user_branding(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/user/branding", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn put_admin_update_user_branding<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .put(format!("{}/admin/user/branding", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn put_admin_update_user_branding_logo<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .put(format!("{}/admin/user/branding/logo", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn put_admin_update_user_profile_banner<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -380,13 +771,19 @@ impl TestApp {
             confirmation_link
         };
 
-        let html = match self.email_client.server {
+        let html = match self.email_client.server() {
             EmailServer::Mailpit => get_link(body["Html"].as_str().unwrap()),
             EmailServer::Postmark => get_link(body["HtmlBody"].as_str().unwrap()),
+            EmailServer::Smtp | EmailServer::Outbox => {
+                unreachable!("Only emails sent through an API reach the mock server.")
+            }
         };
-        let plain_text = match self.email_client.server {
+        let plain_text = match self.email_client.server() {
             EmailServer::Mailpit => get_link(body["Text"].as_str().unwrap()),
             EmailServer::Postmark => get_link(body["TextBody"].as_str().unwrap()),
+            EmailServer::Smtp | EmailServer::Outbox => {
+                unreachable!("Only emails sent through an API reach the mock server.")
+            }
         };
 
         ConfirmationLinks { html, plain_text }
@@ -405,6 +802,22 @@ impl TestApp {
 }
 
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Writes emails to a fresh outbox directory instead of the mock server.
+pub async fn spawn_app_with_outbox() -> TestApp {
+    spawn_app_with(|c| {
+        c.email_client.server = EmailServer::Outbox;
+        c.email_client.outbox_directory = std::env::temp_dir()
+            .join(format!("outbox-{}", Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned();
+    })
+    .await
+}
+
+async fn spawn_app_with(customise: impl FnOnce(&mut Settings)) -> TestApp {
     LazyLock::force(&TRACING);
 
     // Launch a mock server to stand in for Postmark's API
@@ -425,6 +838,7 @@ pub async fn spawn_app() -> TestApp {
         // Use the mock server as email API
         c.email_client.base_url = email_server.uri();
         c.cloudinary_client.base_url = cloudinary_server.uri();
+        customise(&mut c);
         c
     };
 
@@ -436,7 +850,7 @@ pub async fn spawn_app() -> TestApp {
         .await
         .expect("Failed to build application.");
     let application_port = application.port();
-    let _ = tokio::spawn(application.run_until_stopped());
+    let _ = tokio::spawn(application.run_until_stopped(CancellationToken::new()));
 
     let client = reqwest::Client::builder()
         .redirect(reqwest::redirect::Policy::none())
@@ -448,6 +862,8 @@ pub async fn spawn_app() -> TestApp {
     let test_user = TestUser::create(&db_pool)
         .await
         .expect("Failed to create test user.");
+    let delivery_context = DeliveryContext::new(&configuration);
+    let email_webhook_token = configuration.email_client.webhook_token.clone();
     let test_app = TestApp {
         address: format!("http://localhost:{}", application_port),
         port: application_port,
@@ -457,8 +873,15 @@ pub async fn spawn_app() -> TestApp {
         email_server,
         test_user,
         api_client: client,
-        email_client: configuration.email_client.client(),
+        email_client: Arc::new(
+            configuration
+                .email_client
+                .client()
+                .expect("Failed to build the email client."),
+        ),
         captcha_secret: configuration.application.captcha_secret,
+        delivery_context,
+        email_webhook_token,
     };
 
     test_app
@@ -0,0 +1,416 @@
+use crate::helpers::{TestApp, TestUser, spawn_app};
+use newsletter_api::configuration::{WorkerSettings, get_configuration};
+use newsletter_api::email_client::EmailClient;
+use newsletter_api::issue_delivery_worker::{ExecutionOutcome, run_workers, try_execute_task};
+use newsletter_api::rate_limiter::RateLimiter;
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+fn spawn_workers(app: &TestApp, shutdown: &CancellationToken) -> JoinHandle<()> {
+    let settings = WorkerSettings {
+        concurrency: NonZeroUsize::new(2).unwrap(),
+        poll_interval_milliseconds: 10_000,
+        error_backoff_milliseconds: 1_000,
+        lease_seconds: 300,
+        author_emails_per_minute: NonZeroU32::new(1_000).unwrap(),
+    };
+    let workers = run_workers(
+        app.db_pool.clone(),
+        app.email_client.clone(),
+        app.delivery_context.clone(),
+        settings,
+        shutdown.clone(),
+    );
+    tokio::spawn(async move { workers.await.unwrap() })
+}
+
+async fn queued_tasks(app: &TestApp) -> i64 {
+    sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count
+}
+
+/// Publish an issue to a single confirmed subscriber and wait, for much less
+/// than the poll interval, for the workers to deliver it.
+async fn assert_published_issue_is_delivered_promptly(app: &TestApp) {
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+
+    for _ in 0..30 {
+        if queued_tasks(app).await == 0 {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("The workers were not woken up by the newly queued issue.");
+}
+
+#[tokio::test]
+async fn idle_workers_are_woken_up_when_an_issue_is_queued() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, None).await;
+    app.test_user.login(&app).await;
+    let shutdown = CancellationToken::new();
+    let _workers = spawn_workers(&app, &shutdown);
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    // Act & Assert
+    assert_published_issue_is_delivered_promptly(&app).await;
+    shutdown.cancel();
+}
+
+#[tokio::test]
+async fn workers_keep_listening_after_their_connection_drops() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, None).await;
+    app.test_user.login(&app).await;
+    let shutdown = CancellationToken::new();
+    let _workers = spawn_workers(&app, &shutdown);
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    // Act - Part 1 - Kill the listening connection
+    let terminated = sqlx::query!(
+        r#"
+        SELECT pg_terminate_backend(pid) AS "terminated!"
+        FROM pg_stat_activity
+        WHERE datname = current_database() AND query ILIKE 'LISTEN%'
+        "#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(1, terminated.len());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    // Act & Assert - Part 2
+    assert_published_issue_is_delivered_promptly(&app).await;
+    shutdown.cancel();
+}
+
+#[tokio::test]
+async fn idle_workers_stop_as_soon_as_shutdown_is_requested() {
+    // Arrange
+    let app = spawn_app().await;
+    let shutdown = CancellationToken::new();
+    let workers = spawn_workers(&app, &shutdown);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // Act
+    shutdown.cancel();
+
+    // Assert
+    tokio::time::timeout(Duration::from_secs(2), workers)
+        .await
+        .expect("Workers did not stop while sleeping.")
+        .unwrap();
+}
+
+#[tokio::test]
+async fn in_flight_deliveries_are_committed_before_workers_stop() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(None, None).await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(500)))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let shutdown = CancellationToken::new();
+    let workers = spawn_workers(&app, &shutdown);
+
+    // Act - Shut down while the email is being sent
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), workers)
+        .await
+        .expect("Workers did not stop after finishing their batch.")
+        .unwrap();
+
+    // Assert
+    assert_eq!(0, queued_tasks(&app).await);
+    let sent = sqlx::query!(
+        "SELECT COUNT(*) AS \"count!\" FROM issue_delivery_log WHERE outcome = 'sent'"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .count;
+    assert_eq!(1, sent);
+}
+
+async fn publish_to_one_subscriber(app: &TestApp) {
+    app.create_confirmed_subscriber(None, None).await;
+    app.test_user.login(app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+}
+
+#[tokio::test]
+async fn claimed_tasks_are_not_locked_while_emails_are_sent() {
+    // Arrange
+    let app = spawn_app().await;
+    publish_to_one_subscriber(&app).await;
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(1_000)))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Start sending
+    let db_pool = app.db_pool.clone();
+    let email_client = app.email_client.clone();
+    let delivery_context = app.delivery_context.clone();
+    let in_flight = tokio::spawn(async move {
+        try_execute_task(&db_pool, &email_client, &delivery_context)
+            .await
+            .unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    // Assert - Part 1 - The task is leased, but its row is not locked
+    let mut transaction = app.db_pool.begin().await.unwrap();
+    let task = sqlx::query!(
+        r#"
+        SELECT locked_by, locked_until > now() AS "is_leased!"
+        FROM issue_delivery_queue
+        FOR UPDATE NOWAIT
+        "#
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .expect("The queued task should not be locked while it is being sent.");
+    transaction.rollback().await.unwrap();
+    assert!(task.locked_by.is_some());
+    assert!(task.is_leased);
+    let outcome = try_execute_task(&app.db_pool, &app.email_client, &app.delivery_context)
+        .await
+        .unwrap();
+    assert!(matches!(outcome, ExecutionOutcome::EmptyQueue));
+
+    // Assert - Part 2 - The task is acknowledged once sent
+    in_flight.await.unwrap();
+    assert_eq!(0, queued_tasks(&app).await);
+}
+
+#[tokio::test]
+async fn expired_leases_are_reclaimed() {
+    // Arrange
+    let app = spawn_app().await;
+    publish_to_one_subscriber(&app).await;
+    sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue
+        SET locked_until = now() - interval '1 minute', locked_by = gen_random_uuid()
+        "#
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(0, queued_tasks(&app).await);
+}
+
+#[tokio::test]
+async fn active_leases_are_not_reclaimed() {
+    // Arrange
+    let app = spawn_app().await;
+    publish_to_one_subscriber(&app).await;
+    sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue
+        SET locked_until = now() + interval '1 hour', locked_by = gen_random_uuid()
+        "#
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let outcome = try_execute_task(&app.db_pool, &app.email_client, &app.delivery_context)
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(matches!(outcome, ExecutionOutcome::EmptyQueue));
+    assert_eq!(1, queued_tasks(&app).await);
+}
+
+/// Publish an issue from `author` to `n_subscribers` new confirmed subscribers.
+async fn publish_as(app: &TestApp, author: &TestUser, n_subscribers: usize) -> Uuid {
+    for _ in 0..n_subscribers {
+        app.create_confirmed_subscriber(Some(author.username.clone()), None)
+            .await;
+    }
+    author.login(app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+    newsletter_issue_id
+}
+
+async fn queued_tasks_for(app: &TestApp, newsletter_issue_id: &Uuid) -> i64 {
+    sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM issue_delivery_queue
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .count
+}
+
+#[tokio::test]
+async fn the_least_recently_served_author_is_served_first() {
+    // Arrange
+    let app = spawn_app().await;
+    let other_author = TestUser::create(&app.db_pool).await.unwrap();
+    publish_as(&app, &app.test_user, 1).await;
+    let guard = Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+    drop(guard);
+    let busy_issue_id = publish_as(&app, &app.test_user, 1).await;
+    let other_issue_id = publish_as(&app, &other_author, 1).await;
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    try_execute_task(&app.db_pool, &app.email_client, &app.delivery_context)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(0, queued_tasks_for(&app, &other_issue_id).await);
+    assert_eq!(2, queued_tasks_for(&app, &busy_issue_id).await);
+}
+
+#[tokio::test]
+async fn authors_cannot_send_more_than_their_rate_limit() {
+    // Arrange
+    let app = spawn_app().await;
+    let other_author = TestUser::create(&app.db_pool).await.unwrap();
+    let large_issue_id = publish_as(&app, &app.test_user, 4).await;
+    let small_issue_id = publish_as(&app, &other_author, 1).await;
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+    let mut delivery_context = app.delivery_context.clone();
+    delivery_context.author_emails_per_minute = 2;
+
+    // Act
+    while let ExecutionOutcome::TaskCompleted =
+        try_execute_task(&app.db_pool, &app.email_client, &delivery_context)
+            .await
+            .unwrap()
+    {}
+
+    // Assert
+    assert_eq!(2, queued_tasks_for(&app, &large_issue_id).await);
+    assert_eq!(0, queued_tasks_for(&app, &small_issue_id).await);
+}
+
+/// An email client sending through the app's mock server, limited to
+/// `sends_per_second` by a token bucket of its own.
+fn rate_limited_email_client(app: &TestApp, sends_per_second: u32) -> EmailClient {
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.email_client.base_url = app.email_server.uri();
+    let rate_limiter = RateLimiter::new(
+        &configuration.redis_uri,
+        format!("email_rate_limit:{}", Uuid::new_v4()),
+        sends_per_second,
+        None,
+    )
+    .unwrap();
+    configuration
+        .email_client
+        .client()
+        .unwrap()
+        .with_rate_limiter(rate_limiter)
+}
+
+#[tokio::test]
+async fn emails_beyond_the_global_rate_limit_are_put_back_in_the_queue() {
+    // Arrange
+    let app = spawn_app().await;
+    let newsletter_issue_id = publish_as(&app, &app.test_user, 3).await;
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+    let email_client = rate_limited_email_client(&app, 2);
+
+    // Act - Part 1 - Use up the whole burst
+    let outcome = try_execute_task(&app.db_pool, &email_client, &app.delivery_context)
+        .await
+        .unwrap();
+    assert!(matches!(outcome, ExecutionOutcome::TaskCompleted));
+
+    // Act - Part 2
+    let outcome = try_execute_task(&app.db_pool, &email_client, &app.delivery_context)
+        .await
+        .unwrap();
+
+    // Assert
+    let ExecutionOutcome::RateLimited { retry_after } = outcome else {
+        panic!("The worker should have been told to slow down.");
+    };
+    assert!(retry_after <= Duration::from_secs(1));
+    let task = sqlx::query!(
+        r#"
+        SELECT n_retries, locked_by, execute_after > now() AS "is_deferred!"
+        FROM issue_delivery_queue
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("One email should still be queued.");
+    assert_eq!(0, task.n_retries);
+    assert!(task.locked_by.is_none());
+    assert!(task.is_deferred);
+}
@@ -3,8 +3,12 @@ mod admin_dashboard;
 mod change_password;
 mod health_check;
 mod helpers;
+mod issue_delivery_worker;
 mod login;
 mod newsletters;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_unsubscribe;
+mod tracking;
 mod users;
+mod webhooks_postmark;
@@ -103,3 +103,39 @@ async fn public_newsletter_includes_associated_user() {
     assert_eq!(&response_body.user.display_name, "Display name");
     assert_eq!(&response_body.user.username, &app.test_user.username);
 }
+
+#[tokio::test]
+async fn merge_tags_are_not_resolved_on_the_public_newsletter() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.post_admin_create_newsletter(&serde_json::json!({
+      "title": "Hello {{ subscriber.name }}",
+      "description": "Newsletter description",
+      "content": "Sent to {{ subscriber.email }}",
+      "cover_image": "",
+    }))
+    .await;
+    let response = app.get_admin_unpublished_newsletter_issues().await;
+    let response_body: Vec<NewsletterIssueAPI> = response.json().await.unwrap();
+    let newsletter_issue_id = response_body[0].newsletter_issue_id;
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+    app.post_logout().await;
+
+    // Act
+    let response = app
+        .get_public_newsletter(
+            &app.test_user.username,
+            &"hello-subscriber-name".to_string(),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let response_body: PublicNewsletter = response.json().await.unwrap();
+    assert_eq!(&response_body.title, "Hello {{ subscriber.name }}");
+    assert_eq!(
+        &response_body.content,
+        "<p>Sent to {{ subscriber.email }}</p>"
+    );
+}
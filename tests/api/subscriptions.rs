@@ -296,3 +296,274 @@ async fn subscribe_returns_a_400_with_json_message_when_fields_are_present_but_i
         assert_ok!(response_body);
     }
 }
+
+#[tokio::test]
+async fn subscribing_again_while_pending_resends_a_fresh_confirmation_link() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = String::from("ursula_le_guin@gmail.com");
+    let first_links = app
+        .create_unconfirmed_subscriber(None, Some(email.clone()))
+        .await;
+
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let (answer, challenge) = app.get_solved_captcha_challenge();
+    let response = app
+        .post_subscriptions(&serde_json::json!({
+            "name": "le guin",
+            "email": email,
+            "username": &app.test_user.username,
+            "signed_answer": challenge,
+            "answer_attempt": answer
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let email_request = app.email_server.received_requests().await.unwrap();
+    let second_links = app.get_confirmation_links(email_request.last().unwrap());
+    assert_ne!(first_links.html, second_links.html);
+
+    let response = app.api_client.put(second_links.html).send().await.unwrap();
+    assert_eq!(200, response.status().as_u16());
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(1, saved.len());
+    assert_eq!("confirmed", saved[0].status);
+}
+
+#[tokio::test]
+async fn subscribing_again_once_confirmed_is_silent() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = String::from("ursula_le_guin@gmail.com");
+    app.create_confirmed_subscriber(None, Some(email.clone()))
+        .await;
+
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let (answer, challenge) = app.get_solved_captcha_challenge();
+    let response = app
+        .post_subscriptions(&serde_json::json!({
+            "name": "le guin",
+            "email": email,
+            "username": &app.test_user.username,
+            "signed_answer": challenge,
+            "answer_attempt": answer
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!("confirmed", saved.status);
+}
+
+#[tokio::test]
+async fn subscribing_again_after_unsubscribing_requires_a_new_confirmation() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = String::from("ursula_le_guin@gmail.com");
+    app.create_confirmed_subscriber(None, Some(email.clone()))
+        .await;
+    sqlx::query!("UPDATE subscriptions SET status = 'unsubscribed'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let (answer, challenge) = app.get_solved_captcha_challenge();
+    let response = app
+        .post_subscriptions(&serde_json::json!({
+            "name": "le guin",
+            "email": email,
+            "username": &app.test_user.username,
+            "signed_answer": challenge,
+            "answer_attempt": answer
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!("pending_confirmation", saved.status);
+}
+
+#[tokio::test]
+async fn the_confirmation_email_is_written_in_english_by_default() {
+    // Arrange
+    let app = spawn_app().await;
+    let (answer, challenge) = app.get_solved_captcha_challenge();
+
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscriptions(&serde_json::json!({
+        "name": "le guin",
+        "email": "ursula_le_guin@gmail.com",
+        "username": &app.test_user.username,
+        "signed_answer": challenge,
+        "answer_attempt": answer
+    }))
+    .await
+    .error_for_status()
+    .unwrap();
+
+    // Assert
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(
+        format!("Confirm your subscription to {}", app.test_user.username),
+        body["Subject"]
+    );
+    assert!(body["Text"].as_str().unwrap().starts_with("Hi le guin,"));
+    assert!(
+        body["Html"]
+            .as_str()
+            .unwrap()
+            .contains(r#"<html lang="en">"#)
+    );
+    let saved = sqlx::query!("SELECT locale FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!("en", saved.locale);
+}
+
+#[tokio::test]
+async fn the_confirmation_email_is_written_in_the_preferred_language() {
+    // Arrange
+    let app = spawn_app().await;
+    let (answer, challenge) = app.get_solved_captcha_challenge();
+
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscriptions_with_language(
+        &serde_json::json!({
+            "name": "le guin",
+            "email": "ursula_le_guin@gmail.com",
+            "username": &app.test_user.username,
+            "signed_answer": challenge,
+            "answer_attempt": answer
+        }),
+        "de-DE, fr-CA;q=0.9, en;q=0.8",
+    )
+    .await
+    .error_for_status()
+    .unwrap();
+
+    // Assert
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(
+        format!("Confirmez votre abonnement à {}", app.test_user.username),
+        body["Subject"]
+    );
+    assert!(
+        body["Text"]
+            .as_str()
+            .unwrap()
+            .starts_with("Bonjour le guin,")
+    );
+    assert!(
+        body["Html"]
+            .as_str()
+            .unwrap()
+            .contains(r#"<html lang="fr">"#)
+    );
+    let confirmation_links = app.get_confirmation_links(email_request);
+    assert_eq!(confirmation_links.html, confirmation_links.plain_text);
+    let saved = sqlx::query!("SELECT locale FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!("fr", saved.locale);
+}
+
+#[tokio::test]
+async fn the_confirmation_email_presents_the_author() {
+    // Arrange
+    let app = spawn_app().await;
+    let (answer, challenge) = app.get_solved_captcha_challenge();
+    sqlx::query!(
+        r#"
+          UPDATE user_profiles
+          SET display_name = 'Ursula K. Le Guin',
+            avatar_url = 'https://cdn.test/images/user/avatar/1.webp'
+          WHERE user_id = $1
+        "#,
+        app.test_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscriptions(&serde_json::json!({
+        "name": "le guin",
+        "email": "ursula_le_guin@gmail.com",
+        "username": &app.test_user.username,
+        "signed_answer": challenge,
+        "answer_attempt": answer
+    }))
+    .await
+    .error_for_status()
+    .unwrap();
+
+    // Assert
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(
+        "Confirm your subscription to Ursula K. Le Guin",
+        body["Subject"]
+    );
+    let html = body["Html"].as_str().unwrap();
+    assert!(html.contains(r#"src="https://cdn.test/images/user/avatar/1.webp""#));
+    assert!(html.contains("Welcome to Ursula K. Le Guin's newsletter!"));
+}
@@ -0,0 +1,195 @@
+use crate::helpers::{TestApp, spawn_app};
+use newsletter_api::domain::UnsubscribeToken;
+use uuid::Uuid;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn unsubscribe_token_for(app: &TestApp, email: &str) -> String {
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions WHERE email = $1", email)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.")
+        .id;
+
+    UnsubscribeToken::generate(&subscriber_id, &app.delivery_context.hmac_secret)
+        .as_ref()
+        .to_string()
+}
+
+async fn subscription_status(app: &TestApp, email: &str) -> String {
+    sqlx::query!("SELECT status FROM subscriptions WHERE email = $1", email)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.")
+        .status
+}
+
+#[tokio::test]
+async fn unsubscribe_requests_without_token_are_rejected_with_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions/unsubscribe", app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn unsubscribe_requests_with_a_forged_token_are_rejected_with_a_401() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = "ursula_le_guin@gmail.com";
+    app.create_confirmed_subscriber(None, Some(email.into()))
+        .await;
+    let token = unsubscribe_token_for(&app, email).await;
+    let (_, signature) = token.split_once('.').unwrap();
+    let forged_token = format!("{}.{}", Uuid::new_v4().simple(), signature);
+
+    // Act
+    let response = app.post_unsubscribe(&forged_token).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(subscription_status(&app, email).await, "confirmed");
+}
+
+#[tokio::test]
+async fn a_valid_unsubscribe_token_unsubscribes_the_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = "ursula_le_guin@gmail.com";
+    app.create_confirmed_subscriber(None, Some(email.into()))
+        .await;
+    let token = unsubscribe_token_for(&app, email).await;
+
+    // Act
+    let response = app.post_unsubscribe(&token).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(subscription_status(&app, email).await, "unsubscribed");
+}
+
+#[tokio::test]
+async fn unsubscribing_keeps_the_status_of_bounced_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = "ursula_le_guin@gmail.com";
+    app.create_confirmed_subscriber(None, Some(email.into()))
+        .await;
+    sqlx::query!(
+        "UPDATE subscriptions SET status = 'bounced' WHERE email = $1",
+        email
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    let token = unsubscribe_token_for(&app, email).await;
+
+    // Act
+    let response = app.post_unsubscribe(&token).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(subscription_status(&app, email).await, "bounced");
+}
+
+#[tokio::test]
+async fn issue_emails_advertise_a_working_one_click_unsubscribe_link() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = "ursula_le_guin@gmail.com";
+    app.create_confirmed_subscriber(None, Some(email.into()))
+        .await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(
+        body["Headers"]["List-Unsubscribe-Post"],
+        "List-Unsubscribe=One-Click"
+    );
+    let list_unsubscribe = body["Headers"]["List-Unsubscribe"].as_str().unwrap();
+    let mut one_click_url = reqwest::Url::parse(
+        list_unsubscribe
+            .trim_start_matches('<')
+            .trim_end_matches('>'),
+    )
+    .unwrap();
+    assert_eq!(one_click_url.host_str().unwrap(), "127.0.0.1");
+    one_click_url.set_port(Some(app.port)).unwrap();
+    assert!(body["Html"].as_str().unwrap().contains("Unsubscribe"));
+
+    // Act - Mail clients post a fixed form body to the advertised URL
+    let response = app
+        .api_client
+        .post(one_click_url)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body("List-Unsubscribe=One-Click")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(subscription_status(&app, email).await, "unsubscribed");
+}
+
+#[tokio::test]
+async fn newsletters_are_not_delivered_to_unsubscribed_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = "ursula_le_guin@gmail.com";
+    app.create_confirmed_subscriber(None, Some(email.into()))
+        .await;
+    let token = unsubscribe_token_for(&app, email).await;
+    app.post_unsubscribe(&token)
+        .await
+        .error_for_status()
+        .unwrap();
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let queued = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(queued, 0);
+    // Mock verifies on Drop that we haven't sent the newsletter email
+}
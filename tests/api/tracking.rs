@@ -0,0 +1,183 @@
+use crate::helpers::{TestApp, spawn_app};
+use newsletter_api::models::{IssueEngagementAPI, NewsletterIssueAPI};
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+/// Turn tracking on, publish an issue linking to `https://example.com/post?id=1`
+/// to a single confirmed subscriber and return its id and the delivered HTML
+/// body.
+async fn deliver_issue_with_a_link(app: &TestApp) -> (Uuid, String) {
+    app.create_confirmed_subscriber(None, None).await;
+    app.test_user.login(app).await;
+    app.put_admin_update_user_tracking(&serde_json::json!({ "enabled": true }))
+        .await;
+    app.post_admin_create_newsletter(&serde_json::json!({
+      "title": "Newsletter title",
+      "description": "Newsletter description",
+      "content": "Read [the post](https://example.com/post?id=1&ref=email).",
+      "cover_image": "",
+    }))
+    .await;
+    let response = app.get_admin_unpublished_newsletter_issues().await;
+    let response_body: Vec<NewsletterIssueAPI> = response.json().await.unwrap();
+    let newsletter_issue_id = response_body[0].newsletter_issue_id;
+    Mock::given(path("/api/v1/send"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value =
+        serde_json::from_slice(&email_requests.last().unwrap().body).unwrap();
+    (
+        newsletter_issue_id,
+        body["Html"].as_str().unwrap().to_string(),
+    )
+}
+
+/// Find the tracking link of the given kind in an HTML body.
+fn tracking_link(app: &TestApp, html: &str, kind: &str) -> reqwest::Url {
+    let html = html.replace("&amp;", "&");
+    let link = linkify::LinkFinder::new()
+        .links(&html)
+        .map(|link| link.as_str().to_owned())
+        .find(|link| link.contains(&format!("/tracking/{kind}/")))
+        .unwrap();
+    let mut link = reqwest::Url::parse(&link).unwrap();
+    assert_eq!(link.host_str().unwrap(), "127.0.0.1");
+    link.set_port(Some(app.port)).unwrap();
+    link
+}
+
+async fn engagement(app: &TestApp, newsletter_issue_id: &Uuid) -> IssueEngagementAPI {
+    let response = app
+        .get_admin_newsletter_engagement(newsletter_issue_id)
+        .await;
+    assert_eq!(200, response.status().as_u16());
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn delivered_emails_carry_tracking_links() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let (_, html) = deliver_issue_with_a_link(&app).await;
+
+    // Assert
+    assert!(!html.contains(r#"href="https://example.com/post"#));
+    tracking_link(&app, &html, "click");
+    tracking_link(&app, &html, "open");
+}
+
+#[tokio::test]
+async fn opens_are_recorded_through_the_tracking_pixel() {
+    // Arrange
+    let app = spawn_app().await;
+    let (newsletter_issue_id, html) = deliver_issue_with_a_link(&app).await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(tracking_link(&app, &html, "open"))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("image/gif", response.headers()["Content-Type"]);
+    let engagement = engagement(&app, &newsletter_issue_id).await;
+    assert_eq!(1, engagement.sent);
+    assert_eq!(1, engagement.opens);
+    assert_eq!(1, engagement.unique_opens);
+    assert_eq!(1.0, engagement.open_rate);
+    assert_eq!(0.0, engagement.click_rate);
+}
+
+#[tokio::test]
+async fn clicks_are_recorded_and_redirected_to_the_original_link() {
+    // Arrange
+    let app = spawn_app().await;
+    let (newsletter_issue_id, html) = deliver_issue_with_a_link(&app).await;
+    let link = tracking_link(&app, &html, "click");
+
+    // Act
+    for _ in 0..2 {
+        let response = app.api_client.get(link.clone()).send().await.unwrap();
+        assert_eq!(302, response.status().as_u16());
+        assert_eq!(
+            "https://example.com/post?id=1&ref=email",
+            response.headers()["Location"]
+        );
+    }
+
+    // Assert
+    let engagement = engagement(&app, &newsletter_issue_id).await;
+    assert_eq!(2, engagement.clicks);
+    assert_eq!(1, engagement.unique_clicks);
+    assert_eq!(1.0, engagement.click_rate);
+    // A click tells us the email was opened, pixel or not.
+    assert_eq!(1.0, engagement.open_rate);
+    assert_eq!(1, engagement.links.len());
+    assert_eq!(
+        "https://example.com/post?id=1&ref=email",
+        engagement.links[0].url
+    );
+    assert_eq!(2, engagement.links[0].clicks);
+}
+
+#[tokio::test]
+async fn click_links_to_another_url_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    let (newsletter_issue_id, html) = deliver_issue_with_a_link(&app).await;
+    let mut link = tracking_link(&app, &html, "click");
+    let signature = link
+        .query_pairs()
+        .find(|(key, _)| key == "signature")
+        .unwrap()
+        .1
+        .into_owned();
+    link.query_pairs_mut()
+        .clear()
+        .append_pair("url", "https://evil.example.com")
+        .append_pair("signature", &signature);
+
+    // Act
+    let response = app.api_client.get(link).send().await.unwrap();
+
+    // Assert
+    assert_eq!(400, response.status().as_u16());
+    assert_eq!(0, engagement(&app, &newsletter_issue_id).await.clicks);
+}
+
+#[tokio::test]
+async fn invalid_open_tokens_still_get_the_pixel_without_being_recorded() {
+    // Arrange
+    let app = spawn_app().await;
+    let (newsletter_issue_id, _) = deliver_issue_with_a_link(&app).await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!(
+            "{}/tracking/open/{}.{}.forged",
+            app.address,
+            newsletter_issue_id.simple(),
+            Uuid::new_v4().simple()
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(0, engagement(&app, &newsletter_issue_id).await.opens);
+}
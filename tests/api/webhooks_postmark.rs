@@ -0,0 +1,258 @@
+use crate::helpers::{TestApp, spawn_app};
+use secrecy::ExposeSecret;
+use wiremock::matchers::any;
+use wiremock::{Mock, ResponseTemplate};
+
+fn bounce_payload(email: &str, kind: &str) -> serde_json::Value {
+    serde_json::json!({
+        "RecordType": "Bounce",
+        "ID": 4323372036854775807_i64,
+        "Type": kind,
+        "TypeCode": 1,
+        "Name": "Hard bounce",
+        "MessageStream": "outbound",
+        "Description": "The server was unable to deliver your message (ex: unknown user, mailbox not found).",
+        "Email": email,
+        "From": "sender@example.com",
+        "BouncedAt": "2019-11-05T16:33:54.9070259Z",
+        "Inactive": kind == "HardBounce",
+        "CanActivate": true,
+    })
+}
+
+fn spam_complaint_payload(email: &str) -> serde_json::Value {
+    serde_json::json!({
+        "RecordType": "SpamComplaint",
+        "ID": 42,
+        "Type": "SpamComplaint",
+        "TypeCode": 512,
+        "Name": "Spam complaint",
+        "MessageStream": "outbound",
+        "Email": email,
+        "From": "sender@example.com",
+        "BouncedAt": "2019-11-05T16:33:54.9070259Z",
+        "Inactive": true,
+    })
+}
+
+async fn post_with_valid_credentials(app: &TestApp, body: &serde_json::Value) -> reqwest::Response {
+    let password = app.email_webhook_token.expose_secret().to_string();
+    app.post_postmark_webhook(body, Some(&password)).await
+}
+
+async fn subscription_status(app: &TestApp, email: &str) -> String {
+    sqlx::query!("SELECT status FROM subscriptions WHERE email = $1", email)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.")
+        .status
+}
+
+#[tokio::test]
+async fn webhooks_without_credentials_are_rejected_with_a_401() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = bounce_payload("ursula_le_guin@gmail.com", "HardBounce");
+
+    // Act
+    let missing = app.post_postmark_webhook(&body, None).await;
+    let wrong = app
+        .post_postmark_webhook(&body, Some("not-the-token"))
+        .await;
+
+    // Assert
+    assert_eq!(401, missing.status().as_u16());
+    assert_eq!(401, wrong.status().as_u16());
+    let suppressed = sqlx::query!("SELECT email FROM email_suppressions")
+        .fetch_optional(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(suppressed.is_none());
+}
+
+#[tokio::test]
+async fn the_payload_is_only_parsed_once_the_caller_is_authenticated() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = serde_json::json!("not a webhook");
+
+    // Act
+    let unauthenticated = app.post_postmark_webhook(&body, None).await;
+    let authenticated = post_with_valid_credentials(&app, &body).await;
+
+    // Assert
+    assert_eq!(401, unauthenticated.status().as_u16());
+    assert_eq!(400, authenticated.status().as_u16());
+}
+
+#[tokio::test]
+async fn hard_bounces_suppress_the_address_and_flag_the_subscription() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = "ursula_le_guin@gmail.com";
+    app.create_confirmed_subscriber(None, Some(email.into()))
+        .await;
+
+    // Act
+    let response = post_with_valid_credentials(&app, &bounce_payload(email, "HardBounce")).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let suppressed = sqlx::query!("SELECT email, reason FROM email_suppressions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(email, suppressed.email);
+    assert_eq!("bounced", suppressed.reason);
+    assert_eq!("bounced", subscription_status(&app, email).await);
+}
+
+#[tokio::test]
+async fn spam_complaints_suppress_the_address_and_flag_the_subscription() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = "ursula_le_guin@gmail.com";
+    app.create_confirmed_subscriber(None, Some(email.into()))
+        .await;
+
+    // Act
+    let response = post_with_valid_credentials(&app, &spam_complaint_payload(email)).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("complained", subscription_status(&app, email).await);
+}
+
+#[tokio::test]
+async fn soft_bounces_and_other_events_are_acknowledged_and_ignored() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = "ursula_le_guin@gmail.com";
+    app.create_confirmed_subscriber(None, Some(email.into()))
+        .await;
+    let delivery = serde_json::json!({
+        "RecordType": "Delivery",
+        "Email": email,
+        "DeliveredAt": "2019-11-05T16:33:54.9070259Z",
+    });
+
+    // Act
+    let soft_bounce = post_with_valid_credentials(&app, &bounce_payload(email, "SoftBounce")).await;
+    let delivery = post_with_valid_credentials(&app, &delivery).await;
+
+    // Assert
+    assert_eq!(200, soft_bounce.status().as_u16());
+    assert_eq!(200, delivery.status().as_u16());
+    assert_eq!("confirmed", subscription_status(&app, email).await);
+}
+
+#[tokio::test]
+async fn newsletters_are_not_delivered_to_suppressed_addresses() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = "ursula_le_guin@gmail.com";
+    app.create_confirmed_subscriber(None, Some(email.into()))
+        .await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.create_draft_newsletter_issue().await;
+    app.publish_newsletter_issue(&newsletter_issue_id).await;
+    post_with_valid_credentials(&app, &bounce_payload(email, "HardBounce"))
+        .await
+        .error_for_status()
+        .unwrap();
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+    let second_issue_id = app.create_draft_newsletter_issue().await;
+    app.publish_newsletter_issue(&second_issue_id).await;
+
+    // Assert
+    let queued = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(queued, 0);
+    // Mock verifies on Drop that we haven't sent the newsletter email
+}
+
+#[tokio::test]
+async fn suppressed_addresses_do_not_receive_confirmation_emails() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = "ursula_le_guin@gmail.com";
+    post_with_valid_credentials(&app, &spam_complaint_payload(email))
+        .await
+        .error_for_status()
+        .unwrap();
+    let (answer, challenge) = app.get_solved_captcha_challenge();
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_subscriptions(&serde_json::json!({
+            "name": "le guin",
+            "email": email,
+            "username": &app.test_user.username,
+            "signed_answer": challenge,
+            "answer_attempt": answer
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn suppressions_cannot_be_bypassed_by_a_change_of_letter_case() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = "ursula_le_guin@gmail.com";
+    app.create_confirmed_subscriber(None, Some(email.into()))
+        .await;
+
+    // Act
+    post_with_valid_credentials(
+        &app,
+        &bounce_payload("Ursula_Le_Guin@Gmail.com", "HardBounce"),
+    )
+    .await
+    .error_for_status()
+    .unwrap();
+
+    // Assert
+    let suppressed = sqlx::query!("SELECT email FROM email_suppressions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(email, suppressed.email);
+    assert_eq!("bounced", subscription_status(&app, email).await);
+
+    let (answer, challenge) = app.get_solved_captcha_challenge();
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    let response = app
+        .post_subscriptions(&serde_json::json!({
+            "name": "le guin",
+            "email": "URSULA_LE_GUIN@gmail.com",
+            "username": &app.test_user.username,
+            "signed_answer": challenge,
+            "answer_attempt": answer
+        }))
+        .await;
+    assert_eq!(200, response.status().as_u16());
+}